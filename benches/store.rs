@@ -0,0 +1,139 @@
+//! Compares the sampled-eviction `Store` with the `lru`-crate design it replaced,
+//! where every lookup had to take its shard's write lock to reorder the list.
+
+use std::hash::{BuildHasher, RandomState};
+use std::num::NonZero;
+use std::sync::RwLock;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use animus_rust::config::Config;
+use animus_rust::store::store::{Lookup, Store};
+use criterion::{BenchmarkId, Criterion, criterion_group, criterion_main};
+use lru::LruCache;
+
+const SHARDS: usize = 16;
+const KEYS: usize = 10_000;
+const OPS_PER_THREAD: u64 = 10_000;
+
+/// The previous store: one `LruCache` per shard behind a `RwLock`.
+struct LruStore {
+    shards: Vec<RwLock<LruCache<String, Vec<u8>>>>,
+    hasher: RandomState,
+}
+
+impl LruStore {
+    fn new(capacity: usize, shards: usize) -> Self {
+        let per_shard = NonZero::new(capacity.div_ceil(shards)).unwrap();
+        LruStore {
+            shards: (0..shards)
+                .map(|_| RwLock::new(LruCache::new(per_shard)))
+                .collect(),
+            hasher: RandomState::new(),
+        }
+    }
+
+    fn shard(&self, key: &str) -> &RwLock<LruCache<String, Vec<u8>>> {
+        &self.shards[self.hasher.hash_one(key) as usize % self.shards.len()]
+    }
+
+    fn len(&self, key: &str) -> Option<usize> {
+        self.shard(key).write().unwrap().get(key).map(Vec::len)
+    }
+
+    fn set(&self, key: String, val: Vec<u8>) {
+        self.shard(&key).write().unwrap().push(key, val);
+    }
+}
+
+fn keys(n: usize) -> Vec<String> {
+    (0..n).map(|i| format!("key:{i}")).collect()
+}
+
+fn filled_store(keys: &[String]) -> Store<String, Vec<u8>> {
+    let store = Store::new(SHARDS);
+    for key in keys {
+        store.lock([key]).set(key.clone(), vec![0; 64], None);
+    }
+    store
+}
+
+fn filled_lru(capacity: usize, keys: &[String]) -> LruStore {
+    let store = LruStore::new(capacity, SHARDS);
+    for key in keys {
+        store.set(key.clone(), vec![0; 64]);
+    }
+    store
+}
+
+/// Runs `op` for `OPS_PER_THREAD` random keys on each of `threads` threads and
+/// returns the wall time scaled to `iters` rounds.
+fn run_threads(threads: usize, iters: u64, keys: &[String], op: impl Fn(&str) + Sync) -> Duration {
+    let start = Instant::now();
+    for _ in 0..iters {
+        thread::scope(|s| {
+            for _ in 0..threads {
+                s.spawn(|| {
+                    for _ in 0..OPS_PER_THREAD {
+                        op(&keys[fastrand::usize(..keys.len())]);
+                    }
+                });
+            }
+        });
+    }
+    start.elapsed()
+}
+
+fn concurrent_reads(c: &mut Criterion) {
+    let keys = keys(KEYS);
+    let store = filled_store(&keys);
+    let lru = filled_lru(KEYS, &keys);
+    let mut group = c.benchmark_group("concurrent_reads");
+    for threads in [1, 4, 8] {
+        group.bench_with_input(BenchmarkId::new("sampled", threads), &threads, |b, &n| {
+            b.iter_custom(|iters| {
+                run_threads(n, iters, &keys, |key| {
+                    store.read([key]).with(key, Vec::len);
+                })
+            })
+        });
+        group.bench_with_input(BenchmarkId::new("lru", threads), &threads, |b, &n| {
+            b.iter_custom(|iters| {
+                run_threads(n, iters, &keys, |key| {
+                    lru.len(key);
+                })
+            })
+        });
+    }
+    group.finish();
+}
+
+/// Writes to twice as many keys as fit, so most inserts evict.
+fn evicting_writes(c: &mut Criterion) {
+    let keys = keys(KEYS * 2);
+    let store = filled_store(&keys[..KEYS]);
+    let lru = filled_lru(KEYS, &keys);
+    Config::set(&[
+        ("maxmemory".to_string(), store.used_memory().to_string()),
+        ("maxmemory-policy".to_string(), "allkeys-lru".to_string()),
+    ])
+    .unwrap();
+    let mut group = c.benchmark_group("evicting_writes");
+    group.bench_function("sampled", |b| {
+        b.iter(|| {
+            let key = &keys[fastrand::usize(..keys.len())];
+            store.free_memory();
+            store.lock([key]).set(key.clone(), vec![0; 64], None);
+        })
+    });
+    group.bench_function("lru", |b| {
+        b.iter(|| {
+            let key = &keys[fastrand::usize(..keys.len())];
+            lru.set(key.clone(), vec![0; 64]);
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, concurrent_reads, evicting_writes);
+criterion_main!(benches);
@@ -0,0 +1,202 @@
+use crate::commands::handler::{arg, arg_bytes, bytes_from, int_reply, parse_int, syntax_error};
+use crate::resp::resp::Resp;
+use crate::types::bitmap_type::{
+    BitOp, BitUnit, BitmapType, FieldOp, FieldType, MAX_BIT_OFFSET, Overflow,
+};
+
+fn offset_error() -> Resp {
+    Resp::error("ERR bit offset is not an integer or out of range")
+}
+
+fn parse_offset(r: &Resp) -> Result<u64, Resp> {
+    match parse_int(r) {
+        Ok(n) if (0..=MAX_BIT_OFFSET as i64).contains(&n) => Ok(n as u64),
+        _ => Err(offset_error()),
+    }
+}
+
+fn parse_unit(r: Option<&Resp>) -> Result<BitUnit, Resp> {
+    match r.and_then(Resp::as_string) {
+        None => Ok(BitUnit::Byte),
+        Some(s) if s.eq_ignore_ascii_case("BYTE") => Ok(BitUnit::Byte),
+        Some(s) if s.eq_ignore_ascii_case("BIT") => Ok(BitUnit::Bit),
+        Some(_) => Err(syntax_error()),
+    }
+}
+
+/// SETBIT key offset value
+pub fn setbit(args: Vec<Resp>) -> Resp {
+    let offset = match parse_offset(&args[1]) {
+        Ok(n) => n,
+        Err(e) => return e,
+    };
+    let bit = match arg(&args, 2).as_str() {
+        "0" => false,
+        "1" => true,
+        _ => return Resp::error("ERR bit is not an integer or out of range"),
+    };
+    int_reply(BitmapType::setbit(&arg_bytes(&args, 0), offset, bit).map(i64::from))
+}
+
+/// GETBIT key offset
+pub fn getbit(args: Vec<Resp>) -> Resp {
+    match parse_offset(&args[1]) {
+        Ok(offset) => int_reply(BitmapType::getbit(&arg_bytes(&args, 0), offset).map(i64::from)),
+        Err(e) => e,
+    }
+}
+
+/// BITCOUNT key [start end [BYTE | BIT]]
+pub fn bitcount(args: Vec<Resp>) -> Resp {
+    let range = match args.len() {
+        1 => None,
+        3 | 4 => match (
+            parse_int(&args[1]),
+            parse_int(&args[2]),
+            parse_unit(args.get(3)),
+        ) {
+            (Ok(start), Ok(end), Ok(unit)) => Some((start, end, unit)),
+            (Err(e), _, _) | (_, Err(e), _) | (_, _, Err(e)) => return e,
+        },
+        _ => return syntax_error(),
+    };
+    int_reply(BitmapType::bitcount(&arg_bytes(&args, 0), range))
+}
+
+/// BITPOS key bit [start [end [BYTE | BIT]]]
+pub fn bitpos(args: Vec<Resp>) -> Resp {
+    let bit = match arg(&args, 1).as_str() {
+        "0" => false,
+        "1" => true,
+        _ => return Resp::error("ERR The bit argument must be 1 or 0."),
+    };
+    if args.len() > 5 {
+        return syntax_error();
+    }
+    let range = match args.get(2) {
+        None => None,
+        Some(start) => {
+            let end = args.get(3).map(parse_int).transpose();
+            match (parse_int(start), end, parse_unit(args.get(4))) {
+                (Ok(start), Ok(end), Ok(unit)) => Some((start, end, unit)),
+                (Err(e), _, _) | (_, Err(e), _) | (_, _, Err(e)) => return e,
+            }
+        }
+    };
+    int_reply(BitmapType::bitpos(&arg_bytes(&args, 0), bit, range))
+}
+
+/// BITOP AND | OR | XOR | NOT | DIFF destkey key [key ...]
+pub fn bitop(args: Vec<Resp>) -> Resp {
+    let keys = bytes_from(&args, 2);
+    let op = match arg(&args, 0).to_uppercase().as_str() {
+        "AND" => BitOp::And,
+        "OR" => BitOp::Or,
+        "XOR" => BitOp::Xor,
+        "NOT" if keys.len() == 1 => BitOp::Not,
+        "NOT" => return Resp::error("ERR BITOP NOT must be called with a single source key."),
+        "DIFF" if keys.len() >= 2 => BitOp::Diff,
+        "DIFF" => {
+            return Resp::error("ERR BITOP DIFF must be called with at least two source keys.");
+        }
+        _ => return syntax_error(),
+    };
+    int_reply(BitmapType::bitop(op, &arg_bytes(&args, 1), &keys))
+}
+
+/// Parses `type offset`, where an offset prefixed with `#` is multiplied by the width.
+fn parse_field(args: &[Resp], i: usize) -> Result<(FieldType, u64), Resp> {
+    let (Some(ty), Some(offset)) = (args.get(i), args.get(i + 1)) else {
+        return Err(syntax_error());
+    };
+    let ty = ty
+        .as_string()
+        .and_then(|s| FieldType::parse(&s))
+        .ok_or_else(|| {
+            Resp::error(
+                "ERR Invalid bitfield type. Use something like i16 u8. Note that u64 is not supported but i64 is.",
+            )
+        })?;
+    let offset = offset.as_string().unwrap_or_default();
+    let offset = match offset.strip_prefix('#') {
+        Some(n) => n
+            .parse::<u64>()
+            .ok()
+            .and_then(|n| n.checked_mul(ty.bits as u64)),
+        None => offset.parse::<u64>().ok(),
+    };
+    match offset {
+        Some(n) if n <= MAX_BIT_OFFSET => Ok((ty, n)),
+        _ => Err(offset_error()),
+    }
+}
+
+/// Parses the BITFIELD operations starting at `args[1]`.
+fn parse_field_ops(args: &[Resp], read_only: bool) -> Result<Vec<FieldOp>, Resp> {
+    let mut ops = Vec::new();
+    let mut overflow = Overflow::Wrap;
+    let mut i = 1;
+    while i < args.len() {
+        let sub = arg(args, i).to_uppercase();
+        if read_only && sub != "GET" {
+            return Err(Resp::error(
+                "ERR BITFIELD_RO only supports the GET subcommand",
+            ));
+        }
+        match sub.as_str() {
+            "GET" => {
+                let (ty, offset) = parse_field(args, i + 1)?;
+                ops.push(FieldOp::Get(ty, offset));
+                i += 3;
+            }
+            "SET" | "INCRBY" => {
+                let (ty, offset) = parse_field(args, i + 1)?;
+                let value = parse_int(args.get(i + 3).ok_or_else(syntax_error)?)?;
+                ops.push(if sub == "SET" {
+                    FieldOp::Set(ty, offset, value, overflow)
+                } else {
+                    FieldOp::IncrBy(ty, offset, value, overflow)
+                });
+                i += 4;
+            }
+            "OVERFLOW" => {
+                overflow = match arg(args, i + 1).to_uppercase().as_str() {
+                    "WRAP" => Overflow::Wrap,
+                    "SAT" => Overflow::Sat,
+                    "FAIL" => Overflow::Fail,
+                    _ => return Err(Resp::error("ERR Invalid OVERFLOW type specified")),
+                };
+                i += 2;
+            }
+            _ => return Err(syntax_error()),
+        }
+    }
+    Ok(ops)
+}
+
+fn field(args: Vec<Resp>, read_only: bool) -> Resp {
+    let ops = match parse_field_ops(&args, read_only) {
+        Ok(ops) => ops,
+        Err(e) => return e,
+    };
+    match BitmapType::bitfield(&arg_bytes(&args, 0), &ops) {
+        Ok(values) => Resp::array(
+            values
+                .into_iter()
+                .map(|v| v.map_or_else(Resp::null, Resp::int))
+                .collect(),
+        ),
+        Err(e) => Resp::error(e),
+    }
+}
+
+/// BITFIELD key [GET type offset | SET type offset value | INCRBY type offset increment
+/// | OVERFLOW WRAP | SAT | FAIL ...]
+pub fn bitfield(args: Vec<Resp>) -> Resp {
+    field(args, false)
+}
+
+/// BITFIELD_RO key [GET type offset ...]
+pub fn bitfield_ro(args: Vec<Resp>) -> Resp {
+    field(args, true)
+}
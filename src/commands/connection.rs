@@ -0,0 +1,107 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::commands::handler::{arg, parse_int};
+use crate::resp::resp::{Protocol, Resp};
+
+static NEXT_CLIENT_ID: AtomicU64 = AtomicU64::new(1);
+
+/// State kept for each client connection.
+pub struct Client {
+    pub id: u64,
+    pub name: Option<String>,
+    pub protocol: Protocol,
+}
+
+impl Client {
+    pub fn new() -> Self {
+        Client {
+            id: NEXT_CLIENT_ID.fetch_add(1, Ordering::Relaxed),
+            name: None,
+            protocol: Protocol::default(),
+        }
+    }
+}
+
+impl Default for Client {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// HELLO [protover [AUTH username password] [SETNAME clientname]]
+/// Switches the connection's protocol and replies with the server properties.
+/// Nothing is changed unless every option is valid.
+pub fn hello(client: &mut Client, args: &[Resp]) -> Resp {
+    let protocol = match args.first() {
+        None => client.protocol,
+        Some(version) => match parse_int(version) {
+            Ok(v) => match Protocol::from_version(v) {
+                Some(p) => p,
+                None => return Resp::error("NOPROTO unsupported protocol version"),
+            },
+            Err(_) => {
+                return Resp::error("ERR Protocol version is not an integer or out of range");
+            }
+        },
+    };
+
+    let mut name = None;
+    let mut i = 1;
+    while i < args.len() {
+        let option = arg(args, i);
+        let remaining = args.len() - i - 1;
+        if option.eq_ignore_ascii_case("AUTH") && remaining >= 2 {
+            if let Err(e) = authenticate(&arg(args, i + 1), &arg(args, i + 2)) {
+                return e;
+            }
+            i += 3;
+        } else if option.eq_ignore_ascii_case("SETNAME") && remaining >= 1 {
+            match parse_client_name(&arg(args, i + 1)) {
+                Ok(n) => name = Some(n),
+                Err(e) => return e,
+            }
+            i += 2;
+        } else {
+            return Resp::error(format!("ERR Syntax error in HELLO option '{}'", option));
+        }
+    }
+
+    client.protocol = protocol;
+    if let Some(name) = name {
+        client.name = name;
+    }
+    Resp::map(vec![
+        (Resp::bulk("server"), Resp::bulk("animus")),
+        (Resp::bulk("version"), Resp::bulk(env!("CARGO_PKG_VERSION"))),
+        (Resp::bulk("proto"), Resp::int(protocol.version())),
+        (Resp::bulk("id"), Resp::int(client.id as i64)),
+        (Resp::bulk("mode"), Resp::bulk("standalone")),
+        (Resp::bulk("role"), Resp::bulk("master")),
+        (Resp::bulk("modules"), Resp::array(vec![])),
+    ])
+}
+
+/// There are no ACL users, so only the password-less `default` user can authenticate.
+fn authenticate(username: &str, _password: &str) -> Result<(), Resp> {
+    if username == "default" {
+        Ok(())
+    } else {
+        Err(Resp::error(
+            "WRONGPASS invalid username-password pair or user is disabled.",
+        ))
+    }
+}
+
+/// Validates a client name. An empty name clears the current one.
+fn parse_client_name(name: &str) -> Result<Option<String>, Resp> {
+    if name.is_empty() {
+        return Ok(None);
+    }
+    if name.bytes().all(|b| (b'!'..=b'~').contains(&b)) {
+        Ok(Some(name.to_string()))
+    } else {
+        Err(Resp::error(
+            "ERR Client names cannot contain spaces, newlines or special characters.",
+        ))
+    }
+}
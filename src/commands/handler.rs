@@ -1,8 +1,12 @@
-use crate::resp::resp::{Resp, Typ, Value};
+use crate::commands::{bitmaps, hashes, keys, lists, server, sets, streams, strings, zsets};
+use crate::resp::resp::Resp;
+use crate::store::store::global_store;
 use std::{collections::HashMap, sync::OnceLock};
 
+pub type Handler = fn(Vec<Resp>) -> Resp;
+
 pub struct Command {
-    pub func: fn(Vec<Resp>) -> Resp,
+    pub func: Handler,
     pub doc: &'static str,
     pub arity: i32,
     pub flags: &'static [&'static str],
@@ -11,29 +15,195 @@ pub struct Command {
     pub step: i32,
 }
 
+impl Command {
+    /// Checks the argument count (including the command name) against `arity`.
+    /// A negative arity means "at least that many".
+    pub fn accepts(&self, argc: usize) -> bool {
+        if self.arity >= 0 {
+            argc == self.arity as usize
+        } else {
+            argc >= self.arity.unsigned_abs() as usize
+        }
+    }
+}
+
+/// Commands that can grow the dataset. They are refused while memory is over
+/// `maxmemory` and the eviction policy can't free any more.
+const DENY_OOM: &[&str] = &[
+    "APPEND",
+    "DECR",
+    "DECRBY",
+    "GETSET",
+    "INCR",
+    "INCRBY",
+    "INCRBYFLOAT",
+    "MSET",
+    "SET",
+    "SETEX",
+    "PSETEX",
+    "SETRANGE",
+    "SETBIT",
+    "BITFIELD",
+    "BITOP",
+    "HSET",
+    "HSETNX",
+    "HINCRBY",
+    "HINCRBYFLOAT",
+    "LPUSH",
+    "RPUSH",
+    "LPUSHX",
+    "RPUSHX",
+    "LINSERT",
+    "LSET",
+    "LMOVE",
+    "SADD",
+    "SDIFFSTORE",
+    "SINTERSTORE",
+    "SUNIONSTORE",
+    "ZADD",
+    "ZINCRBY",
+    "ZRANGESTORE",
+    "ZUNIONSTORE",
+    "ZINTERSTORE",
+    "ZDIFFSTORE",
+    "XADD",
+    "XGROUP",
+    "XREADGROUP",
+    "XCLAIM",
+];
+
+/// Commands that modify the dataset without growing it, so they are still
+/// allowed while memory is over `maxmemory`.
+const WRITE: &[&str] = &[
+    "GETDEL",
+    "GETEX",
+    "HDEL",
+    "HEXPIRE",
+    "LPOP",
+    "RPOP",
+    "LREM",
+    "LTRIM",
+    "LMPOP",
+    "SREM",
+    "SPOP",
+    "SMOVE",
+    "ZREM",
+    "ZPOPMIN",
+    "ZPOPMAX",
+    "XDEL",
+    "XTRIM",
+    "XACK",
+    "XAUTOCLAIM",
+    "EXPIRE",
+    "PEXPIRE",
+    "EXPIREAT",
+    "PEXPIREAT",
+    "PERSIST",
+    "COPY",
+    "DEL",
+];
+
+const OOM_ERROR: &str = "OOM command not allowed when used memory > 'maxmemory'.";
+
+/// Flags for a command registered from one of the tables: `write` and
+/// `denyoom` for those in `DENY_OOM`, `write` for those in `WRITE`,
+/// otherwise the table's own.
+fn table_flags(name: &str, flags: &'static [&'static str]) -> &'static [&'static str] {
+    if DENY_OOM.contains(&name) {
+        &["write", "denyoom"]
+    } else if WRITE.contains(&name) {
+        &["write"]
+    } else {
+        flags
+    }
+}
+
 static COMMANDS: OnceLock<HashMap<&'static str, Command>> = OnceLock::new();
 
-// ----------------- Example command handlers -----------------
-fn ping(_args: Vec<Resp>) -> Resp {
-    Resp {
-        val: Value::Str("PONG".to_string()),
-        typ: Typ::STRING,
+// ----------------- Argument helpers -----------------
+/// Returns argument `i` as a string, or an empty string if it is not one.
+/// Only for keywords and options: keys, fields and members go through
+/// [`arg_bytes`] so that distinct non-UTF-8 names stay distinct.
+pub fn arg(args: &[Resp], i: usize) -> String {
+    args.get(i).and_then(Resp::as_string).unwrap_or_default()
+}
+
+pub fn parse_int(r: &Resp) -> Result<i64, Resp> {
+    r.as_string()
+        .and_then(|s| s.parse::<i64>().ok())
+        .ok_or_else(|| Resp::error("ERR value is not an integer or out of range"))
+}
+
+/// Parses the count of HRANDFIELD, SRANDMEMBER or ZRANDMEMBER, which must lie
+/// within ±`i64::MAX / 2` like in Redis.
+pub fn parse_rand_count(r: &Resp) -> Result<i64, Resp> {
+    match parse_int(r)? {
+        n if (-(i64::MAX / 2)..=i64::MAX / 2).contains(&n) => Ok(n),
+        _ => Err(Resp::error("ERR value is out of range")),
     }
 }
 
-fn info(_args: Vec<Resp>) -> Resp {
-    Resp {
-        val: Value::Str("INFO".to_string()),
-        typ: Typ::BULK,
+pub fn parse_float(r: &Resp) -> Result<f64, Resp> {
+    r.as_string()
+        .and_then(|s| s.parse::<f64>().ok())
+        .filter(|f| !f.is_nan())
+        .ok_or_else(|| Resp::error("ERR value is not a valid float"))
+}
+
+/// Returns arguments `from..` as strings.
+pub fn args_from(args: &[Resp], from: usize) -> Vec<String> {
+    (from..args.len()).map(|i| arg(args, i)).collect()
+}
+
+/// Returns argument `i` as raw bytes, or an empty value if it is not a string.
+pub fn arg_bytes(args: &[Resp], i: usize) -> Vec<u8> {
+    args.get(i).and_then(Resp::as_bytes).unwrap_or_default()
+}
+
+/// Returns arguments `from..` as raw bytes.
+pub fn bytes_from(args: &[Resp], from: usize) -> Vec<Vec<u8>> {
+    (from..args.len()).map(|i| arg_bytes(args, i)).collect()
+}
+
+pub fn syntax_error() -> Resp {
+    Resp::error("ERR syntax error")
+}
+
+// ----------------- Reply helpers -----------------
+pub fn int_reply(result: Result<i64, String>) -> Resp {
+    match result {
+        Ok(n) => Resp::int(n),
+        Err(e) => Resp::error(e),
     }
 }
 
+pub fn bool_reply(result: Result<bool, String>) -> Resp {
+    int_reply(result.map(i64::from))
+}
+
+pub fn bulk_or_null(result: Result<Option<impl Into<Vec<u8>>>, String>) -> Resp {
+    match result {
+        Ok(Some(s)) => Resp::bulk(s),
+        Ok(None) => Resp::null(),
+        Err(e) => Resp::error(e),
+    }
+}
+
+pub fn bulk_array(result: Result<Vec<impl Into<Vec<u8>>>, String>) -> Resp {
+    match result {
+        Ok(items) => Resp::array(items.into_iter().map(Resp::bulk).collect()),
+        Err(e) => Resp::error(e),
+    }
+}
+
+// ----------------- Example command handlers -----------------
+fn ping(_args: Vec<Resp>) -> Resp {
+    Resp::simple("PONG")
+}
+
 // Placeholder for other commands
 fn dummy(_args: Vec<Resp>) -> Resp {
-    Resp {
-        val: Value::Str("OK".to_string()),
-        typ: Typ::STRING,
-    }
+    Resp::ok()
 }
 
 // ----------------- Initialize commands -----------------
@@ -61,7 +231,7 @@ Returns PONG to test server responsiveness."#,
             func: dummy,
             doc: r#"COMMAND
 Returns metadata about all registered commands."#,
-            arity: 0,
+            arity: -1,
             flags: &["readonly", "fast"],
             first_key: 0,
             last_key: 0,
@@ -72,10 +242,10 @@ Returns metadata about all registered commands."#,
     m.insert(
         "INFO",
         Command {
-            func: info,
-            doc: r#"INFO
+            func: server::info,
+            doc: r#"INFO [section [section ...]]
 Returns information and statistics about the server."#,
-            arity: 0,
+            arity: -1,
             flags: &["readonly", "fast"],
             first_key: 0,
             last_key: 0,
@@ -86,10 +256,10 @@ Returns information and statistics about the server."#,
     m.insert(
         "CONFIG",
         Command {
-            func: dummy,
-            doc: r#"CONFIG
-Command to handle server configuration."#,
-            arity: -1,
+            func: server::config,
+            doc: r#"CONFIG GET parameter [parameter ...] | CONFIG SET parameter value [parameter value ...]
+Reads or changes server configuration parameters at runtime."#,
+            arity: -2,
             flags: &["readonly", "fast"],
             first_key: 0,
             last_key: 0,
@@ -98,121 +268,220 @@ Command to handle server configuration."#,
     );
 
     // Strings
-    let string_cmds = [
+    let string_cmds: &[(&str, Handler, i32, &str)] = &[
         (
             "APPEND",
+            strings::append,
             3,
             r#"APPEND [KEY] [VALUE]
 Appends a value to a key and returns the new length of the string."#,
         ),
         (
             "DECR",
+            strings::decr,
             2,
             r#"DECR [KEY]
 Decrements the integer value of a key by one."#,
         ),
         (
             "DECRBY",
+            strings::decr_by,
             3,
             r#"DECRBY [KEY] [DECREMENT]
 Decrements the integer value of a key by the given amount."#,
         ),
         (
             "GET",
+            strings::get,
             2,
             r#"GET [KEY]
 Gets the value of a key."#,
         ),
         (
             "GETDEL",
+            strings::get_del,
             2,
             r#"GETDEL [KEY]
 Gets the value of a key and deletes it."#,
         ),
         (
             "GETEX",
-            3,
-            r#"GETEX [KEY] [EXPIRATION]
-Gets the value of a key and sets an expiration."#,
+            strings::get_ex,
+            -2,
+            r#"GETEX [KEY] [EX SECONDS | PX MILLISECONDS | PERSIST]
+Gets the value of a key and optionally sets or removes its expiration."#,
         ),
         (
             "GETRANGE",
+            strings::get_range,
             4,
             r#"GETRANGE [KEY] [START] [END]
 Gets a substring of the string stored at a key."#,
         ),
         (
             "GETSET",
+            strings::get_set,
             3,
             r#"GETSET [KEY] [VALUE]
 Gets the previous key value and then sets it to the passed value."#,
         ),
         (
             "INCR",
+            strings::incr,
             2,
             r#"INCR [KEY]
 Increments the integer value of a key by one."#,
         ),
         (
             "INCRBY",
+            strings::incr_by,
             3,
             r#"INCRBY [KEY] [INCREMENT]
 Increments the integer value of a key by the given amount."#,
         ),
         (
             "INCRBYFLOAT",
+            strings::incr_by_float,
             3,
             r#"INCRBYFLOAT [KEY] [INCREMENT]
 Increments the float value of a key by the given amount."#,
         ),
         (
             "LCS",
-            4,
+            strings::lcs,
+            -3,
             r#"LCS [KEY1] [KEY2] LEN
 Finds the Longest Common Subsequence between the value of two keys.
 Send the optional LEN argument to get just the length."#,
         ),
         (
             "MGET",
+            strings::mget,
             -2,
             r#"MGET key [key ...]
 Returns the values for all the keys. Returns nil for a non-existing key."#,
         ),
         (
             "MSET",
+            strings::mset,
             -3,
             r#"MSET key value [key1 value1 ...]
 Sets the values for all the keys value pair."#,
         ),
         (
             "SET",
+            strings::set,
             -3,
-            r#"SET [KEY] [VALUE]
-Sets the value of a key."#,
+            r#"SET [KEY] [VALUE] [NX | XX] [GET] [EX SECONDS | PX MILLISECONDS]
+Sets the value of a key. NX/XX only set the key if it does not/does already exist,
+GET returns the previous value."#,
+        ),
+        (
+            "SETRANGE",
+            strings::set_range,
+            4,
+            r#"SETRANGE [KEY] [OFFSET] [VALUE]
+Overwrites part of the string stored at key, starting at the specified offset."#,
         ),
-        ("SETRANGE", -3, r#"SETRANGE key offset value"#),
         (
             "SETEX",
+            strings::set_ex,
             4,
-            r#"SET [KEY] [VALUE] [EX SECONDS]
+            r#"SETEX [KEY] [SECONDS] [VALUE]
 Sets the value of a key with expiration in seconds."#,
+        ),
+        (
+            "PSETEX",
+            strings::pset_ex,
+            4,
+            r#"PSETEX [KEY] [MILLISECONDS] [VALUE]
+Sets the value of a key with expiration in milliseconds."#,
         ),
         (
             "STRLEN",
+            strings::str_len,
             2,
             r#"STRLEN [KEY]
 Returns the length of the string value stored at key."#,
         ),
     ];
 
-    for &(name, arity, doc) in &string_cmds {
+    for &(name, func, arity, doc) in string_cmds {
         m.insert(
             name,
             Command {
-                func: dummy,
+                func,
+                doc,
+                arity,
+                flags: table_flags(name, &["readonly", "fast"]),
+                first_key: 0,
+                last_key: 0,
+                step: 0,
+            },
+        );
+    }
+
+    // Bitmaps
+    let bitmap_cmds: &[(&str, Handler, i32, &str)] = &[
+        (
+            "SETBIT",
+            bitmaps::setbit,
+            4,
+            r#"SETBIT [KEY] [OFFSET] [VALUE]
+Sets or clears the bit at offset in the string stored at key, returning the previous bit."#,
+        ),
+        (
+            "GETBIT",
+            bitmaps::getbit,
+            3,
+            r#"GETBIT [KEY] [OFFSET]
+Returns the bit at offset in the string stored at key."#,
+        ),
+        (
+            "BITCOUNT",
+            bitmaps::bitcount,
+            -2,
+            r#"BITCOUNT [KEY] [START END [BYTE | BIT]]
+Counts the set bits in the string stored at key, optionally within a range."#,
+        ),
+        (
+            "BITPOS",
+            bitmaps::bitpos,
+            -3,
+            r#"BITPOS [KEY] [BIT] [START [END [BYTE | BIT]]]
+Returns the position of the first set or clear bit in the string stored at key."#,
+        ),
+        (
+            "BITOP",
+            bitmaps::bitop,
+            -4,
+            r#"BITOP [AND | OR | XOR | NOT | DIFF] [DESTKEY] [KEY] [KEY ...]
+Performs a bitwise operation between strings and stores the result in destkey."#,
+        ),
+        (
+            "BITFIELD",
+            bitmaps::bitfield,
+            -2,
+            r#"BITFIELD [KEY] [GET TYPE OFFSET | SET TYPE OFFSET VALUE | INCRBY TYPE OFFSET INCREMENT | OVERFLOW WRAP | SAT | FAIL ...]
+Reads and writes integer fields of arbitrary width in the string stored at key."#,
+        ),
+        (
+            "BITFIELD_RO",
+            bitmaps::bitfield_ro,
+            -2,
+            r#"BITFIELD_RO [KEY] [GET TYPE OFFSET ...]
+Reads integer fields of arbitrary width from the string stored at key."#,
+        ),
+    ];
+
+    for &(name, func, arity, doc) in bitmap_cmds {
+        m.insert(
+            name,
+            Command {
+                func,
                 doc,
                 arity,
-                flags: &["readonly", "fast"],
+                flags: table_flags(name, &["readonly"]),
                 first_key: 0,
                 last_key: 0,
                 step: 0,
@@ -221,53 +490,122 @@ Returns the length of the string value stored at key."#,
     }
 
     // Hashes
-    let hash_cmds = [
+    let hash_cmds: &[(&str, Handler, i32, &str)] = &[
         (
             "HSET",
+            hashes::hset,
             -4,
-            r#"HSET [KEY] [FIELD] [VALUE]
-Sets a field in the hash stored at key to a value."#,
+            r#"HSET [KEY] [FIELD] [VALUE] [FIELD VALUE ...]
+Sets one or more fields in the hash stored at key. Returns the number of fields added."#,
+        ),
+        (
+            "HSETNX",
+            hashes::hsetnx,
+            4,
+            r#"HSETNX [KEY] [FIELD] [VALUE]
+Sets a field in the hash stored at key only if the field does not exist yet."#,
         ),
         (
             "HGET",
+            hashes::hget,
             3,
             r#"HGET [KEY] [FIELD]
 Gets the value of a field in the hash stored at key."#,
+        ),
+        (
+            "HMGET",
+            hashes::hmget,
+            -3,
+            r#"HMGET [KEY] [FIELD] [FIELD ...]
+Gets the values of the given fields. Returns nil for fields that don't exist."#,
         ),
         (
             "HEXISTS",
+            hashes::hexists,
             3,
             r#"HEXISTS [KEY] [FIELD]
 Checks if the hash and the field combination exists in the store."#,
         ),
         (
             "HEXPIRE",
+            dummy,
             3,
             r#"HEXPIRE key seconds [NX XX GT LT]
 Sets a timeout on hash key. After the timeout, the key gets deleted."#,
         ),
         (
             "HDEL",
-            3,
-            r#"HDEL [KEY] [FIELD]
-Deletes a field from the hash stored at key."#,
+            hashes::hdel,
+            -3,
+            r#"HDEL [KEY] [FIELD] [FIELD ...]
+Deletes one or more fields from the hash stored at key. Returns the number of fields removed."#,
         ),
         (
             "HGETALL",
+            hashes::hgetall,
             2,
             r#"HGETALL [KEY]
 Returns all fields and values of the hash stored at key."#,
         ),
+        (
+            "HKEYS",
+            hashes::hkeys,
+            2,
+            r#"HKEYS [KEY]
+Returns all fields of the hash stored at key."#,
+        ),
+        (
+            "HVALS",
+            hashes::hvals,
+            2,
+            r#"HVALS [KEY]
+Returns all values of the hash stored at key."#,
+        ),
+        (
+            "HLEN",
+            hashes::hlen,
+            2,
+            r#"HLEN [KEY]
+Returns the number of fields in the hash stored at key."#,
+        ),
+        (
+            "HSTRLEN",
+            hashes::hstrlen,
+            3,
+            r#"HSTRLEN [KEY] [FIELD]
+Returns the length of the value of a field, or 0 if the field does not exist."#,
+        ),
+        (
+            "HINCRBY",
+            hashes::hincrby,
+            4,
+            r#"HINCRBY [KEY] [FIELD] [INCREMENT]
+Increments the integer value of a field by the given amount."#,
+        ),
+        (
+            "HINCRBYFLOAT",
+            hashes::hincrbyfloat,
+            4,
+            r#"HINCRBYFLOAT [KEY] [FIELD] [INCREMENT]
+Increments the float value of a field by the given amount."#,
+        ),
+        (
+            "HRANDFIELD",
+            hashes::hrandfield,
+            -2,
+            r#"HRANDFIELD [KEY] [COUNT [WITHVALUES]]
+Returns random fields from the hash stored at key. A negative count allows repeated fields."#,
+        ),
     ];
 
-    for &(name, arity, doc) in &hash_cmds {
+    for &(name, func, arity, doc) in hash_cmds {
         m.insert(
             name,
             Command {
-                func: dummy,
+                func,
                 doc,
                 arity,
-                flags: &["readonly", "fast"],
+                flags: table_flags(name, &["readonly", "fast"]),
                 first_key: 0,
                 last_key: 0,
                 step: 0,
@@ -276,29 +614,129 @@ Returns all fields and values of the hash stored at key."#,
     }
 
     // Lists
-    let list_cmds = [
+    let list_cmds: &[(&str, Handler, i32, &str)] = &[
+        (
+            "LPUSH",
+            lists::lpush,
+            -3,
+            r#"LPUSH [KEY] [VALUE] [VALUE ...]
+Inserts one or more elements at the head of the list stored at key."#,
+        ),
+        (
+            "RPUSH",
+            lists::rpush,
+            -3,
+            r#"RPUSH [KEY] [VALUE] [VALUE ...]
+Inserts one or more elements at the end of the list stored at key."#,
+        ),
+        (
+            "LPUSHX",
+            lists::lpushx,
+            -3,
+            r#"LPUSHX [KEY] [VALUE] [VALUE ...]
+Inserts elements at the head of the list stored at key, only if the list exists."#,
+        ),
+        (
+            "RPUSHX",
+            lists::rpushx,
+            -3,
+            r#"RPUSHX [KEY] [VALUE] [VALUE ...]
+Inserts elements at the end of the list stored at key, only if the list exists."#,
+        ),
+        (
+            "LPOP",
+            lists::lpop,
+            -2,
+            r#"LPOP [KEY] [COUNT]
+Removes and returns the first element(s) of the list stored at key."#,
+        ),
         (
             "RPOP",
+            lists::rpop,
             -2,
             r#"RPOP [KEY] [COUNT]
 Removes and returns the last element(s) of the list stored at key."#,
         ),
         (
-            "RPUSH",
+            "LLEN",
+            lists::llen,
+            2,
+            r#"LLEN [KEY]
+Returns the length of the list stored at key."#,
+        ),
+        (
+            "LRANGE",
+            lists::lrange,
+            4,
+            r#"LRANGE [KEY] [START] [STOP]
+Returns the elements between start and stop (inclusive). Negative indices count from the end."#,
+        ),
+        (
+            "LINDEX",
+            lists::lindex,
+            3,
+            r#"LINDEX [KEY] [INDEX]
+Returns the element at index in the list stored at key."#,
+        ),
+        (
+            "LSET",
+            lists::lset,
+            4,
+            r#"LSET [KEY] [INDEX] [VALUE]
+Sets the element at index in the list stored at key."#,
+        ),
+        (
+            "LINSERT",
+            lists::linsert,
+            5,
+            r#"LINSERT [KEY] [BEFORE | AFTER] [PIVOT] [VALUE]
+Inserts value before or after the first occurrence of pivot."#,
+        ),
+        (
+            "LREM",
+            lists::lrem,
+            4,
+            r#"LREM [KEY] [COUNT] [VALUE]
+Removes count occurrences of value. Negative count removes from the tail, 0 removes all."#,
+        ),
+        (
+            "LTRIM",
+            lists::ltrim,
+            4,
+            r#"LTRIM [KEY] [START] [STOP]
+Trims the list so that it only contains the elements between start and stop."#,
+        ),
+        (
+            "LPOS",
+            lists::lpos,
             -3,
-            r#"RPUSH [KEY] [VALUE] [VALUE ...]
-Inserts one or more elements at the end of the list stored at key."#,
+            r#"LPOS [KEY] [ELEMENT] [RANK RANK] [COUNT NUM] [MAXLEN LEN]
+Returns the index of matching elements in the list stored at key."#,
+        ),
+        (
+            "LMOVE",
+            lists::lmove,
+            5,
+            r#"LMOVE [SOURCE] [DESTINATION] [LEFT | RIGHT] [LEFT | RIGHT]
+Pops an element from one end of source and pushes it onto one end of destination."#,
+        ),
+        (
+            "LMPOP",
+            lists::lmpop,
+            -4,
+            r#"LMPOP [NUMKEYS] [KEY] [KEY ...] [LEFT | RIGHT] [COUNT COUNT]
+Pops elements from the first non-empty list among the given keys."#,
         ),
     ];
 
-    for &(name, arity, doc) in &list_cmds {
+    for &(name, func, arity, doc) in list_cmds {
         m.insert(
             name,
             Command {
-                func: dummy,
+                func,
                 doc,
                 arity,
-                flags: &[],
+                flags: table_flags(name, &["readonly"]),
                 first_key: 0,
                 last_key: 0,
                 step: 0,
@@ -307,41 +745,384 @@ Inserts one or more elements at the end of the list stored at key."#,
     }
 
     // Sets
-    let set_cmds = [
+    let set_cmds: &[(&str, Handler, i32, &str)] = &[
         (
             "SADD",
+            sets::sadd,
             -3,
             r#"SADD [KEY] [MEMBER] [MEMBER ...]
 Adds one or more members to the set stored at key."#,
+        ),
+        (
+            "SREM",
+            sets::srem,
+            -3,
+            r#"SREM [KEY] [MEMBER] [MEMBER ...]
+Removes one or more members from the set stored at key."#,
         ),
         (
             "SCARD",
+            sets::scard,
             2,
             r#"SCARD [KEY]
 Returns the number of members in the set stored at key."#,
+        ),
+        (
+            "SMEMBERS",
+            sets::smembers,
+            2,
+            r#"SMEMBERS [KEY]
+Returns all members of the set stored at key."#,
+        ),
+        (
+            "SISMEMBER",
+            sets::sismember,
+            3,
+            r#"SISMEMBER [KEY] [MEMBER]
+Returns if member is a member of the set stored at key."#,
+        ),
+        (
+            "SMISMEMBER",
+            sets::smismember,
+            -3,
+            r#"SMISMEMBER [KEY] [MEMBER] [MEMBER ...]
+Returns for each member whether it is a member of the set stored at key."#,
+        ),
+        (
+            "SPOP",
+            sets::spop,
+            -2,
+            r#"SPOP [KEY] [COUNT]
+Removes and returns one or more random members from the set stored at key."#,
+        ),
+        (
+            "SRANDMEMBER",
+            sets::srandmember,
+            -2,
+            r#"SRANDMEMBER [KEY] [COUNT]
+Returns random members of the set stored at key. A negative count allows repeated members."#,
+        ),
+        (
+            "SMOVE",
+            sets::smove,
+            4,
+            r#"SMOVE [SOURCE] [DESTINATION] [MEMBER]
+Moves a member from the set at source to the set at destination."#,
         ),
         (
             "SDIFF",
+            sets::sdiff,
             -2,
             r#"SDIFF [KEY] [KEY ...]
 Returns the members of the set resulting from the difference between the first set and all the successive sets."#,
         ),
         (
-            "SISMEMBER",
+            "SDIFFSTORE",
+            sets::sdiffstore,
+            -3,
+            r#"SDIFFSTORE [DESTINATION] [KEY] [KEY ...]
+Stores the difference between the first set and all the successive sets in destination."#,
+        ),
+        (
+            "SINTER",
+            sets::sinter,
+            -2,
+            r#"SINTER [KEY] [KEY ...]
+Returns the members of the set resulting from the intersection of all the given sets."#,
+        ),
+        (
+            "SINTERSTORE",
+            sets::sinterstore,
+            -3,
+            r#"SINTERSTORE [DESTINATION] [KEY] [KEY ...]
+Stores the intersection of all the given sets in destination."#,
+        ),
+        (
+            "SINTERCARD",
+            sets::sintercard,
+            -3,
+            r#"SINTERCARD [NUMKEYS] [KEY] [KEY ...] [LIMIT LIMIT]
+Returns the number of members in the intersection of the given sets, capped at limit."#,
+        ),
+        (
+            "SUNION",
+            sets::sunion,
+            -2,
+            r#"SUNION [KEY] [KEY ...]
+Returns the members of the set resulting from the union of all the given sets."#,
+        ),
+        (
+            "SUNIONSTORE",
+            sets::sunionstore,
+            -3,
+            r#"SUNIONSTORE [DESTINATION] [KEY] [KEY ...]
+Stores the union of all the given sets in destination."#,
+        ),
+    ];
+
+    for &(name, func, arity, doc) in set_cmds {
+        m.insert(
+            name,
+            Command {
+                func,
+                doc,
+                arity,
+                flags: table_flags(name, &["readonly", "fast"]),
+                first_key: 0,
+                last_key: 0,
+                step: 0,
+            },
+        );
+    }
+
+    // Sorted sets
+    let zset_cmds: &[(&str, Handler, i32, &str)] = &[
+        (
+            "ZADD",
+            zsets::zadd,
+            -4,
+            r#"ZADD [KEY] [NX | XX] [GT | LT] [CH] [INCR] [SCORE] [MEMBER] [SCORE MEMBER ...]
+Adds members with scores to the sorted set stored at key, or updates their scores."#,
+        ),
+        (
+            "ZINCRBY",
+            zsets::zincrby,
+            4,
+            r#"ZINCRBY [KEY] [INCREMENT] [MEMBER]
+Increments the score of a member in the sorted set stored at key."#,
+        ),
+        (
+            "ZREM",
+            zsets::zrem,
+            -3,
+            r#"ZREM [KEY] [MEMBER] [MEMBER ...]
+Removes one or more members from the sorted set stored at key."#,
+        ),
+        (
+            "ZSCORE",
+            zsets::zscore,
             3,
-            r#"SISMEMBER [KEY] [MEMBER]
-Returns if member is a member of the set stored at key."#,
+            r#"ZSCORE [KEY] [MEMBER]
+Returns the score of a member in the sorted set stored at key."#,
+        ),
+        (
+            "ZMSCORE",
+            zsets::zmscore,
+            -3,
+            r#"ZMSCORE [KEY] [MEMBER] [MEMBER ...]
+Returns the scores of the given members. Returns nil for members that don't exist."#,
+        ),
+        (
+            "ZCARD",
+            zsets::zcard,
+            2,
+            r#"ZCARD [KEY]
+Returns the number of members in the sorted set stored at key."#,
+        ),
+        (
+            "ZCOUNT",
+            zsets::zcount,
+            4,
+            r#"ZCOUNT [KEY] [MIN] [MAX]
+Returns the number of members with a score between min and max."#,
+        ),
+        (
+            "ZRANK",
+            zsets::zrank,
+            -3,
+            r#"ZRANK [KEY] [MEMBER] [WITHSCORE]
+Returns the rank of a member, ordered from the lowest score."#,
+        ),
+        (
+            "ZREVRANK",
+            zsets::zrevrank,
+            -3,
+            r#"ZREVRANK [KEY] [MEMBER] [WITHSCORE]
+Returns the rank of a member, ordered from the highest score."#,
+        ),
+        (
+            "ZRANGE",
+            zsets::zrange,
+            -4,
+            r#"ZRANGE [KEY] [START] [STOP] [BYSCORE | BYLEX] [REV] [LIMIT OFFSET COUNT] [WITHSCORES]
+Returns members in a range of ranks, scores or lexicographical values."#,
+        ),
+        (
+            "ZRANGESTORE",
+            zsets::zrangestore,
+            -5,
+            r#"ZRANGESTORE [DESTINATION] [SOURCE] [MIN] [MAX] [BYSCORE | BYLEX] [REV] [LIMIT OFFSET COUNT]
+Stores a range of members from source in destination."#,
+        ),
+        (
+            "ZPOPMIN",
+            zsets::zpopmin,
+            -2,
+            r#"ZPOPMIN [KEY] [COUNT]
+Removes and returns the members with the lowest scores."#,
+        ),
+        (
+            "ZPOPMAX",
+            zsets::zpopmax,
+            -2,
+            r#"ZPOPMAX [KEY] [COUNT]
+Removes and returns the members with the highest scores."#,
+        ),
+        (
+            "ZRANDMEMBER",
+            zsets::zrandmember,
+            -2,
+            r#"ZRANDMEMBER [KEY] [COUNT [WITHSCORES]]
+Returns random members of the sorted set stored at key. A negative count allows repeated members."#,
+        ),
+        (
+            "ZUNIONSTORE",
+            zsets::zunionstore,
+            -4,
+            r#"ZUNIONSTORE [DESTINATION] [NUMKEYS] [KEY] [KEY ...] [WEIGHTS WEIGHT ...] [AGGREGATE SUM | MIN | MAX]
+Stores the union of the given sorted sets in destination."#,
+        ),
+        (
+            "ZINTERSTORE",
+            zsets::zinterstore,
+            -4,
+            r#"ZINTERSTORE [DESTINATION] [NUMKEYS] [KEY] [KEY ...] [WEIGHTS WEIGHT ...] [AGGREGATE SUM | MIN | MAX]
+Stores the intersection of the given sorted sets in destination."#,
+        ),
+        (
+            "ZDIFFSTORE",
+            zsets::zdiffstore,
+            -4,
+            r#"ZDIFFSTORE [DESTINATION] [NUMKEYS] [KEY] [KEY ...]
+Stores the difference between the first and all successive sorted sets in destination."#,
         ),
     ];
 
-    for &(name, arity, doc) in &set_cmds {
+    for &(name, func, arity, doc) in zset_cmds {
         m.insert(
             name,
             Command {
-                func: dummy,
+                func,
                 doc,
                 arity,
-                flags: &["readonly", "fast"],
+                flags: table_flags(name, &["readonly"]),
+                first_key: 0,
+                last_key: 0,
+                step: 0,
+            },
+        );
+    }
+
+    // Streams
+    let stream_cmds: &[(&str, Handler, i32, &str)] = &[
+        (
+            "XADD",
+            streams::xadd,
+            -5,
+            r#"XADD [KEY] [NOMKSTREAM] [MAXLEN | MINID [= | ~] THRESHOLD [LIMIT COUNT]] [* | ID] [FIELD] [VALUE] [FIELD VALUE ...]
+Appends an entry to the stream stored at key, creating the stream if needed."#,
+        ),
+        (
+            "XLEN",
+            streams::xlen,
+            2,
+            r#"XLEN [KEY]
+Returns the number of entries in the stream stored at key."#,
+        ),
+        (
+            "XRANGE",
+            streams::xrange,
+            -4,
+            r#"XRANGE [KEY] [START] [END] [COUNT COUNT]
+Returns the entries with IDs between start and end."#,
+        ),
+        (
+            "XREVRANGE",
+            streams::xrevrange,
+            -4,
+            r#"XREVRANGE [KEY] [END] [START] [COUNT COUNT]
+Returns the entries with IDs between end and start, in reverse order."#,
+        ),
+        (
+            "XDEL",
+            streams::xdel,
+            -3,
+            r#"XDEL [KEY] [ID] [ID ...]
+Removes entries from the stream stored at key."#,
+        ),
+        (
+            "XTRIM",
+            streams::xtrim,
+            -4,
+            r#"XTRIM [KEY] [MAXLEN | MINID] [= | ~] [THRESHOLD] [LIMIT COUNT]
+Evicts the oldest entries of the stream stored at key."#,
+        ),
+        (
+            "XREAD",
+            streams::xread,
+            -4,
+            r#"XREAD [COUNT COUNT] STREAMS [KEY] [KEY ...] [ID] [ID ...]
+Returns entries with IDs greater than the given ones from one or more streams."#,
+        ),
+        (
+            "XGROUP",
+            streams::xgroup,
+            -2,
+            r#"XGROUP [CREATE | SETID | DESTROY | CREATECONSUMER | DELCONSUMER] [KEY] [GROUP] [ARG ...]
+Manages the consumer groups of the stream stored at key."#,
+        ),
+        (
+            "XREADGROUP",
+            streams::xreadgroup,
+            -7,
+            r#"XREADGROUP GROUP [GROUP] [CONSUMER] [COUNT COUNT] [NOACK] STREAMS [KEY] [KEY ...] [ID] [ID ...]
+Reads entries from one or more streams on behalf of a consumer in a consumer group."#,
+        ),
+        (
+            "XACK",
+            streams::xack,
+            -4,
+            r#"XACK [KEY] [GROUP] [ID] [ID ...]
+Acknowledges pending entries of a consumer group."#,
+        ),
+        (
+            "XPENDING",
+            streams::xpending,
+            -3,
+            r#"XPENDING [KEY] [GROUP] [[IDLE MIN-IDLE-TIME] START END COUNT [CONSUMER]]
+Returns information about the pending entries of a consumer group."#,
+        ),
+        (
+            "XCLAIM",
+            streams::xclaim,
+            -6,
+            r#"XCLAIM [KEY] [GROUP] [CONSUMER] [MIN-IDLE-TIME] [ID] [ID ...] [IDLE MS] [TIME UNIX-TIME-MILLISECONDS] [RETRYCOUNT COUNT] [FORCE] [JUSTID] [LASTID LASTID]
+Transfers ownership of pending entries to another consumer."#,
+        ),
+        (
+            "XAUTOCLAIM",
+            streams::xautoclaim,
+            -6,
+            r#"XAUTOCLAIM [KEY] [GROUP] [CONSUMER] [MIN-IDLE-TIME] [START] [COUNT COUNT] [JUSTID]
+Transfers ownership of pending entries idle for at least min-idle-time to another consumer."#,
+        ),
+        (
+            "XINFO",
+            streams::xinfo,
+            -3,
+            r#"XINFO [STREAM KEY | GROUPS KEY | CONSUMERS KEY GROUP]
+Returns information about a stream, its consumer groups or their consumers."#,
+        ),
+    ];
+
+    for &(name, func, arity, doc) in stream_cmds {
+        m.insert(
+            name,
+            Command {
+                func,
+                doc,
+                arity,
+                flags: table_flags(name, &["readonly"]),
                 first_key: 0,
                 last_key: 0,
                 step: 0,
@@ -364,6 +1145,95 @@ Provides details on how to use a command and what the command actually does."#,
         },
     );
 
+    // Keys
+    let key_cmds: &[(&str, Handler, i32, &str)] = &[
+        (
+            "EXPIRE",
+            keys::expire,
+            -3,
+            r#"EXPIRE key seconds [NX XX GT LT]
+Sets a timeout on key. After the timeout, the key gets deleted."#,
+        ),
+        (
+            "PEXPIRE",
+            keys::pexpire,
+            -3,
+            r#"PEXPIRE key milliseconds [NX XX GT LT]
+Sets a timeout on key in milliseconds. After the timeout, the key gets deleted."#,
+        ),
+        (
+            "EXPIREAT",
+            keys::expire_at,
+            -3,
+            r#"EXPIREAT key unix-time-seconds [NX XX GT LT]
+Sets the timeout of a key to the unix time stamp in seconds. After the timeout, the key gets deleted."#,
+        ),
+        (
+            "PEXPIREAT",
+            keys::pexpire_at,
+            -3,
+            r#"PEXPIREAT key unix-time-milliseconds [NX XX GT LT]
+Sets the timeout of a key to the unix time stamp in milliseconds. After the timeout, the key gets deleted."#,
+        ),
+        (
+            "EXPIRETIME",
+            keys::expire_time,
+            2,
+            r#"EXPIRETIME key
+Returns the expire time of a key in unix epoch seconds. -1 if the key doesn't have an expiry set, -2 if the key doesn't exist."#,
+        ),
+        (
+            "PEXPIRETIME",
+            keys::pexpire_time,
+            2,
+            r#"PEXPIRETIME key
+Returns the expire time of a key in unix epoch milliseconds. -1 if the key doesn't have an expiry set, -2 if the key doesn't exist."#,
+        ),
+        (
+            "TTL",
+            keys::ttl,
+            2,
+            r#"TTL key
+Returns the remaining time to live of a key in seconds. -1 if the key doesn't have an expiry set, -2 if the key doesn't exist."#,
+        ),
+        (
+            "PTTL",
+            keys::pttl,
+            2,
+            r#"PTTL key
+Returns the remaining time to live of a key in milliseconds. -1 if the key doesn't have an expiry set, -2 if the key doesn't exist."#,
+        ),
+        (
+            "PERSIST",
+            keys::persist,
+            2,
+            r#"PERSIST key
+Removes the expiration of a key. Returns 1 if the timeout was removed, 0 if the key doesn't exist or has no timeout."#,
+        ),
+        (
+            "OBJECT",
+            keys::object,
+            -2,
+            r#"OBJECT FREQ [KEY]
+Returns the logarithmic access frequency counter of a key. Requires an LFU maxmemory-policy."#,
+        ),
+    ];
+
+    for &(name, func, arity, doc) in key_cmds {
+        m.insert(
+            name,
+            Command {
+                func,
+                doc,
+                arity,
+                flags: table_flags(name, &["readonly", "fast"]),
+                first_key: 0,
+                last_key: 0,
+                step: 0,
+            },
+        );
+    }
+
     // Generic commands
     let generic_cmds = [
         (
@@ -383,24 +1253,6 @@ Deletes all the keys passed as argument. Ignores the keys in the argument that d
             -2,
             r#"EXISTS key1 [keys...]
 Returns an integer denoting how many of the passed keys exist in the cache."#,
-        ),
-        (
-            "EXPIRE",
-            3,
-            r#"EXPIRE key seconds [NX XX GT LT]
-Sets a timeout on key. After the timeout, the key gets deleted."#,
-        ),
-        (
-            "EXPIREAT",
-            3,
-            r#"EXPIREAT key unix-time-seconds [NX XX GT LT]
-Sets the timeout of a key to the unix time stamp in seconds. After the timeout, the key gets deleted."#,
-        ),
-        (
-            "EXPIRETIME",
-            2,
-            r#"EXPIRETIME key
-Returns the expire time of a key in unix epoch seconds. -1 if the key doesn't have an expiry set, -2 if the key doesn't exist."#,
         ),
         (
             "KEYS",
@@ -417,7 +1269,7 @@ Returns the keys that exist in the store."#,
                 func: dummy,
                 doc,
                 arity,
-                flags: &["readonly", "fast"],
+                flags: table_flags(name, &["readonly", "fast"]),
                 first_key: 0,
                 last_key: 0,
                 step: 0,
@@ -432,3 +1284,39 @@ Returns the keys that exist in the store."#,
 pub fn commands() -> &'static HashMap<&'static str, Command> {
     COMMANDS.get_or_init(init_commands)
 }
+
+/// Looks up `cmd` in the command table, validates its arity and runs it.
+/// Keys are evicted first if memory is over `maxmemory`.
+pub fn execute(cmd: &str, args: Vec<Resp>) -> Resp {
+    match commands().get(cmd) {
+        Some(command) if command.accepts(args.len() + 1) => {
+            if !global_store().free_memory() && command.flags.contains(&"denyoom") {
+                return Resp::error(OOM_ERROR);
+            }
+            (command.func)(args)
+        }
+        Some(_) => Resp::error(format!(
+            "ERR wrong number of arguments for '{}' command",
+            cmd.to_lowercase()
+        )),
+        None => Resp::error(format!("ERR unknown command '{}'", cmd)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn flags_mutating_commands_as_writes() {
+        for name in ["DEL", "HDEL", "LPOP", "SREM", "ZREM", "XACK", "PERSIST"] {
+            assert_eq!(commands()[name].flags, ["write"], "{name}");
+        }
+        for &name in DENY_OOM {
+            assert_eq!(commands()[name].flags, ["write", "denyoom"], "{name}");
+        }
+        for name in ["GET", "HGETALL", "ZRANGE", "XRANGE", "TTL", "EXISTS"] {
+            assert!(commands()[name].flags.contains(&"readonly"), "{name}");
+        }
+    }
+}
@@ -0,0 +1,144 @@
+use crate::commands::handler::{
+    arg, arg_bytes, bool_reply, bulk_array, bulk_or_null, bytes_from, int_reply, parse_float,
+    parse_int, parse_rand_count, syntax_error,
+};
+use crate::resp::resp::Resp;
+use crate::types::hash_type::{FieldValue, HashType};
+
+fn pairs_reply(result: Result<Vec<FieldValue>, String>) -> Resp {
+    match result {
+        Ok(pairs) => Resp::array(
+            pairs
+                .into_iter()
+                .flat_map(|(f, v)| [Resp::bulk(f), Resp::bulk(v)])
+                .collect(),
+        ),
+        Err(e) => Resp::error(e),
+    }
+}
+
+/// HSET key field value [field value ...]
+pub fn hset(args: Vec<Resp>) -> Resp {
+    if args.len().is_multiple_of(2) {
+        return Resp::error("ERR wrong number of arguments for 'hset' command");
+    }
+    let pairs: Vec<(Vec<u8>, Vec<u8>)> = (1..args.len())
+        .step_by(2)
+        .map(|i| (arg_bytes(&args, i), arg_bytes(&args, i + 1)))
+        .collect();
+    int_reply(HashType::hset(&arg_bytes(&args, 0), &pairs))
+}
+
+pub fn hsetnx(args: Vec<Resp>) -> Resp {
+    bool_reply(HashType::hsetnx(
+        &arg_bytes(&args, 0),
+        &arg_bytes(&args, 1),
+        &arg_bytes(&args, 2),
+    ))
+}
+
+pub fn hget(args: Vec<Resp>) -> Resp {
+    bulk_or_null(HashType::hget(&arg_bytes(&args, 0), &arg_bytes(&args, 1)))
+}
+
+pub fn hmget(args: Vec<Resp>) -> Resp {
+    match HashType::hmget(&arg_bytes(&args, 0), &bytes_from(&args, 1)) {
+        Ok(values) => Resp::array(
+            values
+                .into_iter()
+                .map(|v| v.map_or_else(Resp::null, Resp::bulk))
+                .collect(),
+        ),
+        Err(e) => Resp::error(e),
+    }
+}
+
+pub fn hdel(args: Vec<Resp>) -> Resp {
+    int_reply(HashType::hdel(&arg_bytes(&args, 0), &bytes_from(&args, 1)))
+}
+
+pub fn hexists(args: Vec<Resp>) -> Resp {
+    bool_reply(HashType::hexists(
+        &arg_bytes(&args, 0),
+        &arg_bytes(&args, 1),
+    ))
+}
+
+pub fn hgetall(args: Vec<Resp>) -> Resp {
+    match HashType::hgetall(&arg_bytes(&args, 0)) {
+        Ok(pairs) => Resp::map(
+            pairs
+                .into_iter()
+                .map(|(f, v)| (Resp::bulk(f), Resp::bulk(v)))
+                .collect(),
+        ),
+        Err(e) => Resp::error(e),
+    }
+}
+
+pub fn hkeys(args: Vec<Resp>) -> Resp {
+    bulk_array(HashType::hkeys(&arg_bytes(&args, 0)))
+}
+
+pub fn hvals(args: Vec<Resp>) -> Resp {
+    bulk_array(HashType::hvals(&arg_bytes(&args, 0)))
+}
+
+pub fn hlen(args: Vec<Resp>) -> Resp {
+    int_reply(HashType::hlen(&arg_bytes(&args, 0)))
+}
+
+pub fn hstrlen(args: Vec<Resp>) -> Resp {
+    int_reply(HashType::hstrlen(
+        &arg_bytes(&args, 0),
+        &arg_bytes(&args, 1),
+    ))
+}
+
+pub fn hincrby(args: Vec<Resp>) -> Resp {
+    match parse_int(&args[2]) {
+        Ok(n) => int_reply(HashType::hincrby(
+            &arg_bytes(&args, 0),
+            &arg_bytes(&args, 1),
+            n,
+        )),
+        Err(e) => e,
+    }
+}
+
+pub fn hincrbyfloat(args: Vec<Resp>) -> Resp {
+    let incr = match parse_float(&args[2]) {
+        Ok(n) => n,
+        Err(e) => return e,
+    };
+    match HashType::hincrbyfloat(&arg_bytes(&args, 0), &arg_bytes(&args, 1), incr) {
+        Ok(s) => Resp::bulk(s),
+        Err(e) => Resp::error(e),
+    }
+}
+
+/// HRANDFIELD key [count [WITHVALUES]]
+pub fn hrandfield(args: Vec<Resp>) -> Resp {
+    let key = arg_bytes(&args, 0);
+    if args.len() == 1 {
+        return match HashType::hrandfield(&key, 1) {
+            Ok(mut fields) => fields.pop().map_or_else(Resp::null, |(f, _)| Resp::bulk(f)),
+            Err(e) => Resp::error(e),
+        };
+    }
+    let count = match parse_rand_count(&args[1]) {
+        Ok(n) => n,
+        Err(e) => return e,
+    };
+    let with_values = match args.len() {
+        2 => false,
+        3 if arg(&args, 2).eq_ignore_ascii_case("WITHVALUES") => true,
+        _ => return syntax_error(),
+    };
+    let result = HashType::hrandfield(&key, count);
+    if with_values {
+        pairs_reply(result)
+    } else {
+        bulk_array(result.map(|pairs| pairs.into_iter().map(|(f, _)| f).collect()))
+    }
+}
@@ -0,0 +1,117 @@
+use crate::commands::handler::{arg, arg_bytes, args_from, parse_int};
+use crate::resp::resp::Resp;
+use crate::store::store::current_unix_millis;
+use crate::types::key_type::{ExpireCondition, KeyType};
+
+/// Parses the options following the key and time of EXPIRE and its variants.
+fn parse_condition(args: &[Resp]) -> Result<ExpireCondition, Resp> {
+    let mut condition = ExpireCondition::default();
+    for opt in args_from(args, 2) {
+        match opt.to_uppercase().as_str() {
+            "NX" => condition.nx = true,
+            "XX" => condition.xx = true,
+            "GT" => condition.gt = true,
+            "LT" => condition.lt = true,
+            _ => return Err(Resp::error(format!("ERR Unsupported option {}", opt))),
+        }
+    }
+    if condition.nx && (condition.xx || condition.gt || condition.lt) {
+        return Err(Resp::error(
+            "ERR NX and XX, GT or LT options at the same time are not compatible",
+        ));
+    }
+    if condition.gt && condition.lt {
+        return Err(Resp::error(
+            "ERR GT and LT options at the same time are not compatible",
+        ));
+    }
+    Ok(condition)
+}
+
+/// Shared by EXPIRE, PEXPIRE, EXPIREAT and PEXPIREAT. The time is a count of
+/// `unit_ms` milliseconds, from now if `relative`, otherwise from the Unix epoch.
+fn expire_in_units(args: &[Resp], cmd: &str, unit_ms: i64, relative: bool) -> Resp {
+    let condition = match parse_condition(args) {
+        Ok(c) => c,
+        Err(e) => return e,
+    };
+    let n = match parse_int(&args[1]) {
+        Ok(n) => n,
+        Err(e) => return e,
+    };
+    let base = if relative {
+        current_unix_millis() as i64
+    } else {
+        0
+    };
+    let Some(at) = n.checked_mul(unit_ms).and_then(|ms| ms.checked_add(base)) else {
+        return Resp::error(format!("ERR invalid expire time in '{}' command", cmd));
+    };
+    // Times before the epoch have passed all the same, and delete the key.
+    let set = KeyType::expire_at(&arg_bytes(args, 0), at.max(0) as u64, condition);
+    Resp::int(i64::from(set))
+}
+
+/// EXPIRE key seconds [NX | XX | GT | LT]
+pub fn expire(args: Vec<Resp>) -> Resp {
+    expire_in_units(&args, "expire", 1000, true)
+}
+
+/// PEXPIRE key milliseconds [NX | XX | GT | LT]
+pub fn pexpire(args: Vec<Resp>) -> Resp {
+    expire_in_units(&args, "pexpire", 1, true)
+}
+
+/// EXPIREAT key unix-time-seconds [NX | XX | GT | LT]
+pub fn expire_at(args: Vec<Resp>) -> Resp {
+    expire_in_units(&args, "expireat", 1000, false)
+}
+
+/// PEXPIREAT key unix-time-milliseconds [NX | XX | GT | LT]
+pub fn pexpire_at(args: Vec<Resp>) -> Resp {
+    expire_in_units(&args, "pexpireat", 1, false)
+}
+
+pub fn expire_time(args: Vec<Resp>) -> Resp {
+    match KeyType::expire_time(&arg_bytes(&args, 0)) {
+        at if at < 0 => Resp::int(at),
+        at => Resp::int(at / 1000),
+    }
+}
+
+pub fn pexpire_time(args: Vec<Resp>) -> Resp {
+    Resp::int(KeyType::expire_time(&arg_bytes(&args, 0)))
+}
+
+/// Replies with whole seconds, rounded to the nearest one.
+pub fn ttl(args: Vec<Resp>) -> Resp {
+    match KeyType::ttl(&arg_bytes(&args, 0)) {
+        ms if ms < 0 => Resp::int(ms),
+        ms => Resp::int((ms + 500) / 1000),
+    }
+}
+
+pub fn pttl(args: Vec<Resp>) -> Resp {
+    Resp::int(KeyType::ttl(&arg_bytes(&args, 0)))
+}
+
+pub fn persist(args: Vec<Resp>) -> Resp {
+    Resp::int(i64::from(KeyType::persist(&arg_bytes(&args, 0))))
+}
+
+/// OBJECT FREQ key
+pub fn object(args: Vec<Resp>) -> Resp {
+    let sub = arg(&args, 0).to_uppercase();
+    match sub.as_str() {
+        "FREQ" if args.len() == 2 => match KeyType::object_freq(&arg_bytes(&args, 1)) {
+            Ok(Some(freq)) => Resp::int(freq as i64),
+            Ok(None) => Resp::null(),
+            Err(e) => Resp::error(e),
+        },
+        "FREQ" => Resp::error("ERR wrong number of arguments for 'object|freq' command"),
+        _ => Resp::error(format!(
+            "ERR unknown subcommand '{}'. Try OBJECT HELP.",
+            arg(&args, 0)
+        )),
+    }
+}
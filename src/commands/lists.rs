@@ -0,0 +1,224 @@
+use crate::commands::handler::{
+    arg, arg_bytes, bulk_array, bulk_or_null, bytes_from, int_reply, parse_int, syntax_error,
+};
+use crate::resp::resp::Resp;
+use crate::types::list_type::{ListEnd, ListType};
+
+fn push(args: Vec<Resp>, end: ListEnd, only_if_exists: bool) -> Resp {
+    int_reply(ListType::push(
+        &arg_bytes(&args, 0),
+        &bytes_from(&args, 1),
+        end,
+        only_if_exists,
+    ))
+}
+
+pub fn lpush(args: Vec<Resp>) -> Resp {
+    push(args, ListEnd::Left, false)
+}
+
+pub fn rpush(args: Vec<Resp>) -> Resp {
+    push(args, ListEnd::Right, false)
+}
+
+pub fn lpushx(args: Vec<Resp>) -> Resp {
+    push(args, ListEnd::Left, true)
+}
+
+pub fn rpushx(args: Vec<Resp>) -> Resp {
+    push(args, ListEnd::Right, true)
+}
+
+/// LPOP/RPOP key [count]
+fn pop(args: Vec<Resp>, end: ListEnd) -> Resp {
+    let key = arg_bytes(&args, 0);
+    if args.len() == 1 {
+        return bulk_or_null(ListType::pop(&key, 1, end).map(|v| v.and_then(|mut v| v.pop())));
+    }
+    if args.len() > 2 {
+        return syntax_error();
+    }
+    let count = match parse_int(&args[1]) {
+        Ok(n) if n >= 0 => n as usize,
+        Ok(_) => return Resp::error("ERR value is out of range, must be positive"),
+        Err(e) => return e,
+    };
+    match ListType::pop(&key, count, end) {
+        Ok(Some(values)) => Resp::array(values.into_iter().map(Resp::bulk).collect()),
+        Ok(None) => Resp::null_array(),
+        Err(e) => Resp::error(e),
+    }
+}
+
+pub fn lpop(args: Vec<Resp>) -> Resp {
+    pop(args, ListEnd::Left)
+}
+
+pub fn rpop(args: Vec<Resp>) -> Resp {
+    pop(args, ListEnd::Right)
+}
+
+pub fn llen(args: Vec<Resp>) -> Resp {
+    int_reply(ListType::llen(&arg_bytes(&args, 0)))
+}
+
+pub fn lrange(args: Vec<Resp>) -> Resp {
+    match (parse_int(&args[1]), parse_int(&args[2])) {
+        (Ok(start), Ok(stop)) => bulk_array(ListType::lrange(&arg_bytes(&args, 0), start, stop)),
+        (Err(e), _) | (_, Err(e)) => e,
+    }
+}
+
+pub fn lindex(args: Vec<Resp>) -> Resp {
+    match parse_int(&args[1]) {
+        Ok(index) => bulk_or_null(ListType::lindex(&arg_bytes(&args, 0), index)),
+        Err(e) => e,
+    }
+}
+
+pub fn lset(args: Vec<Resp>) -> Resp {
+    let index = match parse_int(&args[1]) {
+        Ok(n) => n,
+        Err(e) => return e,
+    };
+    match ListType::lset(&arg_bytes(&args, 0), index, &arg_bytes(&args, 2)) {
+        Ok(()) => Resp::ok(),
+        Err(e) => Resp::error(e),
+    }
+}
+
+/// LINSERT key BEFORE|AFTER pivot element
+pub fn linsert(args: Vec<Resp>) -> Resp {
+    let position = arg(&args, 1);
+    let before = if position.eq_ignore_ascii_case("BEFORE") {
+        true
+    } else if position.eq_ignore_ascii_case("AFTER") {
+        false
+    } else {
+        return syntax_error();
+    };
+    int_reply(ListType::linsert(
+        &arg_bytes(&args, 0),
+        before,
+        &arg_bytes(&args, 2),
+        &arg_bytes(&args, 3),
+    ))
+}
+
+pub fn lrem(args: Vec<Resp>) -> Resp {
+    match parse_int(&args[1]) {
+        Ok(count) => int_reply(ListType::lrem(
+            &arg_bytes(&args, 0),
+            count,
+            &arg_bytes(&args, 2),
+        )),
+        Err(e) => e,
+    }
+}
+
+pub fn ltrim(args: Vec<Resp>) -> Resp {
+    let (start, stop) = match (parse_int(&args[1]), parse_int(&args[2])) {
+        (Ok(start), Ok(stop)) => (start, stop),
+        (Err(e), _) | (_, Err(e)) => return e,
+    };
+    match ListType::ltrim(&arg_bytes(&args, 0), start, stop) {
+        Ok(()) => Resp::ok(),
+        Err(e) => Resp::error(e),
+    }
+}
+
+/// LPOS key element [RANK rank] [COUNT num-matches] [MAXLEN len]
+pub fn lpos(args: Vec<Resp>) -> Resp {
+    let mut rank = 1;
+    let mut count = None;
+    let mut maxlen = 0;
+
+    let mut i = 2;
+    while i < args.len() {
+        if i + 1 >= args.len() {
+            return syntax_error();
+        }
+        let n = match parse_int(&args[i + 1]) {
+            Ok(n) => n,
+            Err(e) => return e,
+        };
+        match arg(&args, i).to_uppercase().as_str() {
+            "RANK" if n == 0 => {
+                return Resp::error(
+                    "ERR RANK can't be zero: use 1 to start from the first match, 2 from the second ... or use negative to start from the end of the list",
+                );
+            }
+            "RANK" => rank = n,
+            "COUNT" if n < 0 => return Resp::error("ERR COUNT can't be negative"),
+            "COUNT" => count = Some(n as usize),
+            "MAXLEN" if n < 0 => return Resp::error("ERR MAXLEN can't be negative"),
+            "MAXLEN" => maxlen = n as usize,
+            _ => return syntax_error(),
+        }
+        i += 2;
+    }
+
+    let result = ListType::lpos(
+        &arg_bytes(&args, 0),
+        &arg_bytes(&args, 1),
+        rank,
+        count.unwrap_or(1),
+        maxlen,
+    );
+    match (result, count) {
+        (Ok(positions), Some(_)) => Resp::array(positions.into_iter().map(Resp::int).collect()),
+        (Ok(positions), None) => positions.first().map_or_else(Resp::null, |&p| Resp::int(p)),
+        (Err(e), _) => Resp::error(e),
+    }
+}
+
+/// LMOVE source destination LEFT|RIGHT LEFT|RIGHT
+pub fn lmove(args: Vec<Resp>) -> Resp {
+    match (
+        ListEnd::parse(&arg(&args, 2)),
+        ListEnd::parse(&arg(&args, 3)),
+    ) {
+        (Some(from), Some(to)) => bulk_or_null(ListType::lmove(
+            &arg_bytes(&args, 0),
+            &arg_bytes(&args, 1),
+            from,
+            to,
+        )),
+        _ => syntax_error(),
+    }
+}
+
+/// LMPOP numkeys key [key ...] LEFT|RIGHT [COUNT count]
+pub fn lmpop(args: Vec<Resp>) -> Resp {
+    let numkeys = match parse_int(&args[0]) {
+        Ok(n) if n > 0 => n as usize,
+        Ok(_) => return Resp::error("ERR numkeys should be greater than 0"),
+        Err(e) => return e,
+    };
+    if args.len() < numkeys + 2 {
+        return syntax_error();
+    }
+    let keys: Vec<Vec<u8>> = (1..=numkeys).map(|i| arg_bytes(&args, i)).collect();
+    let Some(end) = ListEnd::parse(&arg(&args, numkeys + 1)) else {
+        return syntax_error();
+    };
+    let count = match args.len() - (numkeys + 2) {
+        0 => 1,
+        2 if arg(&args, numkeys + 2).eq_ignore_ascii_case("COUNT") => {
+            match parse_int(&args[numkeys + 3]) {
+                Ok(n) if n > 0 => n as usize,
+                Ok(_) => return Resp::error("ERR count should be greater than 0"),
+                Err(e) => return e,
+            }
+        }
+        _ => return syntax_error(),
+    };
+    match ListType::lmpop(&keys, end, count) {
+        Ok(Some((key, values))) => Resp::array(vec![
+            Resp::bulk(key),
+            Resp::array(values.into_iter().map(Resp::bulk).collect()),
+        ]),
+        Ok(None) => Resp::null_array(),
+        Err(e) => Resp::error(e),
+    }
+}
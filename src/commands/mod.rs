@@ -1 +1,11 @@
+pub mod bitmaps;
+pub mod connection;
 pub mod handler;
+pub mod hashes;
+pub mod keys;
+pub mod lists;
+pub mod server;
+pub mod sets;
+pub mod streams;
+pub mod strings;
+pub mod zsets;
@@ -0,0 +1,110 @@
+use std::fmt::Write;
+
+use crate::commands::handler::{arg, args_from};
+use crate::config::{Config, IO_THREADS, MAXMEMORY, MAXMEMORY_POLICY};
+use crate::net::stats::ThreadStats;
+use crate::resp::resp::Resp;
+use crate::store::store::global_store;
+
+/// Renders one INFO section.
+type InfoSection = fn() -> String;
+
+/// INFO sections in the order they're listed.
+const INFO_SECTIONS: &[(&str, InfoSection)] = &[
+    ("server", info_server),
+    ("memory", info_memory),
+    ("stats", info_stats),
+    ("threads", info_threads),
+];
+
+/// CONFIG GET parameter [parameter ...] | CONFIG SET parameter value [parameter value ...]
+pub fn config(args: Vec<Resp>) -> Resp {
+    let sub = arg(&args, 0).to_uppercase();
+    match sub.as_str() {
+        "GET" if args.len() >= 2 => Resp::map(
+            Config::get(&args_from(&args, 1))
+                .into_iter()
+                .map(|(name, value)| (Resp::bulk(name), Resp::bulk(value)))
+                .collect(),
+        ),
+        "SET" if args.len() >= 3 && args.len() % 2 == 1 => {
+            let pairs: Vec<(String, String)> = (1..args.len())
+                .step_by(2)
+                .map(|i| (arg(&args, i), arg(&args, i + 1)))
+                .collect();
+            match Config::set(&pairs) {
+                Ok(()) => Resp::ok(),
+                Err(e) => Resp::error(e),
+            }
+        }
+        "GET" | "SET" => Resp::error(format!(
+            "ERR wrong number of arguments for 'config|{}' command",
+            sub.to_lowercase()
+        )),
+        _ => Resp::error(format!(
+            "ERR unknown subcommand '{}'. Try CONFIG HELP.",
+            arg(&args, 0)
+        )),
+    }
+}
+
+/// INFO [section [section ...]]
+pub fn info(args: Vec<Resp>) -> Resp {
+    let requested: Vec<String> = args_from(&args, 0)
+        .into_iter()
+        .map(|s| s.to_lowercase())
+        .collect();
+    let all = requested.is_empty()
+        || requested
+            .iter()
+            .any(|s| matches!(s.as_str(), "all" | "default" | "everything"));
+    let text = INFO_SECTIONS
+        .iter()
+        .filter(|(name, _)| all || requested.iter().any(|s| s == name))
+        .map(|(_, render)| render())
+        .collect::<Vec<_>>()
+        .join("\r\n");
+    Resp::verbatim("txt", text)
+}
+
+fn info_server() -> String {
+    format!(
+        "# Server\r\nanimus_version:{}\r\nprocess_id:{}\r\nio_threads:{}\r\n",
+        env!("CARGO_PKG_VERSION"),
+        std::process::id(),
+        IO_THREADS.get()
+    )
+}
+
+fn info_memory() -> String {
+    format!(
+        "# Memory\r\nused_memory:{}\r\nmaxmemory:{}\r\nmaxmemory_policy:{}\r\n",
+        global_store().used_memory(),
+        MAXMEMORY.get(),
+        MAXMEMORY_POLICY.display()
+    )
+}
+
+fn info_stats() -> String {
+    format!(
+        "# Stats\r\nevicted_keys:{}\r\n",
+        global_store().evicted_keys()
+    )
+}
+
+/// One line per I/O thread and the executor, with the time each has spent busy,
+/// that as a percentage of its lifetime, and how many events or commands it handled.
+fn info_threads() -> String {
+    let mut text = "# Threads\r\n".to_string();
+    for stats in ThreadStats::all() {
+        let _ = write!(
+            text,
+            "{}:busy_usec={},utilization={:.2},ops={}\r\n",
+            stats.name,
+            stats.busy_usec(),
+            stats.utilization(),
+            stats.ops()
+        );
+    }
+    text
+}
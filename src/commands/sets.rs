@@ -0,0 +1,142 @@
+use crate::commands::handler::{
+    arg, arg_bytes, bool_reply, bulk_array, bytes_from, int_reply, parse_int, parse_rand_count,
+    syntax_error,
+};
+use crate::resp::resp::Resp;
+use crate::types::set_type::{SetOp, SetType};
+
+/// Replies with `members` as a set, which RESP2 clients receive as an array.
+fn set_reply(result: Result<Vec<Vec<u8>>, String>) -> Resp {
+    match result {
+        Ok(members) => Resp::set(members.into_iter().map(Resp::bulk).collect()),
+        Err(e) => Resp::error(e),
+    }
+}
+
+pub fn sadd(args: Vec<Resp>) -> Resp {
+    int_reply(SetType::sadd(&arg_bytes(&args, 0), &bytes_from(&args, 1)))
+}
+
+pub fn srem(args: Vec<Resp>) -> Resp {
+    int_reply(SetType::srem(&arg_bytes(&args, 0), &bytes_from(&args, 1)))
+}
+
+pub fn scard(args: Vec<Resp>) -> Resp {
+    int_reply(SetType::scard(&arg_bytes(&args, 0)))
+}
+
+pub fn smembers(args: Vec<Resp>) -> Resp {
+    set_reply(SetType::smembers(&arg_bytes(&args, 0)))
+}
+
+pub fn sismember(args: Vec<Resp>) -> Resp {
+    bool_reply(SetType::sismember(
+        &arg_bytes(&args, 0),
+        &arg_bytes(&args, 1),
+    ))
+}
+
+pub fn smismember(args: Vec<Resp>) -> Resp {
+    match SetType::smismember(&arg_bytes(&args, 0), &bytes_from(&args, 1)) {
+        Ok(found) => Resp::array(found.into_iter().map(|f| Resp::int(f.into())).collect()),
+        Err(e) => Resp::error(e),
+    }
+}
+
+/// SPOP key [count]
+pub fn spop(args: Vec<Resp>) -> Resp {
+    let key = arg_bytes(&args, 0);
+    match args.len() {
+        1 => match SetType::spop(&key, 1) {
+            Ok(mut popped) => popped.pop().map_or_else(Resp::null, Resp::bulk),
+            Err(e) => Resp::error(e),
+        },
+        2 => match parse_int(&args[1]) {
+            Ok(n) if n >= 0 => bulk_array(SetType::spop(&key, n as usize)),
+            Ok(_) => Resp::error("ERR value is out of range, must be positive"),
+            Err(e) => e,
+        },
+        _ => syntax_error(),
+    }
+}
+
+/// SRANDMEMBER key [count]
+pub fn srandmember(args: Vec<Resp>) -> Resp {
+    let key = arg_bytes(&args, 0);
+    match args.len() {
+        1 => match SetType::srandmember(&key, 1) {
+            Ok(mut members) => members.pop().map_or_else(Resp::null, Resp::bulk),
+            Err(e) => Resp::error(e),
+        },
+        2 => match parse_rand_count(&args[1]) {
+            Ok(n) => bulk_array(SetType::srandmember(&key, n)),
+            Err(e) => e,
+        },
+        _ => syntax_error(),
+    }
+}
+
+pub fn smove(args: Vec<Resp>) -> Resp {
+    bool_reply(SetType::smove(
+        &arg_bytes(&args, 0),
+        &arg_bytes(&args, 1),
+        &arg_bytes(&args, 2),
+    ))
+}
+
+pub fn sdiff(args: Vec<Resp>) -> Resp {
+    set_reply(SetType::sdiff(&bytes_from(&args, 0)))
+}
+
+pub fn sinter(args: Vec<Resp>) -> Resp {
+    set_reply(SetType::sinter(&bytes_from(&args, 0)))
+}
+
+pub fn sunion(args: Vec<Resp>) -> Resp {
+    set_reply(SetType::sunion(&bytes_from(&args, 0)))
+}
+
+/// SINTERCARD numkeys key [key ...] [LIMIT limit]
+pub fn sintercard(args: Vec<Resp>) -> Resp {
+    let numkeys = match parse_int(&args[0]) {
+        Ok(n) if n > 0 => n as usize,
+        Ok(_) => return Resp::error("ERR numkeys should be greater than 0"),
+        Err(e) => return e,
+    };
+    if args.len() < numkeys + 1 {
+        return Resp::error("ERR Number of keys can't be greater than number of args");
+    }
+    let keys: Vec<Vec<u8>> = (1..=numkeys).map(|i| arg_bytes(&args, i)).collect();
+    let limit = match args.len() - (numkeys + 1) {
+        0 => 0,
+        2 if arg(&args, numkeys + 1).eq_ignore_ascii_case("LIMIT") => {
+            match parse_int(&args[numkeys + 2]) {
+                Ok(n) if n >= 0 => n as usize,
+                Ok(_) => return Resp::error("ERR LIMIT can't be negative"),
+                Err(e) => return e,
+            }
+        }
+        _ => return syntax_error(),
+    };
+    int_reply(SetType::sintercard(&keys, limit))
+}
+
+fn store(args: Vec<Resp>, op: SetOp) -> Resp {
+    int_reply(SetType::store(
+        &arg_bytes(&args, 0),
+        &bytes_from(&args, 1),
+        op,
+    ))
+}
+
+pub fn sdiffstore(args: Vec<Resp>) -> Resp {
+    store(args, SetOp::Diff)
+}
+
+pub fn sinterstore(args: Vec<Resp>) -> Resp {
+    store(args, SetOp::Inter)
+}
+
+pub fn sunionstore(args: Vec<Resp>) -> Resp {
+    store(args, SetOp::Union)
+}
@@ -0,0 +1,712 @@
+use crate::commands::handler::{arg, arg_bytes, int_reply, parse_int, syntax_error};
+use crate::resp::resp::Resp;
+use crate::store::stream::{ClaimOptions, NewId, StreamId, Trim};
+use crate::types::stream_type::{Entry, GroupEntry, StreamType, TrimOptions};
+
+const INVALID_ID: &str = "ERR Invalid stream ID specified as stream command argument";
+
+fn invalid_id() -> Resp {
+    Resp::error(INVALID_ID)
+}
+
+fn entry_reply((id, fields): Entry) -> Resp {
+    let fields = fields
+        .into_iter()
+        .flat_map(|(f, v)| [Resp::bulk(f), Resp::bulk(v)])
+        .collect();
+    Resp::array(vec![Resp::bulk(id.to_string()), Resp::array(fields)])
+}
+
+fn entries_reply(entries: Vec<Entry>) -> Resp {
+    Resp::array(entries.into_iter().map(entry_reply).collect())
+}
+
+fn ids_reply(ids: impl IntoIterator<Item = StreamId>) -> Resp {
+    Resp::array(
+        ids.into_iter()
+            .map(|id| Resp::bulk(id.to_string()))
+            .collect(),
+    )
+}
+
+/// Splits the arguments after STREAMS into keys and IDs.
+fn split_streams<'a>(rest: &'a [Resp], cmd: &str) -> Result<(&'a [Resp], &'a [Resp]), Resp> {
+    if rest.is_empty() || !rest.len().is_multiple_of(2) {
+        return Err(Resp::error(format!(
+            "ERR Unbalanced '{cmd}' list of streams: for each stream key an ID or '$' must be specified."
+        )));
+    }
+    Ok(rest.split_at(rest.len() / 2))
+}
+
+fn parse_id(r: &Resp, default_seq: u64) -> Result<StreamId, Resp> {
+    r.as_string()
+        .and_then(|s| StreamId::parse(&s, default_seq))
+        .ok_or_else(invalid_id)
+}
+
+/// Parses a range bound: `-`, `+`, an (optionally incomplete) ID, or `(` plus an
+/// ID for an exclusive bound. Returns `None` if an exclusive bound leaves nothing.
+fn parse_range_bound(r: &Resp, is_start: bool) -> Result<Option<StreamId>, Resp> {
+    let s = r.as_string().unwrap_or_default();
+    let default_seq = if is_start { 0 } else { u64::MAX };
+    match s.as_str() {
+        "-" => Ok(Some(StreamId::MIN)),
+        "+" => Ok(Some(StreamId::MAX)),
+        _ => match s.strip_prefix('(') {
+            Some(rest) => {
+                let id = StreamId::parse(rest, default_seq).ok_or_else(invalid_id)?;
+                Ok(if is_start { id.next() } else { id.prev() })
+            }
+            None => StreamId::parse(&s, default_seq)
+                .map(Some)
+                .ok_or_else(invalid_id),
+        },
+    }
+}
+
+/// Parses `MAXLEN | MINID [= | ~] threshold [LIMIT count]` starting at `args[i]`.
+/// Returns the options and the index of the first argument after them.
+fn parse_trim(args: &[Resp], mut i: usize) -> Result<(TrimOptions, usize), Resp> {
+    let strategy = arg(args, i).to_uppercase();
+    i += 1;
+    let approx = match arg(args, i).as_str() {
+        "~" => true,
+        "=" => false,
+        _ => {
+            i -= 1;
+            false
+        }
+    };
+    i += 1;
+    let Some(threshold) = args.get(i) else {
+        return Err(syntax_error());
+    };
+    let by = match strategy.as_str() {
+        "MAXLEN" => match parse_int(threshold)? {
+            n if n >= 0 => Trim::MaxLen(n as usize),
+            _ => return Err(Resp::error("ERR The MAXLEN argument must be >= 0.")),
+        },
+        _ => Trim::MinId(parse_id(threshold, 0)?),
+    };
+    i += 1;
+
+    let mut limit = 0;
+    if arg(args, i).eq_ignore_ascii_case("LIMIT") {
+        if !approx {
+            return Err(Resp::error(
+                "ERR syntax error, LIMIT cannot be used without the special ~ option",
+            ));
+        }
+        let Some(count) = args.get(i + 1) else {
+            return Err(syntax_error());
+        };
+        limit = match parse_int(count)? {
+            n if n >= 0 => n as usize,
+            _ => return Err(Resp::error("ERR The LIMIT argument must be >= 0.")),
+        };
+        i += 2;
+    }
+    Ok((TrimOptions { by, limit }, i))
+}
+
+/// XADD key [NOMKSTREAM] [MAXLEN | MINID [= | ~] threshold [LIMIT count]] <* | id> field value [field value ...]
+pub fn xadd(args: Vec<Resp>) -> Resp {
+    let mut no_mkstream = false;
+    let mut trim = None;
+    let mut i = 1;
+    loop {
+        match arg(&args, i).to_uppercase().as_str() {
+            "NOMKSTREAM" => {
+                no_mkstream = true;
+                i += 1;
+            }
+            "MAXLEN" | "MINID" => match parse_trim(&args, i) {
+                Ok((opts, next)) => {
+                    trim = Some(opts);
+                    i = next;
+                }
+                Err(e) => return e,
+            },
+            _ => break,
+        }
+    }
+
+    let rest = args.get(i + 1..).unwrap_or_default();
+    if rest.is_empty() || !rest.len().is_multiple_of(2) {
+        return Resp::error("ERR wrong number of arguments for 'xadd' command");
+    }
+    let id = arg(&args, i);
+    let id = if id == "*" {
+        NewId::Auto
+    } else if let Some(ms) = id.strip_suffix("-*") {
+        match ms.parse() {
+            Ok(ms) => NewId::AutoSeq(ms),
+            Err(_) => return invalid_id(),
+        }
+    } else {
+        match StreamId::parse(&id, 0) {
+            Some(id) => NewId::Explicit(id),
+            None => return invalid_id(),
+        }
+    };
+    let fields = rest
+        .chunks(2)
+        .map(|pair| {
+            (
+                pair[0].as_bytes().unwrap_or_default(),
+                pair[1].as_bytes().unwrap_or_default(),
+            )
+        })
+        .collect();
+
+    match StreamType::xadd(&arg_bytes(&args, 0), id, fields, no_mkstream, trim) {
+        Ok(Some(id)) => Resp::bulk(id.to_string()),
+        Ok(None) => Resp::null(),
+        Err(e) => Resp::error(e),
+    }
+}
+
+pub fn xlen(args: Vec<Resp>) -> Resp {
+    int_reply(StreamType::xlen(&arg_bytes(&args, 0)))
+}
+
+/// XRANGE key start end [COUNT count], or XREVRANGE key end start [COUNT count]
+fn range(args: Vec<Resp>, rev: bool) -> Resp {
+    let (start, end) = if rev {
+        (&args[2], &args[1])
+    } else {
+        (&args[1], &args[2])
+    };
+    let count = match args.len() {
+        3 => None,
+        5 if arg(&args, 3).eq_ignore_ascii_case("COUNT") => match parse_int(&args[4]) {
+            Ok(n) => Some(n.max(0) as usize),
+            Err(e) => return e,
+        },
+        _ => return syntax_error(),
+    };
+    let bounds = parse_range_bound(start, true)
+        .and_then(|start| parse_range_bound(end, false).map(|end| start.zip(end)));
+    match bounds {
+        Ok(Some((start, end))) => {
+            match StreamType::xrange(&arg_bytes(&args, 0), start, end, count, rev) {
+                Ok(entries) => entries_reply(entries),
+                Err(e) => Resp::error(e),
+            }
+        }
+        Ok(None) => Resp::array(vec![]),
+        Err(e) => e,
+    }
+}
+
+pub fn xrange(args: Vec<Resp>) -> Resp {
+    range(args, false)
+}
+
+pub fn xrevrange(args: Vec<Resp>) -> Resp {
+    range(args, true)
+}
+
+pub fn xdel(args: Vec<Resp>) -> Resp {
+    let ids: Result<Vec<StreamId>, Resp> = args[1..].iter().map(|r| parse_id(r, 0)).collect();
+    match ids {
+        Ok(ids) => int_reply(StreamType::xdel(&arg_bytes(&args, 0), &ids)),
+        Err(e) => e,
+    }
+}
+
+/// XTRIM key MAXLEN | MINID [= | ~] threshold [LIMIT count]
+pub fn xtrim(args: Vec<Resp>) -> Resp {
+    if !matches!(arg(&args, 1).to_uppercase().as_str(), "MAXLEN" | "MINID") {
+        return syntax_error();
+    }
+    match parse_trim(&args, 1) {
+        Ok((trim, next)) if next == args.len() => {
+            int_reply(StreamType::xtrim(&arg_bytes(&args, 0), trim))
+        }
+        Ok(_) => syntax_error(),
+        Err(e) => e,
+    }
+}
+
+/// XREAD [COUNT count] STREAMS key [key ...] id [id ...]
+pub fn xread(args: Vec<Resp>) -> Resp {
+    let mut count = None;
+    let mut i = 0;
+    loop {
+        match arg(&args, i).to_uppercase().as_str() {
+            "COUNT" if i + 1 < args.len() => match parse_int(&args[i + 1]) {
+                Ok(n) => {
+                    count = (n > 0).then_some(n as usize);
+                    i += 2;
+                }
+                Err(e) => return e,
+            },
+            "BLOCK" => return Resp::error("ERR XREAD BLOCK is not supported"),
+            "STREAMS" => break,
+            _ => return syntax_error(),
+        }
+    }
+
+    let (keys, ids) = match split_streams(&args[i + 1..], "xread") {
+        Ok(split) => split,
+        Err(e) => return e,
+    };
+    let mut streams = Vec::with_capacity(keys.len());
+    for (key, id) in keys.iter().zip(ids) {
+        // Without blocking, `$` (only entries added from now on) never matches anything.
+        let after = match id.as_string().as_deref() {
+            Some("$") => StreamId::MAX,
+            _ => match parse_id(id, 0) {
+                Ok(id) => id,
+                Err(e) => return e,
+            },
+        };
+        streams.push((key.as_bytes().unwrap_or_default(), after));
+    }
+
+    match StreamType::xread(&streams, count) {
+        Ok(result) if result.is_empty() => Resp::null_array(),
+        Ok(result) => Resp::array(
+            result
+                .into_iter()
+                .map(|(key, entries)| Resp::array(vec![Resp::bulk(key), entries_reply(entries)]))
+                .collect(),
+        ),
+        Err(e) => Resp::error(e),
+    }
+}
+
+/// Parses a consumer group start ID, where `$` (the last entry) becomes `None`.
+fn parse_group_start(r: &Resp) -> Result<Option<StreamId>, Resp> {
+    match r.as_string().as_deref() {
+        Some("$") => Ok(None),
+        _ => parse_id(r, 0).map(Some),
+    }
+}
+
+fn parse_ids(args: &[Resp]) -> Result<Vec<StreamId>, Resp> {
+    args.iter().map(|r| parse_id(r, 0)).collect()
+}
+
+fn parse_non_negative(r: &Resp) -> Result<u64, Resp> {
+    parse_int(r).map(|n| n.max(0) as u64)
+}
+
+/// XGROUP CREATE | SETID | DESTROY | CREATECONSUMER | DELCONSUMER key group ...
+pub fn xgroup(args: Vec<Resp>) -> Resp {
+    let sub = arg(&args, 0).to_uppercase();
+    let arity_ok = match sub.as_str() {
+        "CREATE" => (4..=5).contains(&args.len()),
+        "SETID" => args.len() == 4,
+        "DESTROY" => args.len() == 3,
+        "CREATECONSUMER" | "DELCONSUMER" => args.len() == 4,
+        _ => {
+            return Resp::error(format!(
+                "ERR unknown subcommand '{}'. Try XGROUP HELP.",
+                arg(&args, 0)
+            ));
+        }
+    };
+    if !arity_ok {
+        return Resp::error(format!(
+            "ERR wrong number of arguments for 'xgroup|{}' command",
+            sub.to_lowercase()
+        ));
+    }
+
+    let (key, group) = (arg_bytes(&args, 1), arg(&args, 2));
+    let result = match sub.as_str() {
+        "CREATE" => {
+            let mkstream = match args.get(4).and_then(Resp::as_string) {
+                None => false,
+                Some(opt) if opt.eq_ignore_ascii_case("MKSTREAM") => true,
+                Some(_) => return syntax_error(),
+            };
+            match parse_group_start(&args[3]) {
+                Ok(start) => {
+                    StreamType::xgroup_create(&key, &group, start, mkstream).map(|_| Resp::ok())
+                }
+                Err(e) => return e,
+            }
+        }
+        "SETID" => match parse_group_start(&args[3]) {
+            Ok(start) => StreamType::xgroup_setid(&key, &group, start).map(|_| Resp::ok()),
+            Err(e) => return e,
+        },
+        "DESTROY" => StreamType::xgroup_destroy(&key, &group).map(|d| Resp::int(d.into())),
+        "CREATECONSUMER" => StreamType::xgroup_createconsumer(&key, &group, &arg(&args, 3))
+            .map(|c| Resp::int(c.into())),
+        _ => StreamType::xgroup_delconsumer(&key, &group, &arg(&args, 3)).map(Resp::int),
+    };
+    result.unwrap_or_else(Resp::error)
+}
+
+/// XREADGROUP GROUP group consumer [COUNT count] [NOACK] STREAMS key [key ...] id [id ...]
+pub fn xreadgroup(args: Vec<Resp>) -> Resp {
+    if !arg(&args, 0).eq_ignore_ascii_case("GROUP") || args.len() < 3 {
+        return syntax_error();
+    }
+    let (group, consumer) = (arg(&args, 1), arg(&args, 2));
+    let mut count = None;
+    let mut no_ack = false;
+    let mut i = 3;
+    loop {
+        match arg(&args, i).to_uppercase().as_str() {
+            "COUNT" if i + 1 < args.len() => match parse_int(&args[i + 1]) {
+                Ok(n) => {
+                    count = (n > 0).then_some(n as usize);
+                    i += 2;
+                }
+                Err(e) => return e,
+            },
+            "NOACK" => {
+                no_ack = true;
+                i += 1;
+            }
+            "BLOCK" => return Resp::error("ERR XREADGROUP BLOCK is not supported"),
+            "STREAMS" => break,
+            _ => return syntax_error(),
+        }
+    }
+
+    let (keys, ids) = match split_streams(&args[i + 1..], "xreadgroup") {
+        Ok(split) => split,
+        Err(e) => return e,
+    };
+    let mut streams = Vec::with_capacity(keys.len());
+    for (key, id) in keys.iter().zip(ids) {
+        let from = match id.as_string().as_deref() {
+            Some(">") => None,
+            _ => match parse_id(id, 0) {
+                Ok(id) => Some(id),
+                Err(e) => return e,
+            },
+        };
+        streams.push((key.as_bytes().unwrap_or_default(), from));
+    }
+
+    let group_entry_reply = |(id, fields): GroupEntry| match fields {
+        Some(fields) => entry_reply((id, fields)),
+        None => Resp::array(vec![Resp::bulk(id.to_string()), Resp::null_array()]),
+    };
+    match StreamType::xreadgroup(&group, &consumer, &streams, count, no_ack) {
+        Ok(result) if result.is_empty() => Resp::null_array(),
+        Ok(result) => Resp::array(
+            result
+                .into_iter()
+                .map(|(key, entries)| {
+                    let entries = entries.into_iter().map(group_entry_reply).collect();
+                    Resp::array(vec![Resp::bulk(key), Resp::array(entries)])
+                })
+                .collect(),
+        ),
+        Err(e) => Resp::error(e),
+    }
+}
+
+/// XACK key group id [id ...]
+pub fn xack(args: Vec<Resp>) -> Resp {
+    match parse_ids(&args[2..]) {
+        Ok(ids) => int_reply(StreamType::xack(&arg_bytes(&args, 0), &arg(&args, 1), &ids)),
+        Err(e) => e,
+    }
+}
+
+/// XPENDING key group [[IDLE min-idle-time] start end count [consumer]]
+pub fn xpending(args: Vec<Resp>) -> Resp {
+    let (key, group) = (arg_bytes(&args, 0), arg(&args, 1));
+    if args.len() == 2 {
+        return match StreamType::xpending(&key, &group) {
+            Ok(summary) => {
+                let consumers = summary
+                    .consumers
+                    .into_iter()
+                    .map(|(name, n)| Resp::array(vec![Resp::bulk(name), Resp::bulk(n.to_string())]))
+                    .collect();
+                match summary.range {
+                    Some((first, last)) => Resp::array(vec![
+                        Resp::int(summary.count as i64),
+                        Resp::bulk(first.to_string()),
+                        Resp::bulk(last.to_string()),
+                        Resp::array(consumers),
+                    ]),
+                    None => Resp::array(vec![
+                        Resp::int(0),
+                        Resp::null(),
+                        Resp::null(),
+                        Resp::null_array(),
+                    ]),
+                }
+            }
+            Err(e) => Resp::error(e),
+        };
+    }
+
+    let mut i = 2;
+    let mut min_idle = 0;
+    if arg(&args, i).eq_ignore_ascii_case("IDLE") {
+        min_idle = match args.get(i + 1).map(parse_non_negative) {
+            Some(Ok(n)) => n,
+            Some(Err(e)) => return e,
+            None => return syntax_error(),
+        };
+        i += 2;
+    }
+    if !(i + 3..=i + 4).contains(&args.len()) {
+        return syntax_error();
+    }
+    let bounds = parse_range_bound(&args[i], true)
+        .and_then(|start| parse_range_bound(&args[i + 1], false).map(|end| start.zip(end)));
+    let (start, end) = match bounds {
+        Ok(Some(bounds)) => bounds,
+        Ok(None) => return Resp::array(vec![]),
+        Err(e) => return e,
+    };
+    let count = match parse_int(&args[i + 2]) {
+        Ok(n) => n.max(0) as usize,
+        Err(e) => return e,
+    };
+    let consumer = args.get(i + 3).and_then(Resp::as_string);
+
+    match StreamType::xpending_range(
+        &key,
+        &group,
+        start,
+        end,
+        count,
+        consumer.as_deref(),
+        min_idle,
+    ) {
+        Ok(pending) => Resp::array(
+            pending
+                .into_iter()
+                .map(|p| {
+                    Resp::array(vec![
+                        Resp::bulk(p.id.to_string()),
+                        Resp::bulk(p.consumer),
+                        Resp::int(p.idle as i64),
+                        Resp::int(p.delivery_count as i64),
+                    ])
+                })
+                .collect(),
+        ),
+        Err(e) => Resp::error(e),
+    }
+}
+
+/// XCLAIM key group consumer min-idle-time id [id ...] [IDLE ms] [TIME unix-time-milliseconds]
+/// [RETRYCOUNT count] [FORCE] [JUSTID] [LASTID lastid]
+pub fn xclaim(args: Vec<Resp>) -> Resp {
+    let min_idle = match parse_non_negative(&args[3]) {
+        Ok(n) => n,
+        Err(e) => return e,
+    };
+    let mut opts = ClaimOptions {
+        min_idle,
+        ..Default::default()
+    };
+
+    // IDs run up to the first option.
+    let mut ids = Vec::new();
+    let mut i = 4;
+    while i < args.len() {
+        match parse_id(&args[i], 0) {
+            Ok(id) => ids.push(id),
+            Err(_) if !ids.is_empty() => break,
+            Err(e) => return e,
+        }
+        i += 1;
+    }
+    while i < args.len() {
+        let value = args.get(i + 1);
+        match (arg(&args, i).to_uppercase().as_str(), value) {
+            ("FORCE", _) => opts.force = true,
+            ("JUSTID", _) => opts.just_id = true,
+            ("IDLE", Some(v)) => match parse_non_negative(v) {
+                Ok(n) => opts.idle = Some(n),
+                Err(e) => return e,
+            },
+            ("TIME", Some(v)) => match parse_non_negative(v) {
+                Ok(n) => opts.time = Some(n),
+                Err(e) => return e,
+            },
+            ("RETRYCOUNT", Some(v)) => match parse_non_negative(v) {
+                Ok(n) => opts.retry_count = Some(n),
+                Err(e) => return e,
+            },
+            ("LASTID", Some(v)) => match parse_id(v, 0) {
+                Ok(id) => opts.last_id = Some(id),
+                Err(e) => return e,
+            },
+            _ => return syntax_error(),
+        }
+        i += if matches!(arg(&args, i).to_uppercase().as_str(), "FORCE" | "JUSTID") {
+            1
+        } else {
+            2
+        };
+    }
+
+    match StreamType::xclaim(
+        &arg_bytes(&args, 0),
+        &arg(&args, 1),
+        &arg(&args, 2),
+        &ids,
+        &opts,
+    ) {
+        Ok(claimed) if opts.just_id => ids_reply(claimed.into_iter().map(|(id, _)| id)),
+        Ok(claimed) => entries_reply(claimed),
+        Err(e) => Resp::error(e),
+    }
+}
+
+/// XAUTOCLAIM key group consumer min-idle-time start [COUNT count] [JUSTID]
+pub fn xautoclaim(args: Vec<Resp>) -> Resp {
+    let min_idle = match parse_non_negative(&args[3]) {
+        Ok(n) => n,
+        Err(e) => return e,
+    };
+    let mut opts = ClaimOptions {
+        min_idle,
+        ..Default::default()
+    };
+    let start = match parse_range_bound(&args[4], true) {
+        Ok(Some(start)) => start,
+        Ok(None) => StreamId::MAX,
+        Err(e) => return e,
+    };
+    let mut count = 100;
+    let mut i = 5;
+    while i < args.len() {
+        match arg(&args, i).to_uppercase().as_str() {
+            "COUNT" if i + 1 < args.len() => {
+                count = match parse_int(&args[i + 1]) {
+                    Ok(n) if n > 0 => n as usize,
+                    Ok(_) => return Resp::error("ERR COUNT must be > 0"),
+                    Err(e) => return e,
+                };
+                i += 1;
+            }
+            "JUSTID" => opts.just_id = true,
+            _ => return syntax_error(),
+        }
+        i += 1;
+    }
+
+    match StreamType::xautoclaim(
+        &arg_bytes(&args, 0),
+        &arg(&args, 1),
+        &arg(&args, 2),
+        start,
+        count,
+        &opts,
+    ) {
+        Ok(result) => {
+            let claimed = if opts.just_id {
+                ids_reply(result.claimed.into_iter().map(|(id, _)| id))
+            } else {
+                entries_reply(result.claimed)
+            };
+            Resp::array(vec![
+                Resp::bulk(result.cursor.to_string()),
+                claimed,
+                ids_reply(result.deleted),
+            ])
+        }
+        Err(e) => Resp::error(e),
+    }
+}
+
+/// XINFO STREAM key | GROUPS key | CONSUMERS key group
+pub fn xinfo(args: Vec<Resp>) -> Resp {
+    let sub = arg(&args, 0).to_uppercase();
+    let expected = match sub.as_str() {
+        "STREAM" | "GROUPS" => 2,
+        "CONSUMERS" => 3,
+        _ => {
+            return Resp::error(format!(
+                "ERR unknown subcommand '{}'. Try XINFO HELP.",
+                arg(&args, 0)
+            ));
+        }
+    };
+    if args.len() != expected {
+        return Resp::error(format!(
+            "ERR wrong number of arguments for 'xinfo|{}' command",
+            sub.to_lowercase()
+        ));
+    }
+
+    let key = arg_bytes(&args, 1);
+    let field = |name: &str, value: Resp| [Resp::bulk(name), value];
+    let entry_or_null = |entry: Option<Entry>| entry.map_or_else(Resp::null, entry_reply);
+    let result = match sub.as_str() {
+        "STREAM" => StreamType::xinfo_stream(&key).map(|info| {
+            let first_id = info.first.as_ref().map_or(StreamId::MIN, |(id, _)| *id);
+            Resp::array(
+                [
+                    field("length", Resp::int(info.length as i64)),
+                    field("last-generated-id", Resp::bulk(info.last_id.to_string())),
+                    field(
+                        "max-deleted-entry-id",
+                        Resp::bulk(info.max_deleted_id.to_string()),
+                    ),
+                    field("entries-added", Resp::int(info.entries_added as i64)),
+                    field("recorded-first-entry-id", Resp::bulk(first_id.to_string())),
+                    field("groups", Resp::int(info.groups as i64)),
+                    field("first-entry", entry_or_null(info.first)),
+                    field("last-entry", entry_or_null(info.last)),
+                ]
+                .into_iter()
+                .flatten()
+                .collect(),
+            )
+        }),
+        "GROUPS" => StreamType::xinfo_groups(&key).map(|groups| {
+            Resp::array(
+                groups
+                    .into_iter()
+                    .map(|g| {
+                        Resp::array(
+                            [
+                                field("name", Resp::bulk(g.name)),
+                                field("consumers", Resp::int(g.consumers as i64)),
+                                field("pending", Resp::int(g.pending as i64)),
+                                field(
+                                    "last-delivered-id",
+                                    Resp::bulk(g.last_delivered.to_string()),
+                                ),
+                            ]
+                            .into_iter()
+                            .flatten()
+                            .collect(),
+                        )
+                    })
+                    .collect(),
+            )
+        }),
+        _ => StreamType::xinfo_consumers(&key, &arg(&args, 2)).map(|consumers| {
+            Resp::array(
+                consumers
+                    .into_iter()
+                    .map(|c| {
+                        Resp::array(
+                            [
+                                field("name", Resp::bulk(c.name)),
+                                field("pending", Resp::int(c.pending as i64)),
+                                field("idle", Resp::int(c.idle as i64)),
+                                field("inactive", Resp::int(c.inactive.map_or(-1, |t| t as i64))),
+                            ]
+                            .into_iter()
+                            .flatten()
+                            .collect(),
+                        )
+                    })
+                    .collect(),
+            )
+        }),
+    };
+    result.unwrap_or_else(Resp::error)
+}
@@ -0,0 +1,223 @@
+use std::collections::HashMap;
+
+use crate::commands::handler::{
+    arg, arg_bytes, bulk_or_null, bytes_from, int_reply, parse_float, parse_int, syntax_error,
+};
+use crate::config::PROTO_MAX_BULK_LEN;
+use crate::resp::resp::Resp;
+use crate::types::string_type::{SetCondition, StringType};
+
+/// Parses a relative expiration given in seconds (`EX`) or milliseconds (`PX`)
+/// into milliseconds.
+fn parse_expire(unit: &str, value: &Resp, cmd: &str) -> Result<u64, Resp> {
+    let n = parse_int(value)?;
+    let ms = if unit == "PX" {
+        Some(n)
+    } else {
+        n.checked_mul(1000)
+    };
+    match ms {
+        Some(ms) if ms > 0 => Ok(ms as u64),
+        _ => Err(Resp::error(format!(
+            "ERR invalid expire time in '{}' command",
+            cmd
+        ))),
+    }
+}
+
+pub fn append(args: Vec<Resp>) -> Resp {
+    int_reply(StringType::append(&arg_bytes(&args, 0), &arg_bytes(&args, 1)).map(|n| n as i64))
+}
+
+pub fn decr(args: Vec<Resp>) -> Resp {
+    int_reply(StringType::decr(&arg_bytes(&args, 0)))
+}
+
+pub fn decr_by(args: Vec<Resp>) -> Resp {
+    match parse_int(&args[1]) {
+        Ok(n) => int_reply(StringType::decr_by(&arg_bytes(&args, 0), n)),
+        Err(e) => e,
+    }
+}
+
+pub fn get(args: Vec<Resp>) -> Resp {
+    bulk_or_null(StringType::get(&arg_bytes(&args, 0)))
+}
+
+pub fn get_del(args: Vec<Resp>) -> Resp {
+    bulk_or_null(StringType::get_del(&arg_bytes(&args, 0)))
+}
+
+pub fn get_ex(args: Vec<Resp>) -> Resp {
+    let key = arg_bytes(&args, 0);
+    if args.len() == 1 {
+        return bulk_or_null(StringType::get(&key));
+    }
+    let opt = arg(&args, 1).to_uppercase();
+    let ttl = match (opt.as_str(), args.len()) {
+        ("PERSIST", 2) => None,
+        ("EX" | "PX", 3) => match parse_expire(&opt, &args[2], "getex") {
+            Ok(ms) => Some(ms),
+            Err(e) => return e,
+        },
+        _ => return syntax_error(),
+    };
+    bulk_or_null(StringType::get_ex(&key, ttl))
+}
+
+pub fn get_range(args: Vec<Resp>) -> Resp {
+    let (start, end) = match (parse_int(&args[1]), parse_int(&args[2])) {
+        (Ok(s), Ok(e)) => (s, e),
+        (Err(e), _) | (_, Err(e)) => return e,
+    };
+    match StringType::get_range(&arg_bytes(&args, 0), start, end) {
+        Ok(s) => Resp::bulk(s),
+        Err(e) => Resp::error(e),
+    }
+}
+
+pub fn get_set(args: Vec<Resp>) -> Resp {
+    bulk_or_null(StringType::get_set(
+        &arg_bytes(&args, 0),
+        &arg_bytes(&args, 1),
+    ))
+}
+
+pub fn incr(args: Vec<Resp>) -> Resp {
+    int_reply(StringType::incr(&arg_bytes(&args, 0)))
+}
+
+pub fn incr_by(args: Vec<Resp>) -> Resp {
+    match parse_int(&args[1]) {
+        Ok(n) => int_reply(StringType::incr_by(&arg_bytes(&args, 0), n)),
+        Err(e) => e,
+    }
+}
+
+pub fn incr_by_float(args: Vec<Resp>) -> Resp {
+    let incr = match parse_float(&args[1]) {
+        Ok(n) => n,
+        Err(e) => return e,
+    };
+    match StringType::incr_by_float(&arg_bytes(&args, 0), incr) {
+        Ok(s) => Resp::bulk(s),
+        Err(e) => Resp::error(e),
+    }
+}
+
+pub fn lcs(args: Vec<Resp>) -> Resp {
+    let len_only = match args.len() {
+        2 => false,
+        3 if arg(&args, 2).eq_ignore_ascii_case("LEN") => true,
+        _ => return syntax_error(),
+    };
+    if len_only {
+        return int_reply(
+            StringType::lcs_len(&arg_bytes(&args, 0), &arg_bytes(&args, 1)).map(|n| n as i64),
+        );
+    }
+    match StringType::lcs(&arg_bytes(&args, 0), &arg_bytes(&args, 1)) {
+        Ok(s) => Resp::bulk(s),
+        Err(e) => Resp::error(e),
+    }
+}
+
+pub fn mget(args: Vec<Resp>) -> Resp {
+    let keys = bytes_from(&args, 0);
+    Resp::array(
+        StringType::mget(&keys)
+            .into_iter()
+            .map(|v| v.map_or_else(Resp::null, Resp::bulk))
+            .collect(),
+    )
+}
+
+pub fn mset(args: Vec<Resp>) -> Resp {
+    if !args.len().is_multiple_of(2) {
+        return Resp::error("ERR wrong number of arguments for 'mset' command");
+    }
+    let mut pairs = HashMap::new();
+    for i in (0..args.len()).step_by(2) {
+        pairs.insert(arg_bytes(&args, i), arg_bytes(&args, i + 1));
+    }
+    StringType::mset(&pairs);
+    Resp::ok()
+}
+
+/// SET key value [NX | XX] [GET] [EX seconds | PX milliseconds]
+pub fn set(args: Vec<Resp>) -> Resp {
+    let key = arg_bytes(&args, 0);
+    let value = arg_bytes(&args, 1);
+    let mut condition = SetCondition::Always;
+    let mut get = false;
+    let mut ttl = None;
+
+    let mut i = 2;
+    while i < args.len() {
+        let opt = arg(&args, i).to_uppercase();
+        match opt.as_str() {
+            "NX" if condition == SetCondition::Always => condition = SetCondition::IfNotExists,
+            "XX" if condition == SetCondition::Always => condition = SetCondition::IfExists,
+            "GET" => get = true,
+            "EX" | "PX" if ttl.is_none() && i + 1 < args.len() => {
+                i += 1;
+                match parse_expire(&opt, &args[i], "set") {
+                    Ok(ms) => ttl = Some(ms),
+                    Err(e) => return e,
+                }
+            }
+            _ => return syntax_error(),
+        }
+        i += 1;
+    }
+
+    match StringType::set_with(&key, &value, condition, get, ttl) {
+        Ok((_, old)) if get => old.map_or_else(Resp::null, Resp::bulk),
+        Ok((true, _)) => Resp::ok(),
+        Ok((false, _)) => Resp::null(),
+        Err(e) => Resp::error(e),
+    }
+}
+
+/// SETEX key seconds value
+pub fn set_ex(args: Vec<Resp>) -> Resp {
+    match parse_expire("EX", &args[1], "setex") {
+        Ok(ms) => {
+            StringType::set_ex(&arg_bytes(&args, 0), &arg_bytes(&args, 2), ms);
+            Resp::ok()
+        }
+        Err(e) => e,
+    }
+}
+
+/// PSETEX key milliseconds value
+pub fn pset_ex(args: Vec<Resp>) -> Resp {
+    match parse_expire("PX", &args[1], "psetex") {
+        Ok(ms) => {
+            StringType::set_ex(&arg_bytes(&args, 0), &arg_bytes(&args, 2), ms);
+            Resp::ok()
+        }
+        Err(e) => e,
+    }
+}
+
+/// SETRANGE key offset value
+/// The resulting string may not be longer than `proto-max-bulk-len`.
+pub fn set_range(args: Vec<Resp>) -> Resp {
+    let offset = match parse_int(&args[1]) {
+        Ok(n) if n >= 0 => n as u64,
+        Ok(_) => return Resp::error("ERR offset is out of range"),
+        Err(e) => return e,
+    };
+    let value = arg_bytes(&args, 2);
+    if offset.saturating_add(value.len() as u64) > PROTO_MAX_BULK_LEN.get() {
+        return Resp::error("ERR string exceeds maximum allowed size (proto-max-bulk-len)");
+    }
+    int_reply(
+        StringType::set_range(&arg_bytes(&args, 0), offset as usize, &value).map(|n| n as i64),
+    )
+}
+
+pub fn str_len(args: Vec<Resp>) -> Resp {
+    int_reply(StringType::str_len(&arg_bytes(&args, 0)).map(|n| n as i64))
+}
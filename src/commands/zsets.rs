@@ -0,0 +1,351 @@
+use crate::commands::handler::{
+    arg, arg_bytes, bytes_from, int_reply, parse_float, parse_int, parse_rand_count, syntax_error,
+};
+use crate::resp::resp::Resp;
+use crate::store::zset::{LexBound, ScoreBound};
+use crate::types::set_type::SetOp;
+use crate::types::zset_type::{Aggregate, RangeBy, RangeSpec, ZAddOptions, ZSetType};
+
+fn score_reply(score: f64) -> Resp {
+    Resp::double(score)
+}
+
+fn members_reply(result: Result<Vec<(Vec<u8>, f64)>, String>, with_scores: bool) -> Resp {
+    match result {
+        Ok(members) => Resp::array(
+            members
+                .into_iter()
+                .flat_map(|(m, s)| {
+                    let score = with_scores.then(|| score_reply(s));
+                    std::iter::once(Resp::bulk(m)).chain(score)
+                })
+                .collect(),
+        ),
+        Err(e) => Resp::error(e),
+    }
+}
+
+fn parse_score_bound(r: &Resp) -> Result<ScoreBound, Resp> {
+    let s = r.as_string().unwrap_or_default();
+    let (exclusive, value) = match s.strip_prefix('(') {
+        Some(rest) => (true, rest),
+        None => (false, s.as_str()),
+    };
+    value
+        .parse::<f64>()
+        .ok()
+        .filter(|v| !v.is_nan())
+        .map(|value| ScoreBound { value, exclusive })
+        .ok_or_else(|| Resp::error("ERR min or max is not a float"))
+}
+
+fn parse_lex_bound(r: &Resp) -> Result<LexBound, Resp> {
+    let s = r.as_bytes().unwrap_or_default();
+    match s.as_slice() {
+        b"-" => Ok(LexBound::NegInf),
+        b"+" => Ok(LexBound::PosInf),
+        _ => match (s.strip_prefix(b"["), s.strip_prefix(b"(")) {
+            (Some(m), _) => Ok(LexBound::Inclusive(m.to_vec())),
+            (_, Some(m)) => Ok(LexBound::Exclusive(m.to_vec())),
+            _ => Err(Resp::error("ERR min or max not valid string range item")),
+        },
+    }
+}
+
+/// Parses `start stop [BYSCORE | BYLEX] [REV] [LIMIT offset count] [WITHSCORES]`
+/// starting at `args[from]`. Returns the range and whether WITHSCORES was given.
+fn parse_range(args: &[Resp], from: usize, allow_scores: bool) -> Result<(RangeSpec, bool), Resp> {
+    let mut by_score = false;
+    let mut by_lex = false;
+    let mut rev = false;
+    let mut limit = None;
+    let mut with_scores = false;
+
+    let mut i = from + 2;
+    while i < args.len() {
+        match arg(args, i).to_uppercase().as_str() {
+            "BYSCORE" if !by_lex => by_score = true,
+            "BYLEX" if !by_score => by_lex = true,
+            "REV" => rev = true,
+            "WITHSCORES" if allow_scores => with_scores = true,
+            "LIMIT" if i + 2 < args.len() => {
+                limit = Some((parse_int(&args[i + 1])?, parse_int(&args[i + 2])?));
+                i += 2;
+            }
+            _ => return Err(syntax_error()),
+        }
+        i += 1;
+    }
+
+    if limit.is_some() && !by_score && !by_lex {
+        return Err(Resp::error(
+            "ERR syntax error, LIMIT is only supported in combination with either BYSCORE or BYLEX",
+        ));
+    }
+    if with_scores && by_lex {
+        return Err(Resp::error(
+            "ERR syntax error, WITHSCORES not supported in combination with BYLEX",
+        ));
+    }
+
+    // With REV, score and lex ranges are given as max then min.
+    let (lo, hi) = if rev && (by_score || by_lex) {
+        (&args[from + 1], &args[from])
+    } else {
+        (&args[from], &args[from + 1])
+    };
+    let by = if by_score {
+        RangeBy::Score(parse_score_bound(lo)?, parse_score_bound(hi)?)
+    } else if by_lex {
+        RangeBy::Lex(parse_lex_bound(lo)?, parse_lex_bound(hi)?)
+    } else {
+        RangeBy::Rank(parse_int(lo)?, parse_int(hi)?)
+    };
+    Ok((RangeSpec { by, rev, limit }, with_scores))
+}
+
+/// ZADD key [NX | XX] [GT | LT] [CH] [INCR] score member [score member ...]
+pub fn zadd(args: Vec<Resp>) -> Resp {
+    let mut opts = ZAddOptions::default();
+    let mut i = 1;
+    while i < args.len() {
+        match arg(&args, i).to_uppercase().as_str() {
+            "NX" => opts.nx = true,
+            "XX" => opts.xx = true,
+            "GT" => opts.gt = true,
+            "LT" => opts.lt = true,
+            "CH" => opts.ch = true,
+            "INCR" => opts.incr = true,
+            _ => break,
+        }
+        i += 1;
+    }
+
+    let rest = &args[i..];
+    if rest.is_empty() || !rest.len().is_multiple_of(2) {
+        return syntax_error();
+    }
+    if opts.nx && opts.xx {
+        return Resp::error("ERR XX and NX options at the same time are not compatible");
+    }
+    if (opts.gt && opts.lt) || (opts.nx && (opts.gt || opts.lt)) {
+        return Resp::error("ERR GT, LT, and/or NX options at the same time are not compatible");
+    }
+    if opts.incr && rest.len() > 2 {
+        return Resp::error("ERR INCR option supports a single increment-element pair");
+    }
+
+    let mut pairs = Vec::with_capacity(rest.len() / 2);
+    for pair in rest.chunks(2) {
+        match parse_float(&pair[0]) {
+            Ok(score) => pairs.push((score, pair[1].as_bytes().unwrap_or_default())),
+            Err(e) => return e,
+        }
+    }
+
+    match ZSetType::zadd(&arg_bytes(&args, 0), &opts, &pairs) {
+        Ok((_, score)) if opts.incr => score.map_or_else(Resp::null, score_reply),
+        Ok((count, _)) => Resp::int(count),
+        Err(e) => Resp::error(e),
+    }
+}
+
+pub fn zincrby(args: Vec<Resp>) -> Resp {
+    let incr = match parse_float(&args[1]) {
+        Ok(n) => n,
+        Err(e) => return e,
+    };
+    match ZSetType::zincrby(&arg_bytes(&args, 0), incr, &arg_bytes(&args, 2)) {
+        Ok(score) => score_reply(score),
+        Err(e) => Resp::error(e),
+    }
+}
+
+pub fn zrem(args: Vec<Resp>) -> Resp {
+    int_reply(ZSetType::zrem(&arg_bytes(&args, 0), &bytes_from(&args, 1)))
+}
+
+pub fn zscore(args: Vec<Resp>) -> Resp {
+    match ZSetType::zscore(&arg_bytes(&args, 0), &arg_bytes(&args, 1)) {
+        Ok(score) => score.map_or_else(Resp::null, score_reply),
+        Err(e) => Resp::error(e),
+    }
+}
+
+pub fn zmscore(args: Vec<Resp>) -> Resp {
+    match ZSetType::zmscore(&arg_bytes(&args, 0), &bytes_from(&args, 1)) {
+        Ok(scores) => Resp::array(
+            scores
+                .into_iter()
+                .map(|s| s.map_or_else(Resp::null, score_reply))
+                .collect(),
+        ),
+        Err(e) => Resp::error(e),
+    }
+}
+
+pub fn zcard(args: Vec<Resp>) -> Resp {
+    int_reply(ZSetType::zcard(&arg_bytes(&args, 0)))
+}
+
+pub fn zcount(args: Vec<Resp>) -> Resp {
+    match (parse_score_bound(&args[1]), parse_score_bound(&args[2])) {
+        (Ok(min), Ok(max)) => int_reply(ZSetType::zcount(&arg_bytes(&args, 0), min, max)),
+        (Err(e), _) | (_, Err(e)) => e,
+    }
+}
+
+/// ZRANK/ZREVRANK key member [WITHSCORE]
+fn rank(args: Vec<Resp>, rev: bool) -> Resp {
+    let with_score = match args.len() {
+        2 => false,
+        3 if arg(&args, 2).eq_ignore_ascii_case("WITHSCORE") => true,
+        _ => return syntax_error(),
+    };
+    match ZSetType::zrank(&arg_bytes(&args, 0), &arg_bytes(&args, 1), rev) {
+        Ok(Some((rank, score))) if with_score => {
+            Resp::array(vec![Resp::int(rank), score_reply(score)])
+        }
+        Ok(Some((rank, _))) => Resp::int(rank),
+        Ok(None) => Resp::null(),
+        Err(e) => Resp::error(e),
+    }
+}
+
+pub fn zrank(args: Vec<Resp>) -> Resp {
+    rank(args, false)
+}
+
+pub fn zrevrank(args: Vec<Resp>) -> Resp {
+    rank(args, true)
+}
+
+/// ZRANGE key start stop [BYSCORE | BYLEX] [REV] [LIMIT offset count] [WITHSCORES]
+pub fn zrange(args: Vec<Resp>) -> Resp {
+    match parse_range(&args, 1, true) {
+        Ok((spec, with_scores)) => {
+            members_reply(ZSetType::zrange(&arg_bytes(&args, 0), &spec), with_scores)
+        }
+        Err(e) => e,
+    }
+}
+
+/// ZRANGESTORE dst src min max [BYSCORE | BYLEX] [REV] [LIMIT offset count]
+pub fn zrangestore(args: Vec<Resp>) -> Resp {
+    match parse_range(&args, 2, false) {
+        Ok((spec, _)) => int_reply(ZSetType::zrangestore(
+            &arg_bytes(&args, 0),
+            &arg_bytes(&args, 1),
+            &spec,
+        )),
+        Err(e) => e,
+    }
+}
+
+/// ZPOPMIN/ZPOPMAX key [count]
+fn pop(args: Vec<Resp>, max: bool) -> Resp {
+    let count = match args.len() {
+        1 => 1,
+        2 => match parse_int(&args[1]) {
+            Ok(n) if n >= 0 => n as usize,
+            Ok(_) => return Resp::error("ERR value is out of range, must be positive"),
+            Err(e) => return e,
+        },
+        _ => return syntax_error(),
+    };
+    members_reply(ZSetType::zpop(&arg_bytes(&args, 0), count, max), true)
+}
+
+pub fn zpopmin(args: Vec<Resp>) -> Resp {
+    pop(args, false)
+}
+
+pub fn zpopmax(args: Vec<Resp>) -> Resp {
+    pop(args, true)
+}
+
+/// ZRANDMEMBER key [count [WITHSCORES]]
+pub fn zrandmember(args: Vec<Resp>) -> Resp {
+    let key = arg_bytes(&args, 0);
+    if args.len() == 1 {
+        return match ZSetType::zrandmember(&key, 1) {
+            Ok(mut members) => members
+                .pop()
+                .map_or_else(Resp::null, |(m, _)| Resp::bulk(m)),
+            Err(e) => Resp::error(e),
+        };
+    }
+    let count = match parse_rand_count(&args[1]) {
+        Ok(n) => n,
+        Err(e) => return e,
+    };
+    let with_scores = match args.len() {
+        2 => false,
+        3 if arg(&args, 2).eq_ignore_ascii_case("WITHSCORES") => true,
+        _ => return syntax_error(),
+    };
+    members_reply(ZSetType::zrandmember(&key, count), with_scores)
+}
+
+/// destination numkeys key [key ...] [WEIGHTS weight ...] [AGGREGATE SUM | MIN | MAX]
+fn store(args: Vec<Resp>, op: SetOp) -> Resp {
+    let numkeys = match parse_int(&args[1]) {
+        Ok(n) if n > 0 => n as usize,
+        Ok(_) => {
+            return Resp::error("ERR at least 1 input key is needed for this command");
+        }
+        Err(e) => return e,
+    };
+    if args.len() < numkeys + 2 {
+        return syntax_error();
+    }
+    let keys: Vec<Vec<u8>> = (2..numkeys + 2).map(|i| arg_bytes(&args, i)).collect();
+
+    let mut weights = Vec::new();
+    let mut aggregate = Aggregate::Sum;
+    let mut i = numkeys + 2;
+    while i < args.len() {
+        match arg(&args, i).to_uppercase().as_str() {
+            "WEIGHTS" if !matches!(op, SetOp::Diff) && i + numkeys < args.len() => {
+                for w in &args[i + 1..=i + numkeys] {
+                    match parse_float(w) {
+                        Ok(w) => weights.push(w),
+                        Err(_) => return Resp::error("ERR weight value is not a float"),
+                    }
+                }
+                i += numkeys;
+            }
+            "AGGREGATE" if !matches!(op, SetOp::Diff) && i + 1 < args.len() => {
+                aggregate = match arg(&args, i + 1).to_uppercase().as_str() {
+                    "SUM" => Aggregate::Sum,
+                    "MIN" => Aggregate::Min,
+                    "MAX" => Aggregate::Max,
+                    _ => return syntax_error(),
+                };
+                i += 1;
+            }
+            _ => return syntax_error(),
+        }
+        i += 1;
+    }
+
+    int_reply(ZSetType::zstore(
+        &arg_bytes(&args, 0),
+        &keys,
+        &weights,
+        aggregate,
+        op,
+    ))
+}
+
+pub fn zunionstore(args: Vec<Resp>) -> Resp {
+    store(args, SetOp::Union)
+}
+
+pub fn zinterstore(args: Vec<Resp>) -> Resp {
+    store(args, SetOp::Inter)
+}
+
+pub fn zdiffstore(args: Vec<Resp>) -> Resp {
+    store(args, SetOp::Diff)
+}
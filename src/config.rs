@@ -0,0 +1,269 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// How a parameter's value is parsed.
+#[derive(Clone, Copy)]
+enum Kind {
+    Int,
+    /// A byte count that accepts `kb`, `mb` and `gb` style suffixes.
+    Memory,
+    /// One of a fixed set of names, stored as its index.
+    Enum(&'static [&'static str]),
+}
+
+/// A server parameter that can be changed at startup or through CONFIG SET.
+pub struct Param {
+    pub name: &'static str,
+    kind: Kind,
+    min: u64,
+    max: u64,
+    /// Whether CONFIG SET may change the parameter, rather than only startup options.
+    mutable: bool,
+    value: AtomicU64,
+}
+
+impl Param {
+    const fn new(name: &'static str, kind: Kind, default: u64, min: u64, max: u64) -> Self {
+        Param {
+            name,
+            kind,
+            min,
+            max,
+            mutable: true,
+            value: AtomicU64::new(default),
+        }
+    }
+
+    /// Marks the parameter as only settable at startup.
+    const fn immutable(mut self) -> Self {
+        self.mutable = false;
+        self
+    }
+
+    pub fn get(&self) -> u64 {
+        self.value.load(Ordering::Relaxed)
+    }
+
+    /// Returns the current value as CONFIG GET shows it.
+    pub fn display(&self) -> String {
+        match self.kind {
+            Kind::Enum(names) => names[self.get() as usize].to_string(),
+            Kind::Int | Kind::Memory => self.get().to_string(),
+        }
+    }
+
+    fn parse(&self, s: &str) -> Result<u64, String> {
+        let n = match self.kind {
+            Kind::Int => s
+                .parse::<u64>()
+                .map_err(|_| "argument couldn't be parsed into an integer".to_string())?,
+            Kind::Memory => {
+                parse_memory(s).ok_or_else(|| "argument must be a memory value".to_string())?
+            }
+            Kind::Enum(names) => names
+                .iter()
+                .position(|name| name.eq_ignore_ascii_case(s))
+                .ok_or_else(|| {
+                    "argument(s) must be one of the following: ".to_string() + &names.join(", ")
+                })? as u64,
+        };
+        if n < self.min || n > self.max {
+            return Err(format!(
+                "argument must be between {} and {} inclusive",
+                self.min, self.max
+            ));
+        }
+        Ok(n)
+    }
+}
+
+/// Largest bulk string a client may send.
+pub static PROTO_MAX_BULK_LEN: Param = Param::new(
+    "proto-max-bulk-len",
+    Kind::Memory,
+    512 * 1024 * 1024,
+    1024,
+    i64::MAX as u64,
+);
+
+/// Largest number of elements in an array, map, set or push frame a client may send.
+pub static PROTO_MAX_MULTIBULK_LEN: Param = Param::new(
+    "proto-max-multibulk-len",
+    Kind::Int,
+    1024 * 1024,
+    1,
+    i32::MAX as u64,
+);
+
+/// How deeply aggregate frames may be nested inside each other.
+pub static PROTO_MAX_NESTING: Param = Param::new("proto-max-nesting", Kind::Int, 32, 1, 1024);
+
+/// Number of threads that read requests from clients and write their replies.
+pub static IO_THREADS: Param = Param::new("io-threads", Kind::Int, 4, 1, 128).immutable();
+
+/// Bytes the dataset may use before keys are evicted by `maxmemory-policy`. 0 means no limit.
+pub static MAXMEMORY: Param = Param::new("maxmemory", Kind::Memory, 0, 0, i64::MAX as u64);
+
+/// Names of the `maxmemory-policy` values, in the order `MAXMEMORY_POLICY` stores them.
+pub const EVICTION_POLICIES: &[&str] = &[
+    "noeviction",
+    "allkeys-lru",
+    "allkeys-lfu",
+    "allkeys-random",
+    "volatile-lru",
+    "volatile-lfu",
+    "volatile-random",
+    "volatile-ttl",
+];
+
+/// Which keys are evicted once `maxmemory` is reached. With `noeviction`,
+/// commands that would add data are refused instead.
+pub static MAXMEMORY_POLICY: Param = Param::new(
+    "maxmemory-policy",
+    Kind::Enum(EVICTION_POLICIES),
+    0,
+    0,
+    EVICTION_POLICIES.len() as u64 - 1,
+);
+
+/// How many keys eviction samples when picking the one to drop.
+/// Higher values approximate the policy more closely at the cost of CPU.
+pub static MAXMEMORY_SAMPLES: Param = Param::new("maxmemory-samples", Kind::Int, 5, 1, 64);
+
+/// How slowly the LFU frequency counter grows: the higher it is, the more
+/// accesses it takes to saturate the counter.
+pub static LFU_LOG_FACTOR: Param = Param::new("lfu-log-factor", Kind::Int, 10, 0, i32::MAX as u64);
+
+/// Minutes of not being accessed for each of which a key's LFU frequency counter
+/// is decremented. 0 means counters never decay.
+pub static LFU_DECAY_TIME: Param = Param::new("lfu-decay-time", Kind::Int, 1, 0, i32::MAX as u64);
+
+static PARAMS: &[&Param] = &[
+    &IO_THREADS,
+    &LFU_DECAY_TIME,
+    &LFU_LOG_FACTOR,
+    &MAXMEMORY,
+    &MAXMEMORY_POLICY,
+    &MAXMEMORY_SAMPLES,
+    &PROTO_MAX_BULK_LEN,
+    &PROTO_MAX_MULTIBULK_LEN,
+    &PROTO_MAX_NESTING,
+];
+
+pub struct Config;
+
+impl Config {
+    /// Returns every parameter whose name matches one of the glob `patterns`,
+    /// along with its current value.
+    pub fn get(patterns: &[String]) -> Vec<(&'static str, String)> {
+        PARAMS
+            .iter()
+            .filter(|p| {
+                patterns
+                    .iter()
+                    .any(|pat| glob_match(pat.as_bytes(), p.name.as_bytes()))
+            })
+            .map(|p| (p.name, p.display()))
+            .collect()
+    }
+
+    /// Applies every name/value pair, or none of them if any is invalid.
+    pub fn set(pairs: &[(String, String)]) -> Result<(), String> {
+        Self::apply(pairs, false)
+    }
+
+    /// Like `set`, but also accepts parameters that can only be given at startup.
+    pub fn load(pairs: &[(String, String)]) -> Result<(), String> {
+        Self::apply(pairs, true)
+    }
+
+    fn apply(pairs: &[(String, String)], startup: bool) -> Result<(), String> {
+        let mut parsed = Vec::with_capacity(pairs.len());
+        for (name, value) in pairs {
+            let Some(param) = PARAMS.iter().find(|p| p.name.eq_ignore_ascii_case(name)) else {
+                return Err(format!(
+                    "ERR Unknown option or number of arguments for CONFIG SET - '{}'",
+                    name
+                ));
+            };
+            let value = if param.mutable || startup {
+                param.parse(value)
+            } else {
+                Err("can't set immutable config".to_string())
+            }
+            .map_err(|e| {
+                format!(
+                    "ERR CONFIG SET failed (possibly related to argument '{}') - {}",
+                    param.name, e
+                )
+            })?;
+            parsed.push((param, value));
+        }
+        for (param, value) in parsed {
+            param.value.store(value, Ordering::Relaxed);
+        }
+        Ok(())
+    }
+}
+
+/// Parses a byte count such as `100`, `64kb` or `1gb`.
+/// `k`, `m` and `g` are powers of 1000, `kb`, `mb` and `gb` powers of 1024.
+fn parse_memory(s: &str) -> Option<u64> {
+    let lower = s.to_ascii_lowercase();
+    let split = lower
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(lower.len());
+    let (digits, unit) = lower.split_at(split);
+    let multiplier: u64 = match unit {
+        "" | "b" => 1,
+        "k" => 1000,
+        "kb" => 1024,
+        "m" => 1000 * 1000,
+        "mb" => 1024 * 1024,
+        "g" => 1000 * 1000 * 1000,
+        "gb" => 1024 * 1024 * 1024,
+        _ => return None,
+    };
+    digits.parse::<u64>().ok()?.checked_mul(multiplier)
+}
+
+/// Case-insensitive glob match supporting `*`, `?`, `[...]` classes and `\` escapes.
+fn glob_match(pattern: &[u8], s: &[u8]) -> bool {
+    match pattern.split_first() {
+        None => s.is_empty(),
+        Some((b'*', rest)) => (0..=s.len()).any(|i| glob_match(rest, &s[i..])),
+        Some((b'?', rest)) => !s.is_empty() && glob_match(rest, &s[1..]),
+        Some((b'[', rest)) => {
+            let Some(end) = rest.iter().position(|&c| c == b']') else {
+                return false;
+            };
+            let Some((&c, tail)) = s.split_first() else {
+                return false;
+            };
+            let (negate, class) = match rest[..end].split_first() {
+                Some((b'^', class)) => (true, class),
+                _ => (false, &rest[..end]),
+            };
+            let c = c.to_ascii_lowercase();
+            let mut matched = false;
+            let mut i = 0;
+            while i < class.len() {
+                let lo = class[i].to_ascii_lowercase();
+                if i + 2 < class.len() && class[i + 1] == b'-' {
+                    let hi = class[i + 2].to_ascii_lowercase();
+                    matched |= (lo.min(hi)..=lo.max(hi)).contains(&c);
+                    i += 3;
+                } else {
+                    matched |= lo == c;
+                    i += 1;
+                }
+            }
+            matched != negate && glob_match(&rest[end + 1..], tail)
+        }
+        Some((b'\\', [escaped, rest @ ..])) => {
+            s.first().is_some_and(|c| c.eq_ignore_ascii_case(escaped)) && glob_match(rest, &s[1..])
+        }
+        Some((p, rest)) => {
+            s.first().is_some_and(|c| c.eq_ignore_ascii_case(p)) && glob_match(rest, &s[1..])
+        }
+    }
+}
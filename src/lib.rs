@@ -1,4 +1,6 @@
 pub mod commands;
+pub mod config;
+pub mod net;
 pub mod resp;
 pub mod store;
 pub mod types;
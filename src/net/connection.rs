@@ -0,0 +1,152 @@
+use std::io::{self, Read};
+
+use mio::net::TcpStream;
+
+use crate::commands::connection::{self, Client};
+use crate::net::executor::{Call, ExecutorHandle};
+use crate::resp::parser::{Parsed, RequestParser};
+use crate::resp::resp::Resp;
+use crate::resp::writer::Writer;
+
+/// How much input is read from the socket at a time.
+const READ_CHUNK: usize = 16 * 1024;
+
+/// What the event loop should do with a connection after it was processed.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Status {
+    /// Wait for more input, and for the socket to become writable if replies are still queued.
+    Open {
+        wants_write: bool,
+    },
+    Closed,
+}
+
+/// A client socket along with its buffered input, queued replies and client state.
+pub struct Connection {
+    writer: Writer<TcpStream>,
+    /// Bytes read from the socket that haven't been parsed into a complete request yet.
+    input: Vec<u8>,
+    parser: RequestParser,
+    client: Client,
+    /// Set once the connection should be closed as soon as the queued replies are sent.
+    closing: bool,
+}
+
+impl Connection {
+    pub fn new(stream: TcpStream) -> Self {
+        Connection {
+            writer: Writer::new(stream),
+            input: Vec::new(),
+            parser: RequestParser::new(),
+            client: Client::new(),
+            closing: false,
+        }
+    }
+
+    pub fn stream(&mut self) -> &mut TcpStream {
+        self.writer.get_mut()
+    }
+
+    /// Reads everything the client has sent, runs each complete request through
+    /// `executor` and sends the replies, stopping once the socket would block.
+    pub fn process(&mut self, executor: &mut ExecutorHandle) -> Status {
+        if !self.closing && self.read_requests(executor).is_err() {
+            return Status::Closed;
+        }
+        if self.input.is_empty() && self.input.capacity() > READ_CHUNK {
+            // Don't hold on to a large buffer for an idle client.
+            self.input = Vec::new();
+        }
+        match self.writer.flush() {
+            Ok(()) if self.closing => Status::Closed,
+            Ok(()) => Status::Open { wants_write: false },
+            Err(e) if e.kind() == io::ErrorKind::WouldBlock => Status::Open { wants_write: true },
+            Err(_e) => Status::Closed,
+        }
+    }
+
+    fn read_requests(&mut self, executor: &mut ExecutorHandle) -> io::Result<()> {
+        let mut chunk = [0u8; READ_CHUNK];
+        while !self.closing {
+            match self.stream().read(&mut chunk) {
+                Ok(0) => self.closing = true, // The client closed its side; send what's left.
+                Ok(n) => {
+                    self.input.extend_from_slice(&chunk[..n]);
+                    self.run_requests(executor);
+                }
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => break,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(())
+    }
+
+    /// Runs every complete request in the input buffer, queueing the replies.
+    /// Consecutive commands go to the executor as one batch. The arguments are
+    /// copied out of the buffer once here, since the executor runs on its own thread.
+    fn run_requests(&mut self, executor: &mut ExecutorHandle) {
+        let mut start = 0;
+        let mut batch: Vec<Call> = Vec::new();
+        while !self.closing {
+            let (cmd, args, len) = match self.parser.parse(&self.input[start..]) {
+                Ok(Parsed::Complete(request, len)) => {
+                    let cmd = request
+                        .get(0)
+                        .map(|name| String::from_utf8_lossy(name).to_uppercase());
+                    let args: Vec<Resp> = request.iter().skip(1).map(Resp::bulk).collect();
+                    (cmd, args, len)
+                }
+                Ok(Parsed::Incomplete) => break,
+                Err(e) => {
+                    // Malformed request: report it and close, since the stream can't be resynced.
+                    self.run_batch(executor, &mut batch);
+                    self.writer.write(&Resp::error(format!("ERR {}", e)));
+                    self.closing = true;
+                    break;
+                }
+            };
+            start += len;
+            // Empty requests, such as a blank inline line, get no reply.
+            let Some(cmd) = cmd else {
+                continue;
+            };
+            match cmd.as_str() {
+                "QUIT" | "HELLO" => {
+                    self.run_batch(executor, &mut batch);
+                    self.run_connection_command(&cmd, &args);
+                }
+                _ => batch.push((cmd, args)),
+            }
+        }
+        self.run_batch(executor, &mut batch);
+        self.input.drain(..start);
+    }
+
+    /// Sends the batched commands to the executor and queues their replies.
+    /// The connection is closed if the executor is gone.
+    fn run_batch(&mut self, executor: &mut ExecutorHandle, batch: &mut Vec<Call>) {
+        if batch.is_empty() {
+            return;
+        }
+        let Some(replies) = executor.run(std::mem::take(batch)) else {
+            self.closing = true;
+            return;
+        };
+        for reply in replies {
+            self.writer.write(&reply);
+        }
+    }
+
+    /// Runs a command that acts on the connection rather than the keyspace.
+    fn run_connection_command(&mut self, cmd: &str, args: &[Resp]) {
+        if cmd == "QUIT" {
+            self.writer.write(&Resp::ok());
+            self.closing = true;
+        } else {
+            let reply = connection::hello(&mut self.client, args);
+            self.writer.set_protocol(self.client.protocol);
+            self.writer.write(&reply);
+        }
+    }
+}
@@ -0,0 +1,147 @@
+use std::collections::HashMap;
+use std::io;
+use std::sync::Arc;
+use std::sync::mpsc::{self, Receiver};
+
+use mio::net::TcpListener;
+use mio::{Events, Interest, Poll, Registry, Token, Waker};
+
+use crate::net::connection::{Connection, Status};
+use crate::net::executor::Executor;
+use crate::net::io_threads::{Done, IoThreads};
+
+const LISTENER: Token = Token(0);
+const WAKER: Token = Token(1);
+const FIRST_CONNECTION: usize = 2;
+
+/// Where a connection is while the event loop tracks it.
+enum Slot {
+    /// Waiting for readiness on the event loop.
+    Idle(Box<Connection>),
+    /// Being processed by an I/O thread. `rearm` records readiness that arrived in the
+    /// meantime, so the connection is processed again once it's handed back.
+    Busy { rearm: bool },
+}
+
+/// Accepts clients on `listener` and serves them from a single readiness loop,
+/// handing connections with pending input or output to `io_threads` I/O threads.
+/// Idle connections cost only their buffers.
+pub fn run(mut listener: TcpListener, io_threads: usize) -> io::Result<()> {
+    let mut poll = Poll::new()?;
+    poll.registry()
+        .register(&mut listener, LISTENER, Interest::READABLE)?;
+    let waker = Arc::new(Waker::new(poll.registry(), WAKER)?);
+    let (done_tx, done_rx) = mpsc::channel();
+    let executor = Executor::start();
+    let io_threads = IoThreads::new(io_threads, &executor, done_tx, waker);
+
+    let mut event_loop = EventLoop {
+        listener,
+        slots: HashMap::new(),
+        next_token: FIRST_CONNECTION,
+        io_threads,
+        done: done_rx,
+    };
+    let mut events = Events::with_capacity(1024);
+    loop {
+        if let Err(e) = poll.poll(&mut events, None) {
+            if e.kind() == io::ErrorKind::Interrupted {
+                continue;
+            }
+            return Err(e);
+        }
+        for event in events.iter() {
+            match event.token() {
+                LISTENER => event_loop.accept(poll.registry()),
+                WAKER => {}
+                token => event_loop.dispatch(token),
+            }
+        }
+        event_loop.collect(poll.registry());
+    }
+}
+
+struct EventLoop {
+    listener: TcpListener,
+    slots: HashMap<Token, Slot>,
+    next_token: usize,
+    io_threads: IoThreads,
+    done: Receiver<Done>,
+}
+
+impl EventLoop {
+    /// Accepts every pending client.
+    fn accept(&mut self, registry: &Registry) {
+        loop {
+            let mut stream = match self.listener.accept() {
+                Ok((stream, _addr)) => stream,
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => return,
+                Err(e)
+                    if matches!(
+                        e.kind(),
+                        io::ErrorKind::Interrupted | io::ErrorKind::ConnectionAborted
+                    ) =>
+                {
+                    continue;
+                }
+                Err(e) => {
+                    // E.g. out of file descriptors. Clients left in the backlog are
+                    // picked up on the next readiness event.
+                    eprintln!("Failed to accept connection: {}", e);
+                    return;
+                }
+            };
+            if let Err(e) = stream.set_nodelay(true) {
+                eprintln!("Failed to set TCP_NODELAY: {}", e);
+            }
+            let token = Token(self.next_token);
+            self.next_token += 1;
+            if let Err(e) = registry.register(&mut stream, token, Interest::READABLE) {
+                eprintln!("Failed to register connection: {}", e);
+                continue;
+            }
+            self.slots
+                .insert(token, Slot::Idle(Box::new(Connection::new(stream))));
+        }
+    }
+
+    /// Hands a ready connection to the I/O threads, unless one of them already has it.
+    fn dispatch(&mut self, token: Token) {
+        let Some(slot) = self.slots.get_mut(&token) else {
+            return;
+        };
+        match std::mem::replace(slot, Slot::Busy { rearm: false }) {
+            Slot::Idle(conn) => self.io_threads.submit(token, conn),
+            Slot::Busy { .. } => *slot = Slot::Busy { rearm: true },
+        }
+    }
+
+    /// Takes back the connections the I/O threads are done with, closing them or waiting
+    /// for their next readiness event.
+    fn collect(&mut self, registry: &Registry) {
+        while let Ok((token, mut conn, status)) = self.done.try_recv() {
+            let rearm = matches!(self.slots.get(&token), Some(Slot::Busy { rearm: true }));
+            let interest = match status {
+                Status::Closed => None,
+                Status::Open { wants_write: false } => Some(Interest::READABLE),
+                Status::Open { wants_write: true } => Some(Interest::READABLE | Interest::WRITABLE),
+            };
+            // Reregistering also reports readiness that arrived while an I/O thread held the
+            // connection; an event seen in that window is replayed through `rearm`.
+            let registered =
+                interest.map(|interest| registry.reregister(conn.stream(), token, interest));
+            match registered {
+                Some(Ok(())) => {
+                    self.slots.insert(token, Slot::Idle(conn));
+                    if rearm {
+                        self.dispatch(token);
+                    }
+                }
+                Some(Err(_)) | None => {
+                    let _ = registry.deregister(conn.stream());
+                    self.slots.remove(&token);
+                }
+            }
+        }
+    }
+}
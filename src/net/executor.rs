@@ -0,0 +1,87 @@
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::commands::handler;
+use crate::net::stats::ThreadStats;
+use crate::resp::resp::Resp;
+
+/// An uppercased command name and its arguments.
+pub type Call = (String, Vec<Resp>);
+
+type Job = (Vec<Call>, Sender<Vec<Resp>>);
+
+/// Runs a command, replying with an error instead of taking the executor
+/// thread down if it panics. Shard locks held at the time are recovered by the
+/// next command that takes them.
+fn execute(cmd: &str, args: Vec<Resp>) -> Resp {
+    panic::catch_unwind(AssertUnwindSafe(|| handler::execute(cmd, args)))
+        .unwrap_or_else(|_| Resp::error("ERR internal error"))
+}
+
+/// Runs commands against the keyspace on a single thread, so they execute one at a
+/// time in the order they were submitted, whichever I/O thread parsed them.
+pub struct Executor {
+    jobs: Sender<Job>,
+}
+
+impl Executor {
+    pub fn start() -> Self {
+        let (jobs, queue) = mpsc::channel::<Job>();
+        thread::Builder::new()
+            .name("executor".to_string())
+            .spawn(move || {
+                let stats = ThreadStats::register("executor".to_string());
+                for (calls, replies) in queue {
+                    let started = Instant::now();
+                    let count = calls.len() as u64;
+                    let results = calls
+                        .into_iter()
+                        .map(|(cmd, args)| execute(&cmd, args))
+                        .collect();
+                    stats.record(started.elapsed(), count);
+                    // The I/O thread only goes away along with its connection.
+                    let _ = replies.send(results);
+                }
+            })
+            .expect("failed to spawn executor thread");
+        Executor { jobs }
+    }
+
+    /// Returns a handle for one I/O thread to submit commands through.
+    pub fn handle(&self) -> ExecutorHandle {
+        let (replies_tx, replies) = mpsc::channel();
+        ExecutorHandle {
+            jobs: self.jobs.clone(),
+            replies_tx,
+            replies,
+            waited: Duration::ZERO,
+        }
+    }
+}
+
+pub struct ExecutorHandle {
+    jobs: Sender<Job>,
+    replies_tx: Sender<Vec<Resp>>,
+    replies: Receiver<Vec<Resp>>,
+    /// Time spent waiting on the executor since the last `take_waited`.
+    waited: Duration,
+}
+
+impl ExecutorHandle {
+    /// Runs `calls` in order and waits for their replies.
+    /// Returns `None` if the executor thread has exited.
+    pub fn run(&mut self, calls: Vec<Call>) -> Option<Vec<Resp>> {
+        let started = Instant::now();
+        self.jobs.send((calls, self.replies_tx.clone())).ok()?;
+        let replies = self.replies.recv().ok();
+        self.waited += started.elapsed();
+        replies
+    }
+
+    /// Returns how long this handle has waited on the executor since the last call.
+    pub fn take_waited(&mut self) -> Duration {
+        std::mem::take(&mut self.waited)
+    }
+}
@@ -0,0 +1,73 @@
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Instant;
+
+use mio::{Token, Waker};
+
+use crate::net::connection::{Connection, Status};
+use crate::net::executor::{Executor, ExecutorHandle};
+use crate::net::stats::ThreadStats;
+
+/// A connection handed back to the event loop once an I/O thread is done with it.
+pub type Done = (Token, Box<Connection>, Status);
+
+/// A fixed number of threads that read and parse requests from ready connections,
+/// pass the commands to the executor and write the replies. A connection is only
+/// ever held by one thread at a time, so its requests run in order.
+pub struct IoThreads {
+    jobs: Sender<(Token, Box<Connection>)>,
+}
+
+impl IoThreads {
+    /// Starts `size` threads. Each processed connection is sent to `done`,
+    /// and `waker` wakes the event loop to pick it up.
+    pub fn new(size: usize, executor: &Executor, done: Sender<Done>, waker: Arc<Waker>) -> Self {
+        let (jobs, queue) = mpsc::channel();
+        let queue = Arc::new(Mutex::new(queue));
+        for i in 0..size.max(1) {
+            let queue = Arc::clone(&queue);
+            let executor = executor.handle();
+            let done = done.clone();
+            let waker = Arc::clone(&waker);
+            thread::Builder::new()
+                .name(format!("io-thread-{}", i))
+                .spawn(move || {
+                    let stats = ThreadStats::register(format!("io_thread_{}", i));
+                    work(&queue, executor, &stats, &done, &waker)
+                })
+                .expect("failed to spawn I/O thread");
+        }
+        IoThreads { jobs }
+    }
+
+    /// Queues `conn` to be processed by the next free thread.
+    pub fn submit(&self, token: Token, conn: Box<Connection>) {
+        self.jobs.send((token, conn)).expect("I/O threads exited");
+    }
+}
+
+fn work(
+    queue: &Mutex<Receiver<(Token, Box<Connection>)>>,
+    mut executor: ExecutorHandle,
+    stats: &ThreadStats,
+    done: &Sender<Done>,
+    waker: &Waker,
+) {
+    loop {
+        let job = queue.lock().unwrap().recv();
+        let Ok((token, mut conn)) = job else {
+            return; // The event loop was dropped.
+        };
+        let started = Instant::now();
+        let status = conn.process(&mut executor);
+        // Waiting on the executor isn't I/O work.
+        stats.record(started.elapsed().saturating_sub(executor.take_waited()), 1);
+        if done.send((token, conn, status)).is_err() {
+            return;
+        }
+        if let Err(e) = waker.wake() {
+            eprintln!("Failed to wake the event loop: {}", e);
+        }
+    }
+}
@@ -0,0 +1,5 @@
+pub mod connection;
+pub mod event_loop;
+pub mod executor;
+pub mod io_threads;
+pub mod stats;
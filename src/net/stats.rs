@@ -0,0 +1,53 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+static THREADS: Mutex<Vec<Arc<ThreadStats>>> = Mutex::new(Vec::new());
+
+/// How busy one server thread has been, as reported by INFO.
+pub struct ThreadStats {
+    pub name: String,
+    started: Instant,
+    busy_usec: AtomicU64,
+    ops: AtomicU64,
+}
+
+impl ThreadStats {
+    /// Creates the stats for a thread and lists them in INFO.
+    pub fn register(name: String) -> Arc<Self> {
+        let stats = Arc::new(ThreadStats {
+            name,
+            started: Instant::now(),
+            busy_usec: AtomicU64::new(0),
+            ops: AtomicU64::new(0),
+        });
+        THREADS.lock().unwrap().push(Arc::clone(&stats));
+        stats
+    }
+
+    /// Returns the stats of every registered thread, in registration order.
+    pub fn all() -> Vec<Arc<Self>> {
+        THREADS.lock().unwrap().clone()
+    }
+
+    /// Records `busy` time spent doing `ops` units of work.
+    pub fn record(&self, busy: Duration, ops: u64) {
+        self.busy_usec
+            .fetch_add(busy.as_micros() as u64, Ordering::Relaxed);
+        self.ops.fetch_add(ops, Ordering::Relaxed);
+    }
+
+    pub fn busy_usec(&self) -> u64 {
+        self.busy_usec.load(Ordering::Relaxed)
+    }
+
+    pub fn ops(&self) -> u64 {
+        self.ops.load(Ordering::Relaxed)
+    }
+
+    /// Percentage of the time since the thread started that it spent busy.
+    pub fn utilization(&self) -> f64 {
+        let elapsed = self.started.elapsed().as_micros().max(1) as f64;
+        (self.busy_usec() as f64 * 100.0 / elapsed).min(100.0)
+    }
+}
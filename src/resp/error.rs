@@ -0,0 +1,36 @@
+use std::fmt;
+
+/// Why a frame could not be read from a client.
+#[derive(Debug)]
+pub enum ProtocolError {
+    /// A length, integer or other value could not be parsed, e.g. `Invalid("bulk length")`.
+    Invalid(&'static str),
+    /// A line or bulk payload was not terminated by CRLF.
+    MissingCrlf,
+    /// A request array held something other than a bulk string; carries the type byte found.
+    ExpectedBulk(u8),
+    /// Aggregates were nested deeper than `proto-max-nesting`.
+    TooDeep,
+    /// A line grew past the inline size limit before its terminator arrived.
+    TooBigRequest,
+    UnbalancedQuotes,
+}
+
+impl fmt::Display for ProtocolError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProtocolError::Invalid(what) => write!(f, "Protocol error: invalid {}", what),
+            ProtocolError::MissingCrlf => write!(f, "Protocol error: expected CRLF"),
+            ProtocolError::ExpectedBulk(got) => {
+                write!(f, "Protocol error: expected '$', got '{}'", *got as char)
+            }
+            ProtocolError::TooDeep => write!(f, "Protocol error: nesting too deep"),
+            ProtocolError::TooBigRequest => write!(f, "Protocol error: too big inline request"),
+            ProtocolError::UnbalancedQuotes => {
+                write!(f, "Protocol error: unbalanced quotes in request")
+            }
+        }
+    }
+}
+
+impl std::error::Error for ProtocolError {}
@@ -1,3 +1,6 @@
+pub mod error;
+pub mod parser;
 pub mod reader;
+#[allow(clippy::module_inception)]
 pub mod resp;
 pub mod writer;
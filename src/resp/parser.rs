@@ -0,0 +1,811 @@
+use std::ops::Range;
+
+use crate::resp::error::ProtocolError;
+use crate::resp::reader::{Limits, MAX_INLINE_LEN, MAX_PREALLOC, split_args};
+use crate::resp::resp::{Resp, Typ, Value};
+
+/// The outcome of parsing the start of a buffer.
+#[derive(Debug)]
+pub enum Parsed<T> {
+    /// A whole frame was parsed from the first `usize` bytes.
+    Complete(T, usize),
+    /// The buffer ends partway through a frame; call again once more bytes arrive.
+    Incomplete,
+}
+
+/// Finds the CRLF-terminated line starting at `pos` and returns it without the
+/// terminator, along with the position just past it.
+fn line_at(buf: &[u8], pos: usize) -> Result<Option<(&[u8], usize)>, ProtocolError> {
+    let Some(lf) = buf[pos..].iter().position(|&b| b == b'\n') else {
+        if buf.len() - pos > MAX_INLINE_LEN {
+            return Err(ProtocolError::TooBigRequest);
+        }
+        return Ok(None);
+    };
+    let end = pos + lf;
+    if end == pos || buf[end - 1] != b'\r' {
+        return Err(ProtocolError::MissingCrlf);
+    }
+    Ok(Some((&buf[pos..end - 1], end + 1)))
+}
+
+fn parse_int(line: &[u8], what: &'static str) -> Result<i64, ProtocolError> {
+    std::str::from_utf8(line)
+        .ok()
+        .and_then(|s| s.parse().ok())
+        .ok_or(ProtocolError::Invalid(what))
+}
+
+/// Checks a declared bulk length against the limits. Returns `None` for -1.
+fn bulk_len(n: i64, limits: &Limits) -> Result<Option<usize>, ProtocolError> {
+    match n {
+        -1 => Ok(None),
+        n if n < 0 || n as u64 > limits.max_bulk_len => Err(ProtocolError::Invalid("bulk length")),
+        n => Ok(Some(n as usize)),
+    }
+}
+
+/// Checks a declared aggregate length against the limits. Returns `None` for -1.
+fn multibulk_len(n: i64, limits: &Limits) -> Result<Option<usize>, ProtocolError> {
+    match n {
+        -1 => Ok(None),
+        n if n < 0 || n as u64 > limits.max_multibulk_len => {
+            Err(ProtocolError::Invalid("multibulk length"))
+        }
+        n => Ok(Some(n as usize)),
+    }
+}
+
+/// Where a request's arguments live.
+#[derive(Debug)]
+enum Args {
+    /// Byte ranges of the input buffer, for multibulk requests.
+    Ranges(Vec<Range<usize>>),
+    /// Unescaped copies, for inline requests.
+    Inline(Vec<Vec<u8>>),
+}
+
+/// A parsed request whose arguments borrow from the input buffer.
+pub struct Request<'a> {
+    buf: &'a [u8],
+    args: &'a Args,
+}
+
+impl<'a> Request<'a> {
+    pub fn len(&self) -> usize {
+        match self.args {
+            Args::Ranges(ranges) => ranges.len(),
+            Args::Inline(args) => args.len(),
+        }
+    }
+
+    /// True for blank lines and empty arrays, which are skipped.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn get(&self, i: usize) -> Option<&'a [u8]> {
+        match self.args {
+            Args::Ranges(ranges) => ranges.get(i).map(|r| &self.buf[r.clone()]),
+            Args::Inline(args) => args.get(i).map(Vec::as_slice),
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &'a [u8]> + '_ {
+        (0..self.len()).filter_map(|i| self.get(i))
+    }
+}
+
+/// A resumable parser for client requests: multibulk arrays of bulk strings or
+/// inline commands. Progress is kept between calls, so a request arriving in
+/// pieces is scanned once rather than from the start on every call.
+///
+/// Each call must pass the same buffer, possibly with more bytes appended. Once a
+/// request is complete the caller drops its length from the front of the buffer
+/// before the next call.
+#[derive(Debug)]
+pub struct RequestParser {
+    /// Offset of the next unparsed byte.
+    pos: usize,
+    /// Arguments declared by the multibulk header, once it has been read.
+    expected: Option<usize>,
+    /// Length of the bulk string whose header has been read but whose data hasn't.
+    pending_bulk: Option<usize>,
+    args: Args,
+    complete: bool,
+    limits: Limits,
+}
+
+impl Default for RequestParser {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RequestParser {
+    pub fn new() -> Self {
+        RequestParser {
+            pos: 0,
+            expected: None,
+            pending_bulk: None,
+            args: Args::Ranges(Vec::new()),
+            complete: false,
+            limits: Limits::current(),
+        }
+    }
+
+    fn reset(&mut self) {
+        self.pos = 0;
+        self.expected = None;
+        self.pending_bulk = None;
+        self.complete = false;
+        match &mut self.args {
+            Args::Ranges(ranges) => ranges.clear(),
+            Args::Inline(_) => self.args = Args::Ranges(Vec::new()),
+        }
+        self.limits = Limits::current();
+    }
+
+    /// Parses the request at the start of `buf`.
+    pub fn parse<'a>(&'a mut self, buf: &'a [u8]) -> Result<Parsed<Request<'a>>, ProtocolError> {
+        if self.complete {
+            self.reset();
+        }
+        let Some(expected) = self.expected else {
+            return match buf.first() {
+                None => Ok(Parsed::Incomplete),
+                Some(b'*') => self.parse_header(buf),
+                Some(_) => self.parse_inline(buf),
+            };
+        };
+
+        let Args::Ranges(ranges) = &mut self.args else {
+            unreachable!("multibulk requests collect ranges");
+        };
+        while ranges.len() < expected {
+            let len = match self.pending_bulk {
+                Some(len) => len,
+                None => {
+                    let Some(&first) = buf.get(self.pos) else {
+                        return Ok(Parsed::Incomplete);
+                    };
+                    if first != b'$' {
+                        return Err(ProtocolError::ExpectedBulk(first));
+                    }
+                    let Some((line, next)) = line_at(buf, self.pos + 1)? else {
+                        return Ok(Parsed::Incomplete);
+                    };
+                    let len = bulk_len(parse_int(line, "bulk length")?, &self.limits)?
+                        .ok_or(ProtocolError::Invalid("bulk length"))?;
+                    self.pos = next;
+                    self.pending_bulk = Some(len);
+                    len
+                }
+            };
+            let end = self.pos + len;
+            if buf.len() < end + 2 {
+                return Ok(Parsed::Incomplete);
+            }
+            if &buf[end..end + 2] != b"\r\n" {
+                return Err(ProtocolError::MissingCrlf);
+            }
+            ranges.push(self.pos..end);
+            self.pos = end + 2;
+            self.pending_bulk = None;
+        }
+        Ok(self.finish(buf))
+    }
+
+    fn parse_header<'a>(&'a mut self, buf: &'a [u8]) -> Result<Parsed<Request<'a>>, ProtocolError> {
+        let Some((line, next)) = line_at(buf, 1)? else {
+            return Ok(Parsed::Incomplete);
+        };
+        let expected =
+            multibulk_len(parse_int(line, "multibulk length")?, &self.limits)?.unwrap_or(0);
+        self.pos = next;
+        self.expected = Some(expected);
+        if let Args::Ranges(ranges) = &mut self.args {
+            ranges.reserve(expected.min(MAX_PREALLOC));
+        }
+        self.parse(buf)
+    }
+
+    /// Parses an inline command. A blank line is an empty request.
+    fn parse_inline<'a>(&'a mut self, buf: &'a [u8]) -> Result<Parsed<Request<'a>>, ProtocolError> {
+        // Bytes before `pos` were already searched for the end of the line.
+        let Some(lf) = buf[self.pos..].iter().position(|&b| b == b'\n') else {
+            if buf.len() > MAX_INLINE_LEN {
+                return Err(ProtocolError::TooBigRequest);
+            }
+            self.pos = buf.len();
+            return Ok(Parsed::Incomplete);
+        };
+        let lf = self.pos + lf;
+        let line = &buf[..lf];
+        let line = line.strip_suffix(b"\r").unwrap_or(line);
+        let args = split_args(line).ok_or(ProtocolError::UnbalancedQuotes)?;
+        self.pos = lf + 1;
+        self.args = Args::Inline(args);
+        Ok(self.finish(buf))
+    }
+
+    fn finish<'a>(&'a mut self, buf: &'a [u8]) -> Parsed<Request<'a>> {
+        self.complete = true;
+        Parsed::Complete(
+            Request {
+                buf,
+                args: &self.args,
+            },
+            self.pos,
+        )
+    }
+}
+
+/// A RESP2 or RESP3 frame whose strings borrow from the buffer it was parsed from.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Frame<'a> {
+    Simple(&'a [u8]),
+    Error(&'a [u8]),
+    Integer(i64),
+    Bulk(&'a [u8]),
+    Array(Vec<Frame<'a>>),
+    Null,
+    NullArray,
+    Double(f64),
+    Boolean(bool),
+    BigNumber(&'a [u8]),
+    Verbatim(&'a [u8], &'a [u8]),
+    Map(Vec<(Frame<'a>, Frame<'a>)>),
+    Set(Vec<Frame<'a>>),
+    Attr(Vec<(Frame<'a>, Frame<'a>)>, Box<Frame<'a>>),
+    Push(Vec<Frame<'a>>),
+}
+
+impl<'a> Frame<'a> {
+    /// Parses one frame of any type from the start of `buf`, such as a reply.
+    /// Returns `Incomplete` if `buf` ends partway through it; use a `FrameParser`
+    /// to pick up where this left off once more bytes arrive.
+    pub fn parse(buf: &'a [u8]) -> Result<Parsed<Frame<'a>>, ProtocolError> {
+        FrameParser::new().parse(buf)
+    }
+
+    /// Copies the frame into an owned `Resp`.
+    pub fn to_resp(&self) -> Resp {
+        let items = |frames: &[Frame]| frames.iter().map(Frame::to_resp).collect();
+        let pairs = |pairs: &[(Frame, Frame)]| {
+            pairs
+                .iter()
+                .map(|(k, v)| (k.to_resp(), v.to_resp()))
+                .collect()
+        };
+        let val = match self {
+            Frame::Simple(s) => Value::Simple(s.to_vec()),
+            Frame::Error(s) => Value::Error(s.to_vec()),
+            Frame::Integer(n) => Value::Num(*n),
+            Frame::Bulk(s) => Value::Bulk(s.to_vec()),
+            Frame::Array(a) => Value::Arr(items(a)),
+            Frame::Null => Value::Null,
+            Frame::NullArray => Value::NullArr,
+            Frame::Double(n) => Value::Double(*n),
+            Frame::Boolean(b) => Value::Boolean(*b),
+            Frame::BigNumber(n) => Value::BigNumber(String::from_utf8_lossy(n).into_owned()),
+            Frame::Verbatim(format, s) => {
+                Value::Verbatim(String::from_utf8_lossy(format).into_owned(), s.to_vec())
+            }
+            Frame::Map(p) => Value::Map(pairs(p)),
+            Frame::Set(s) => Value::Set(items(s)),
+            Frame::Attr(attrs, inner) => Value::Attr(pairs(attrs), Box::new(inner.to_resp())),
+            Frame::Push(p) => Value::Push(items(p)),
+        };
+        Resp { val }
+    }
+}
+
+/// An aggregate frame whose elements are still being scanned.
+#[derive(Debug)]
+struct Open {
+    /// Frames left to scan, counting both halves of each map or attribute pair.
+    remaining: usize,
+    /// Set for attributes, which are followed by the frame they annotate.
+    attribute: bool,
+}
+
+/// A resumable parser for frames of any type, such as replies. Like
+/// `RequestParser`, progress is kept between calls: the frame is scanned to
+/// find where it ends, remembering the aggregates still open, and is only built
+/// from the buffer once all of it has arrived.
+///
+/// Each call must pass the same buffer, possibly with more bytes appended. Once a
+/// frame is complete the caller drops its length from the front of the buffer
+/// before the next call.
+#[derive(Debug)]
+pub struct FrameParser {
+    /// Offset of the next unscanned byte.
+    pos: usize,
+    open: Vec<Open>,
+    complete: bool,
+    limits: Limits,
+}
+
+impl Default for FrameParser {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FrameParser {
+    pub fn new() -> Self {
+        FrameParser {
+            pos: 0,
+            open: Vec::new(),
+            complete: false,
+            limits: Limits::current(),
+        }
+    }
+
+    fn reset(&mut self) {
+        self.pos = 0;
+        self.open.clear();
+        self.complete = false;
+        self.limits = Limits::current();
+    }
+
+    /// Parses the frame at the start of `buf`.
+    pub fn parse<'a>(&mut self, buf: &'a [u8]) -> Result<Parsed<Frame<'a>>, ProtocolError> {
+        if self.complete {
+            self.reset();
+        }
+        loop {
+            let depth = self.open.len() as u64;
+            let Some((head, next)) = parse_head(buf, self.pos, depth, &self.limits)? else {
+                return Ok(Parsed::Incomplete);
+            };
+            self.pos = next;
+            let scanned = match head {
+                Head::Scalar(_) => self.end_frame(),
+                Head::Aggregate(Typ::ATTRIBUTE, 0) => false,
+                Head::Aggregate(_, 0) => self.end_frame(),
+                Head::Aggregate(typ, remaining) => {
+                    self.open.push(Open {
+                        remaining,
+                        attribute: typ == Typ::ATTRIBUTE,
+                    });
+                    false
+                }
+            };
+            if scanned {
+                break;
+            }
+        }
+        self.complete = true;
+        let (frame, len) =
+            parse_frame(buf, 0, 0, &self.limits)?.expect("the whole frame was scanned");
+        debug_assert_eq!(len, self.pos);
+        Ok(Parsed::Complete(frame, len))
+    }
+
+    /// Counts a frame that was scanned to its end against the aggregates it is
+    /// nested in. Returns true once the outermost frame is complete.
+    fn end_frame(&mut self) -> bool {
+        while let Some(open) = self.open.last_mut() {
+            open.remaining -= 1;
+            if open.remaining > 0 {
+                return false;
+            }
+            if self.open.pop().is_some_and(|open| open.attribute) {
+                // The annotated frame that follows takes the attribute's place.
+                return false;
+            }
+        }
+        true
+    }
+}
+
+/// The start of a frame.
+enum Head<'a> {
+    /// A frame without elements, parsed whole.
+    Scalar(Frame<'a>),
+    /// The header of an aggregate and the number of frames that follow it,
+    /// counting both halves of each map or attribute pair.
+    Aggregate(Typ, usize),
+}
+
+/// Parses the frame at `pos`, nested inside `depth` aggregates, up to its
+/// elements. Returns the head and the position just past it, or `None` if incomplete.
+fn parse_head<'a>(
+    buf: &'a [u8],
+    pos: usize,
+    depth: u64,
+    limits: &Limits,
+) -> Result<Option<(Head<'a>, usize)>, ProtocolError> {
+    let Some(&first) = buf.get(pos) else {
+        return Ok(None);
+    };
+    let typ = Typ::from_byte(first).ok_or(ProtocolError::Invalid("frame type"))?;
+    let Some((line, next)) = line_at(buf, pos + 1)? else {
+        return Ok(None);
+    };
+
+    let frame = match typ {
+        Typ::STRING => Frame::Simple(line),
+        Typ::ERROR => Frame::Error(line),
+        Typ::INTEGER => Frame::Integer(parse_int(line, "integer")?),
+        Typ::NULL if line.is_empty() => Frame::Null,
+        Typ::NULL => return Err(ProtocolError::Invalid("null")),
+        Typ::BOOLEAN => match line {
+            b"t" => Frame::Boolean(true),
+            b"f" => Frame::Boolean(false),
+            _ => return Err(ProtocolError::Invalid("boolean")),
+        },
+        Typ::DOUBLE => std::str::from_utf8(line)
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .map(Frame::Double)
+            .ok_or(ProtocolError::Invalid("double"))?,
+        Typ::BIGNUMBER => {
+            let digits = line.strip_prefix(b"-").unwrap_or(line);
+            if digits.is_empty() || !digits.iter().all(u8::is_ascii_digit) {
+                return Err(ProtocolError::Invalid("big number"));
+            }
+            Frame::BigNumber(line)
+        }
+        Typ::BULK | Typ::BLOBERROR | Typ::VERBATIM => {
+            let Some(len) = bulk_len(parse_int(line, "bulk length")?, limits)? else {
+                if typ != Typ::BULK {
+                    return Err(ProtocolError::Invalid("bulk length"));
+                }
+                return Ok(Some((Head::Scalar(Frame::Null), next)));
+            };
+            let end = next + len;
+            if buf.len() < end + 2 {
+                return Ok(None);
+            }
+            if &buf[end..end + 2] != b"\r\n" {
+                return Err(ProtocolError::MissingCrlf);
+            }
+            let data = &buf[next..end];
+            let frame = match typ {
+                Typ::BULK => Frame::Bulk(data),
+                Typ::BLOBERROR => Frame::Error(data),
+                _ if data.get(3) == Some(&b':') => Frame::Verbatim(&data[..3], &data[4..]),
+                _ => return Err(ProtocolError::Invalid("verbatim string")),
+            };
+            return Ok(Some((Head::Scalar(frame), end + 2)));
+        }
+        Typ::ARRAY | Typ::SET | Typ::PUSH | Typ::MAP | Typ::ATTRIBUTE => {
+            if depth >= limits.max_nesting {
+                return Err(ProtocolError::TooDeep);
+            }
+            let Some(len) = multibulk_len(parse_int(line, "multibulk length")?, limits)? else {
+                if typ != Typ::ARRAY {
+                    return Err(ProtocolError::Invalid("multibulk length"));
+                }
+                return Ok(Some((Head::Scalar(Frame::NullArray), next)));
+            };
+            let per_entry = if matches!(typ, Typ::MAP | Typ::ATTRIBUTE) {
+                2
+            } else {
+                1
+            };
+            return Ok(Some((Head::Aggregate(typ, len * per_entry), next)));
+        }
+    };
+    Ok(Some((Head::Scalar(frame), next)))
+}
+
+type FrameAt<'a> = Option<(Frame<'a>, usize)>;
+
+/// Parses the frame at `pos`, nested inside `depth` aggregates.
+/// Returns the frame and the position just past it, or `None` if incomplete.
+fn parse_frame<'a>(
+    buf: &'a [u8],
+    pos: usize,
+    depth: u64,
+    limits: &Limits,
+) -> Result<FrameAt<'a>, ProtocolError> {
+    let Some((head, next)) = parse_head(buf, pos, depth, limits)? else {
+        return Ok(None);
+    };
+    let (typ, count) = match head {
+        Head::Scalar(frame) => return Ok(Some((frame, next))),
+        Head::Aggregate(typ, count) => (typ, count),
+    };
+    let mut items = Vec::with_capacity(count.min(MAX_PREALLOC));
+    let mut pos = next;
+    for _ in 0..count {
+        let Some((item, after)) = parse_frame(buf, pos, depth + 1, limits)? else {
+            return Ok(None);
+        };
+        items.push(item);
+        pos = after;
+    }
+    let frame = match typ {
+        Typ::ARRAY => Frame::Array(items),
+        Typ::SET => Frame::Set(items),
+        Typ::PUSH => Frame::Push(items),
+        _ => {
+            let mut iter = items.into_iter();
+            let mut pairs = Vec::with_capacity(count / 2);
+            while let (Some(k), Some(v)) = (iter.next(), iter.next()) {
+                pairs.push((k, v));
+            }
+            if typ == Typ::MAP {
+                Frame::Map(pairs)
+            } else {
+                let Some((inner, after)) = parse_frame(buf, pos, depth, limits)? else {
+                    return Ok(None);
+                };
+                return Ok(Some((Frame::Attr(pairs, Box::new(inner)), after)));
+            }
+        }
+    };
+    Ok(Some((frame, pos)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const REQUESTS: &[u8] = b"*3\r\n$3\r\nSET\r\n$1\r\nk\r\n$5\r\nv\r\nal\r\n\
+        GET k\r\n\
+        *0\r\n\
+        \r\n\
+        *2\r\n$4\r\nECHO\r\n$0\r\n\r\n";
+
+    fn requests() -> Vec<Vec<Vec<u8>>> {
+        vec![
+            vec![b"SET".to_vec(), b"k".to_vec(), b"v\r\nal".to_vec()],
+            vec![b"GET".to_vec(), b"k".to_vec()],
+            vec![],
+            vec![],
+            vec![b"ECHO".to_vec(), Vec::new()],
+        ]
+    }
+
+    /// Feeds `input` to `parser` the way a connection does: appending a byte at
+    /// a time, or all at once, and dropping each complete request from the front.
+    fn parse_requests(
+        parser: &mut RequestParser,
+        input: &[u8],
+        bytewise: bool,
+    ) -> Result<Vec<Vec<Vec<u8>>>, ProtocolError> {
+        let mut buf = Vec::new();
+        let mut parsed = Vec::new();
+        let chunk = if bytewise { 1 } else { input.len() };
+        for bytes in input.chunks(chunk) {
+            buf.extend_from_slice(bytes);
+            while let Parsed::Complete(request, len) = parser.parse(&buf)? {
+                parsed.push(request.iter().map(<[u8]>::to_vec).collect());
+                buf.drain(..len);
+            }
+        }
+        assert!(buf.is_empty(), "bytes left over: {:?}", buf);
+        Ok(parsed)
+    }
+
+    fn parse_frames(input: &[u8], split: usize) -> Result<Vec<Resp>, ProtocolError> {
+        let mut parser = FrameParser::new();
+        let mut buf = input[..split].to_vec();
+        let mut frames = Vec::new();
+        for more in [&input[split..], &[]] {
+            while let Parsed::Complete(frame, len) = parser.parse(&buf)? {
+                frames.push(frame.to_resp());
+                buf.drain(..len);
+            }
+            buf.extend_from_slice(more);
+        }
+        assert!(buf.is_empty(), "bytes left over: {:?}", buf);
+        Ok(frames)
+    }
+
+    fn limits(max_bulk_len: u64, max_multibulk_len: u64, max_nesting: u64) -> Limits {
+        Limits {
+            max_bulk_len,
+            max_multibulk_len,
+            max_nesting,
+        }
+    }
+
+    #[test]
+    fn parses_pipelined_requests() {
+        let parsed = parse_requests(&mut RequestParser::new(), REQUESTS, false).unwrap();
+        assert_eq!(parsed, requests());
+    }
+
+    #[test]
+    fn resumes_requests_split_at_every_byte() {
+        let parsed = parse_requests(&mut RequestParser::new(), REQUESTS, true).unwrap();
+        assert_eq!(parsed, requests());
+    }
+
+    #[test]
+    fn resumes_without_rescanning() {
+        let mut parser = RequestParser::new();
+        let buf = b"*2\r\n$3\r\nGET\r\n$3\r\nkey\r\n";
+        assert!(matches!(parser.parse(&buf[..16]), Ok(Parsed::Incomplete)));
+        assert_eq!(parser.pos, 13, "the first argument is kept");
+        let Ok(Parsed::Complete(request, len)) = parser.parse(buf) else {
+            panic!("request should be complete");
+        };
+        assert_eq!(request.iter().collect::<Vec<_>>(), [&b"GET"[..], b"key"]);
+        assert_eq!(len, buf.len());
+    }
+
+    #[test]
+    fn splits_quoted_inline_arguments() {
+        let input = b"SET \"a b\" 'c\\'d' \"\\x41\\n\\\"\"  \r\n";
+        let parsed = parse_requests(&mut RequestParser::new(), input, false).unwrap();
+        assert_eq!(
+            parsed,
+            [vec![
+                b"SET".to_vec(),
+                b"a b".to_vec(),
+                b"c'd".to_vec(),
+                b"A\n\"".to_vec()
+            ]]
+        );
+        let err = parse_requests(&mut RequestParser::new(), b"GET \"k\r\n", false);
+        assert!(matches!(err, Err(ProtocolError::UnbalancedQuotes)));
+    }
+
+    #[test]
+    fn limits_request_lengths() {
+        let mut parser = RequestParser {
+            limits: limits(4, 2, 1),
+            ..RequestParser::new()
+        };
+        let ok = parse_requests(&mut parser, b"*2\r\n$4\r\nECHO\r\n$4\r\nfour\r\n", false);
+        assert_eq!(ok.unwrap(), [vec![b"ECHO".to_vec(), b"four".to_vec()]]);
+
+        let mut parser = RequestParser {
+            limits: limits(4, 2, 1),
+            ..RequestParser::new()
+        };
+        let err = parse_requests(&mut parser, b"*1\r\n$5\r\n", false);
+        assert!(matches!(err, Err(ProtocolError::Invalid("bulk length"))));
+
+        let mut parser = RequestParser {
+            limits: limits(4, 2, 1),
+            ..RequestParser::new()
+        };
+        let err = parse_requests(&mut parser, b"*3\r\n", false);
+        assert!(matches!(
+            err,
+            Err(ProtocolError::Invalid("multibulk length"))
+        ));
+
+        let err = parse_requests(&mut RequestParser::new(), b"*1\r\n*1\r\n", false);
+        assert!(matches!(err, Err(ProtocolError::ExpectedBulk(b'*'))));
+    }
+
+    #[test]
+    fn limits_inline_length() {
+        let line = vec![b'a'; MAX_INLINE_LEN + 1];
+        let err = parse_requests(&mut RequestParser::new(), &line, false);
+        assert!(matches!(err, Err(ProtocolError::TooBigRequest)));
+    }
+
+    #[test]
+    fn parses_every_frame_type() {
+        let input = b"+OK\r\n-ERR bad\r\n:-12\r\n$3\r\na\r\n\r\n$-1\r\n*-1\r\n_\r\n\
+            #t\r\n,1.5\r\n(123456789012345678901234567890\r\n!3\r\nerr\r\n\
+            =7\r\ntxt:abc\r\n*2\r\n:1\r\n*1\r\n+x\r\n%1\r\n+k\r\n:1\r\n\
+            ~1\r\n+m\r\n>1\r\n+p\r\n|1\r\n+a\r\n:2\r\n*1\r\n:3\r\n";
+        let expected = [
+            Value::Simple(b"OK".to_vec()),
+            Value::Error(b"ERR bad".to_vec()),
+            Value::Num(-12),
+            Value::Bulk(b"a\r\n".to_vec()),
+            Value::Null,
+            Value::NullArr,
+            Value::Null,
+            Value::Boolean(true),
+            Value::Double(1.5),
+            Value::BigNumber("123456789012345678901234567890".to_string()),
+            Value::Error(b"err".to_vec()),
+            Value::Verbatim("txt".to_string(), b"abc".to_vec()),
+            Value::Arr(vec![
+                Resp::int(1),
+                Resp {
+                    val: Value::Arr(vec![Resp::simple("x")]),
+                },
+            ]),
+            Value::Map(vec![(Resp::simple("k"), Resp::int(1))]),
+            Value::Set(vec![Resp::simple("m")]),
+            Value::Push(vec![Resp::simple("p")]),
+            Value::Attr(
+                vec![(Resp::simple("a"), Resp::int(2))],
+                Box::new(Resp {
+                    val: Value::Arr(vec![Resp::int(3)]),
+                }),
+            ),
+        ];
+        let expected: Vec<Resp> = expected.into_iter().map(|val| Resp { val }).collect();
+        for split in 0..=input.len() {
+            assert_eq!(
+                parse_frames(input, split).unwrap(),
+                expected,
+                "split at {}",
+                split
+            );
+        }
+    }
+
+    #[test]
+    fn resumes_frames_split_at_every_byte() {
+        let input = b"*3\r\n%1\r\n$1\r\nk\r\n*2\r\n:1\r\n|0\r\n#f\r\n$5\r\nhello\r\n|1\r\n+a\r\n+b\r\n*0\r\n";
+        let mut parser = FrameParser::new();
+        for end in 0..input.len() {
+            assert!(matches!(
+                parser.parse(&input[..end]),
+                Ok(Parsed::Incomplete)
+            ));
+        }
+        let Ok(Parsed::Complete(frame, len)) = parser.parse(input) else {
+            panic!("frame should be complete");
+        };
+        assert_eq!(len, input.len());
+        assert_eq!(
+            frame,
+            Frame::Array(vec![
+                Frame::Map(vec![(
+                    Frame::Bulk(b"k"),
+                    Frame::Array(vec![
+                        Frame::Integer(1),
+                        Frame::Attr(Vec::new(), Box::new(Frame::Boolean(false)))
+                    ])
+                )]),
+                Frame::Bulk(b"hello"),
+                Frame::Attr(
+                    vec![(Frame::Simple(b"a"), Frame::Simple(b"b"))],
+                    Box::new(Frame::Array(Vec::new()))
+                ),
+            ])
+        );
+    }
+
+    #[test]
+    fn limits_frame_nesting_and_lengths() {
+        fn parse(input: &[u8], limits: Limits) -> Result<Parsed<Frame<'_>>, ProtocolError> {
+            FrameParser {
+                limits,
+                ..FrameParser::new()
+            }
+            .parse(input)
+        }
+        let nested = b"*1\r\n*1\r\n*1\r\n:1\r\n";
+        assert!(matches!(
+            parse(nested, limits(8, 8, 3)),
+            Ok(Parsed::Complete(_, 16))
+        ));
+        assert!(matches!(
+            parse(nested, limits(8, 8, 2)),
+            Err(ProtocolError::TooDeep)
+        ));
+        // Attributes annotate a frame at their own depth.
+        let annotated = b"*1\r\n|1\r\n+a\r\n+b\r\n*1\r\n:1\r\n";
+        assert!(matches!(
+            parse(annotated, limits(8, 8, 2)),
+            Ok(Parsed::Complete(..))
+        ));
+        assert!(matches!(
+            parse(annotated, limits(8, 8, 1)),
+            Err(ProtocolError::TooDeep)
+        ));
+
+        let bulk = b"$9\r\n";
+        assert!(matches!(
+            parse(bulk, limits(8, 8, 8)),
+            Err(ProtocolError::Invalid("bulk length"))
+        ));
+        let map = b"%5\r\n";
+        assert!(matches!(
+            parse(map, limits(8, 4, 8)),
+            Err(ProtocolError::Invalid("multibulk length"))
+        ));
+        assert!(matches!(
+            parse(b"*1\r\n:1\n", limits(8, 8, 8)),
+            Err(ProtocolError::MissingCrlf)
+        ));
+    }
+}
@@ -1,93 +1,110 @@
-use crate::resp::resp::{Resp, Typ, Value};
-use std::io::{self, BufRead, BufReader, Read};
+use crate::config::{PROTO_MAX_BULK_LEN, PROTO_MAX_MULTIBULK_LEN, PROTO_MAX_NESTING};
 
-pub struct Reader<R: Read> {
-    reader: BufReader<R>,
-}
+/// Longest line accepted for inline commands and frame headers.
+pub(crate) const MAX_INLINE_LEN: usize = 64 * 1024;
 
-impl<R: Read> Reader<R> {
-    pub fn new(rd: R) -> Self {
-        Self {
-            reader: BufReader::new(rd),
-        }
-    }
+/// Most elements or bytes allocated up front for a frame, so a large declared
+/// length only costs memory once the data actually arrives.
+pub(crate) const MAX_PREALLOC: usize = 1024;
 
-    pub fn read(&mut self) -> io::Result<Resp> {
-        // Peek the next byte without consuming it
-        let buf = self.reader.fill_buf()?;
-        if buf.is_empty() {
-            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "EOF"));
-        }
-        let first_byte = buf[0];
+/// Limits applied to a request, read from the config when the request starts.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Limits {
+    pub max_bulk_len: u64,
+    pub max_multibulk_len: u64,
+    pub max_nesting: u64,
+}
 
-        match Typ::from_byte(first_byte) {
-            Some(Typ::ARRAY) => {
-                // consume that byte before reading array content
-                self.reader.consume(1);
-                self.read_array()
-            }
-            Some(Typ::BULK) => {
-                // consume that byte before reading bulk content
-                self.reader.consume(1);
-                self.read_bulk()
-            }
-            _ => {
-                // don't consume; let `read_inline()` start from same byte
-                self.read_inline()
-            }
+impl Limits {
+    pub fn current() -> Self {
+        Limits {
+            max_bulk_len: PROTO_MAX_BULK_LEN.get(),
+            max_multibulk_len: PROTO_MAX_MULTIBULK_LEN.get(),
+            max_nesting: PROTO_MAX_NESTING.get(),
         }
     }
+}
 
-    fn read_array(&mut self) -> std::io::Result<Resp> {
-        let len = self.read_int()?;
-        let mut arr = Vec::with_capacity(len as usize);
-        for _ in 0..len {
-            arr.push(self.read()?);
+/// Splits an inline command into arguments the way redis does. Arguments are separated
+/// by whitespace and may be double quoted, with `\n`, `\xff` style escapes, or single
+/// quoted, where only `\'` is an escape. Returns `None` on unbalanced quotes or when a
+/// closing quote is not followed by whitespace.
+pub(crate) fn split_args(line: &[u8]) -> Option<Vec<Vec<u8>>> {
+    let mut args = Vec::new();
+    let mut i = 0;
+    loop {
+        while i < line.len() && line[i].is_ascii_whitespace() {
+            i += 1;
         }
-        Ok(Resp {
-            typ: Typ::ARRAY,
-            val: Value::Arr(arr),
-        })
-    }
-
-    fn read_bulk(&mut self) -> std::io::Result<Resp> {
-        let len = self.read_int()?;
-        if len < 0 {
-            return Ok(Resp {
-                typ: Typ::BULK,
-                val: Value::Str("".to_string()),
-            });
+        if i == line.len() {
+            return Some(args);
         }
-        let mut buf = vec![0u8; len as usize];
-        self.reader.read_exact(&mut buf)?;
-        self.read_line()?;
-        Ok(Resp {
-            typ: Typ::BULK,
-            val: Value::Str(String::from_utf8(buf).unwrap_or_default()),
-        })
-    }
-
-    fn read_inline(&mut self) -> std::io::Result<Resp> {
-        let line = self.read_line()?;
-        let s = String::from_utf8(line).unwrap_or_default();
-        Ok(Resp {
-            typ: Typ::STRING,
-            val: Value::Str(s),
-        })
-    }
-
-    fn read_int(&mut self) -> std::io::Result<i64> {
-        let line = self.read_line()?;
-        let s = String::from_utf8(line).unwrap_or_default();
-        Ok(s.trim().parse().unwrap_or(0))
-    }
-
-    fn read_line(&mut self) -> std::io::Result<Vec<u8>> {
-        let mut line = Vec::new();
-        self.reader.read_until(b'\n', &mut line)?;
-        if line.len() >= 2 && line[line.len() - 2] == b'\r' {
-            line.truncate(line.len() - 2);
+        let mut current = Vec::new();
+        let mut in_double = false;
+        let mut in_single = false;
+        loop {
+            let c = *line.get(i)?;
+            if in_double {
+                match c {
+                    b'\\'
+                        if i + 3 < line.len()
+                            && line[i + 1] == b'x'
+                            && line[i + 2].is_ascii_hexdigit()
+                            && line[i + 3].is_ascii_hexdigit() =>
+                    {
+                        let hex = std::str::from_utf8(&line[i + 2..i + 4]).ok()?;
+                        current.push(u8::from_str_radix(hex, 16).ok()?);
+                        i += 3;
+                    }
+                    b'\\' if i + 1 < line.len() => {
+                        i += 1;
+                        current.push(match line[i] {
+                            b'n' => b'\n',
+                            b'r' => b'\r',
+                            b't' => b'\t',
+                            b'b' => 0x08,
+                            b'a' => 0x07,
+                            other => other,
+                        });
+                    }
+                    b'"' => {
+                        // The closing quote must be followed by a space or the end.
+                        if line.get(i + 1).is_some_and(|c| !c.is_ascii_whitespace()) {
+                            return None;
+                        }
+                        i += 1;
+                        break;
+                    }
+                    _ => current.push(c),
+                }
+            } else if in_single {
+                match c {
+                    b'\\' if line.get(i + 1) == Some(&b'\'') => {
+                        current.push(b'\'');
+                        i += 1;
+                    }
+                    b'\'' => {
+                        if line.get(i + 1).is_some_and(|c| !c.is_ascii_whitespace()) {
+                            return None;
+                        }
+                        i += 1;
+                        break;
+                    }
+                    _ => current.push(c),
+                }
+            } else {
+                match c {
+                    b' ' | b'\n' | b'\r' | b'\t' | b'\0' => break,
+                    b'"' => in_double = true,
+                    b'\'' => in_single = true,
+                    _ => current.push(c),
+                }
+            }
+            i += 1;
+            if i == line.len() && !in_double && !in_single {
+                break;
+            }
         }
-        Ok(line)
+        args.push(current);
     }
 }
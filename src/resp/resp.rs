@@ -1,10 +1,21 @@
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum Typ {
     STRING,
     ERROR,
     INTEGER,
     BULK,
     ARRAY,
+    // RESP3
+    NULL,
+    BOOLEAN,
+    DOUBLE,
+    BIGNUMBER,
+    BLOBERROR,
+    VERBATIM,
+    MAP,
+    SET,
+    ATTRIBUTE,
+    PUSH,
 }
 
 impl Typ {
@@ -15,6 +26,16 @@ impl Typ {
             b':' => Some(Typ::INTEGER),
             b'$' => Some(Typ::BULK),
             b'*' => Some(Typ::ARRAY),
+            b'_' => Some(Typ::NULL),
+            b'#' => Some(Typ::BOOLEAN),
+            b',' => Some(Typ::DOUBLE),
+            b'(' => Some(Typ::BIGNUMBER),
+            b'!' => Some(Typ::BLOBERROR),
+            b'=' => Some(Typ::VERBATIM),
+            b'%' => Some(Typ::MAP),
+            b'~' => Some(Typ::SET),
+            b'|' => Some(Typ::ATTRIBUTE),
+            b'>' => Some(Typ::PUSH),
             _ => None,
         }
     }
@@ -26,19 +47,201 @@ impl Typ {
             Typ::INTEGER => b':',
             Typ::BULK => b'$',
             Typ::ARRAY => b'*',
+            Typ::NULL => b'_',
+            Typ::BOOLEAN => b'#',
+            Typ::DOUBLE => b',',
+            Typ::BIGNUMBER => b'(',
+            Typ::BLOBERROR => b'!',
+            Typ::VERBATIM => b'=',
+            Typ::MAP => b'%',
+            Typ::SET => b'~',
+            Typ::ATTRIBUTE => b'|',
+            Typ::PUSH => b'>',
         }
     }
 }
 
-#[derive(Debug, Clone)]
+/// The protocol version negotiated by a connection through HELLO.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum Protocol {
+    #[default]
+    Resp2,
+    Resp3,
+}
+
+impl Protocol {
+    pub fn from_version(version: i64) -> Option<Protocol> {
+        match version {
+            2 => Some(Protocol::Resp2),
+            3 => Some(Protocol::Resp3),
+            _ => None,
+        }
+    }
+
+    pub fn version(&self) -> i64 {
+        match self {
+            Protocol::Resp2 => 2,
+            Protocol::Resp3 => 3,
+        }
+    }
+}
+
+/// A RESP value. The variant alone decides how the value is encoded on the wire.
+#[derive(Debug, Clone, PartialEq)]
 pub enum Value {
+    Simple(Vec<u8>),
+    Error(Vec<u8>),
     Num(i64),
-    Str(String),
+    Bulk(Vec<u8>),
     Arr(Vec<Resp>),
+    /// The null bulk string, `$-1`.
+    Null,
+    /// The null array, `*-1`.
+    NullArr,
+    // RESP3 types. Under RESP2 they are sent as their closest RESP2 equivalent.
+    Double(f64),
+    Boolean(bool),
+    BigNumber(String),
+    /// A verbatim string with its three letter format, such as `txt`.
+    Verbatim(String, Vec<u8>),
+    Map(Vec<(Resp, Resp)>),
+    Set(Vec<Resp>),
+    /// Attributes attached to the value that follows them.
+    Attr(Vec<(Resp, Resp)>, Box<Resp>),
+    Push(Vec<Resp>),
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct Resp {
-    pub typ: Typ,
     pub val: Value,
 }
+
+impl Resp {
+    pub fn simple(s: impl Into<String>) -> Resp {
+        Resp {
+            val: Value::Simple(s.into().into_bytes()),
+        }
+    }
+
+    pub fn ok() -> Resp {
+        Resp::simple("OK")
+    }
+
+    pub fn bulk(s: impl Into<Vec<u8>>) -> Resp {
+        Resp {
+            val: Value::Bulk(s.into()),
+        }
+    }
+
+    pub fn error(s: impl Into<String>) -> Resp {
+        Resp {
+            val: Value::Error(s.into().into_bytes()),
+        }
+    }
+
+    pub fn int(n: i64) -> Resp {
+        Resp { val: Value::Num(n) }
+    }
+
+    /// The null bulk string, used for missing values.
+    pub fn null() -> Resp {
+        Resp { val: Value::Null }
+    }
+
+    /// The null array, used where a missing reply would otherwise be an array.
+    pub fn null_array() -> Resp {
+        Resp {
+            val: Value::NullArr,
+        }
+    }
+
+    pub fn array(items: Vec<Resp>) -> Resp {
+        Resp {
+            val: Value::Arr(items),
+        }
+    }
+
+    pub fn double(n: f64) -> Resp {
+        Resp {
+            val: Value::Double(n),
+        }
+    }
+
+    pub fn boolean(b: bool) -> Resp {
+        Resp {
+            val: Value::Boolean(b),
+        }
+    }
+
+    pub fn big_number(n: impl Into<String>) -> Resp {
+        Resp {
+            val: Value::BigNumber(n.into()),
+        }
+    }
+
+    pub fn verbatim(format: &str, s: impl Into<Vec<u8>>) -> Resp {
+        Resp {
+            val: Value::Verbatim(format.to_string(), s.into()),
+        }
+    }
+
+    pub fn map(pairs: Vec<(Resp, Resp)>) -> Resp {
+        Resp {
+            val: Value::Map(pairs),
+        }
+    }
+
+    pub fn set(items: Vec<Resp>) -> Resp {
+        Resp {
+            val: Value::Set(items),
+        }
+    }
+
+    pub fn push(items: Vec<Resp>) -> Resp {
+        Resp {
+            val: Value::Push(items),
+        }
+    }
+
+    /// Attaches `attrs` to this value.
+    pub fn with_attributes(self, attrs: Vec<(Resp, Resp)>) -> Resp {
+        Resp {
+            val: Value::Attr(attrs, Box::new(self)),
+        }
+    }
+
+    /// Returns the wire type of the value. Nulls report the type they stand in for.
+    pub fn typ(&self) -> Typ {
+        match self.val {
+            Value::Simple(_) => Typ::STRING,
+            Value::Error(_) => Typ::ERROR,
+            Value::Num(_) => Typ::INTEGER,
+            Value::Bulk(_) | Value::Null => Typ::BULK,
+            Value::Arr(_) | Value::NullArr => Typ::ARRAY,
+            Value::Double(_) => Typ::DOUBLE,
+            Value::Boolean(_) => Typ::BOOLEAN,
+            Value::BigNumber(_) => Typ::BIGNUMBER,
+            Value::Verbatim(..) => Typ::VERBATIM,
+            Value::Map(_) => Typ::MAP,
+            Value::Set(_) => Typ::SET,
+            Value::Attr(..) => Typ::ATTRIBUTE,
+            Value::Push(_) => Typ::PUSH,
+        }
+    }
+
+    /// Returns the argument as a string, formatting integers if needed.
+    /// Invalid UTF-8 is replaced, so use `as_bytes` for values.
+    pub fn as_string(&self) -> Option<String> {
+        self.as_bytes()
+            .map(|s| String::from_utf8_lossy(&s).into_owned())
+    }
+
+    /// Returns the argument as raw bytes, formatting integers if needed.
+    pub fn as_bytes(&self) -> Option<Vec<u8>> {
+        match &self.val {
+            Value::Simple(s) | Value::Bulk(s) | Value::Verbatim(_, s) => Some(s.clone()),
+            Value::Num(n) => Some(n.to_string().into_bytes()),
+            _ => None,
+        }
+    }
+}
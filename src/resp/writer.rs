@@ -1,67 +1,163 @@
-use crate::resp::resp::{Resp, Value};
+use crate::resp::resp::{Protocol, Resp, Value};
+use crate::types::string_type::format_float;
 use std::io::{self, Write};
 
 pub struct Writer<W: Write> {
     writer: W,
+    /// Encoded replies that haven't been sent yet.
+    buf: Vec<u8>,
+    protocol: Protocol,
 }
 
 impl<W: Write> Writer<W> {
     pub fn new(writer: W) -> Self {
-        Writer { writer }
+        Writer {
+            writer,
+            buf: Vec::new(),
+            protocol: Protocol::default(),
+        }
     }
 
-    pub fn write(&mut self, v: Resp) -> io::Result<()> {
-        let bytes = v.marshal();
-        let _err: Result<(), io::Error> = self.writer.write_all(&bytes);
-        self.writer.flush()?;
-        Ok(())
+    /// Sets the protocol that replies are encoded with from now on.
+    pub fn set_protocol(&mut self, protocol: Protocol) {
+        self.protocol = protocol;
     }
-}
 
-impl Resp {
-    pub fn marshal(&self) -> Vec<u8> {
-        match &self.val {
-            Value::Arr(arr) => self.marshal_array(arr),
-            Value::Str(s) => self.marshal_string(s),
-            Value::Num(n) => self.marshal_int(*n),
-        }
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.writer
     }
 
-    fn marshal_int(&self, num: i64) -> Vec<u8> {
-        let mut bytes = vec![b':'];
-        bytes.extend_from_slice(num.to_string().as_bytes());
-        bytes.extend_from_slice(b"\r\n");
-        bytes
+    /// Queues a reply. Nothing is sent until `flush` is called,
+    /// so pipelined replies go out together.
+    pub fn write(&mut self, v: &Resp) {
+        v.write_to(&mut self.buf, self.protocol)
+            .expect("writing to a Vec cannot fail");
     }
 
-    fn marshal_string(&self, s: &str) -> Vec<u8> {
-        let mut bytes = vec![b'+'];
-        bytes.extend_from_slice(s.as_bytes());
-        bytes.extend_from_slice(b"\r\n");
-        bytes
+    /// Sends as much queued output as the underlying writer accepts.
+    /// Whatever couldn't be sent stays queued, so after a `WouldBlock` error
+    /// call again once the socket is writable.
+    pub fn flush(&mut self) -> io::Result<()> {
+        let mut sent = 0;
+        let result = loop {
+            if sent == self.buf.len() {
+                break self.writer.flush();
+            }
+            match self.writer.write(&self.buf[sent..]) {
+                Ok(0) => break Err(io::ErrorKind::WriteZero.into()),
+                Ok(n) => sent += n,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => break Err(e),
+            }
+        };
+        self.buf.drain(..sent);
+        result
     }
+}
 
-    fn marshal_array(&self, arr: &[Resp]) -> Vec<u8> {
-        let mut bytes = vec![b'*'];
-        bytes.extend_from_slice(arr.len().to_string().as_bytes());
-        bytes.extend_from_slice(b"\r\n");
-
-        for item in arr {
-            bytes.extend_from_slice(&item.marshal());
+impl Resp {
+    /// Streams the encoding for `protocol` into `out`, element by element,
+    /// without building the nested values up in memory first.
+    /// RESP3 types are downgraded under RESP2: maps become flat arrays,
+    /// doubles and big numbers bulk strings, booleans integers,
+    /// and attributes are dropped.
+    pub fn write_to<W: Write>(&self, out: &mut W, protocol: Protocol) -> io::Result<()> {
+        let resp3 = protocol == Protocol::Resp3;
+        match &self.val {
+            Value::Simple(s) => write_line(out, b'+', s),
+            Value::Error(s) => write_line(out, b'-', s),
+            Value::Num(n) => write!(out, ":{}\r\n", n),
+            Value::Bulk(s) => write_bulk(out, b'$', s),
+            Value::Arr(arr) => write_array(out, b'*', arr, protocol),
+            Value::Null | Value::NullArr if resp3 => out.write_all(b"_\r\n"),
+            Value::Null => out.write_all(b"$-1\r\n"),
+            Value::NullArr => out.write_all(b"*-1\r\n"),
+            Value::Double(n) => {
+                let s = if n.is_nan() {
+                    "nan".to_string()
+                } else {
+                    format_float(*n)
+                };
+                if resp3 {
+                    write_line(out, b',', s.as_bytes())
+                } else {
+                    write_bulk(out, b'$', s.as_bytes())
+                }
+            }
+            Value::Boolean(b) if resp3 => write_line(out, b'#', if *b { b"t" } else { b"f" }),
+            Value::Boolean(b) => write_line(out, b':', if *b { b"1" } else { b"0" }),
+            Value::BigNumber(n) if resp3 => write_line(out, b'(', n.as_bytes()),
+            Value::BigNumber(n) => write_bulk(out, b'$', n.as_bytes()),
+            Value::Verbatim(format, s) if resp3 => {
+                write_len(out, b'=', format.len() + 1 + s.len())?;
+                out.write_all(format.as_bytes())?;
+                out.write_all(b":")?;
+                out.write_all(s)?;
+                out.write_all(b"\r\n")
+            }
+            Value::Verbatim(_, s) => write_bulk(out, b'$', s),
+            Value::Map(pairs) if resp3 => {
+                write_len(out, b'%', pairs.len())?;
+                write_pairs(out, pairs, protocol)
+            }
+            Value::Map(pairs) => {
+                write_len(out, b'*', pairs.len() * 2)?;
+                write_pairs(out, pairs, protocol)
+            }
+            Value::Set(items) => write_array(out, if resp3 { b'~' } else { b'*' }, items, protocol),
+            Value::Attr(attrs, inner) if resp3 => {
+                write_len(out, b'|', attrs.len())?;
+                write_pairs(out, attrs, protocol)?;
+                inner.write_to(out, protocol)
+            }
+            Value::Attr(_, inner) => inner.write_to(out, protocol),
+            Value::Push(items) => {
+                write_array(out, if resp3 { b'>' } else { b'*' }, items, protocol)
+            }
         }
-
-        bytes
     }
+}
 
-    // Optional: add null and error if you want
-    pub fn marshal_null() -> Vec<u8> {
-        b"$-1\r\n".to_vec()
+/// Writes a single-line value: the type byte, the payload and CRLF.
+fn write_line<W: Write>(out: &mut W, prefix: u8, s: &[u8]) -> io::Result<()> {
+    out.write_all(&[prefix])?;
+    out.write_all(s)?;
+    out.write_all(b"\r\n")
+}
+
+/// Writes an aggregate or blob header: the type byte, a length and CRLF.
+fn write_len<W: Write>(out: &mut W, prefix: u8, len: usize) -> io::Result<()> {
+    write!(out, "{}{}\r\n", prefix as char, len)
+}
+
+fn write_bulk<W: Write>(out: &mut W, prefix: u8, s: &[u8]) -> io::Result<()> {
+    write_len(out, prefix, s.len())?;
+    out.write_all(s)?;
+    out.write_all(b"\r\n")
+}
+
+fn write_array<W: Write>(
+    out: &mut W,
+    prefix: u8,
+    arr: &[Resp],
+    protocol: Protocol,
+) -> io::Result<()> {
+    write_len(out, prefix, arr.len())?;
+    for item in arr {
+        item.write_to(out, protocol)?;
     }
+    Ok(())
+}
 
-    pub fn marshal_error(s: &str) -> Vec<u8> {
-        let mut bytes = vec![b'-'];
-        bytes.extend_from_slice(s.as_bytes());
-        bytes.extend_from_slice(b"\r\n");
-        bytes
+/// Writes each key followed by its value.
+fn write_pairs<W: Write>(
+    out: &mut W,
+    pairs: &[(Resp, Resp)],
+    protocol: Protocol,
+) -> io::Result<()> {
+    for (k, v) in pairs {
+        k.write_to(out, protocol)?;
+        v.write_to(out, protocol)?;
     }
+    Ok(())
 }
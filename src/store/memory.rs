@@ -0,0 +1,38 @@
+use std::mem::size_of;
+
+/// How many elements of a collection are measured when estimating its size.
+/// Like Redis' MEMORY USAGE, the rest are assumed to be the same on average.
+const SAMPLES: usize = 5;
+
+/// Approximate heap memory owned by a value, not counting the value itself.
+/// Used to charge stored entries against `maxmemory`.
+pub trait MemSize {
+    fn heap_size(&self) -> usize;
+}
+
+impl MemSize for Vec<u8> {
+    fn heap_size(&self) -> usize {
+        self.capacity()
+    }
+}
+
+impl MemSize for String {
+    fn heap_size(&self) -> usize {
+        self.capacity()
+    }
+}
+
+/// Estimates the heap size of a collection of `len` elements by measuring the
+/// first few `items` with `size`, so large collections aren't walked on every write.
+pub fn estimate<T>(items: impl Iterator<Item = T>, len: usize, size: impl Fn(T) -> usize) -> usize {
+    let (count, total) = items.take(SAMPLES).fold((0, 0), |(count, total), item| {
+        (count + 1, total + size(item))
+    });
+    (total * len).checked_div(count).unwrap_or(0)
+}
+
+/// Heap size of a byte string held as an element of a collection,
+/// including the `Vec` header stored in the collection's own buffer.
+pub fn element_size(bytes: &Vec<u8>) -> usize {
+    size_of::<Vec<u8>>() + bytes.heap_size()
+}
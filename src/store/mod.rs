@@ -1 +1,6 @@
+pub mod memory;
+pub mod skiplist;
+#[allow(clippy::module_inception)]
 pub mod store;
+pub mod stream;
+pub mod zset;
@@ -0,0 +1,426 @@
+use std::mem::size_of;
+
+/// Most levels a node can have, enough for 4^32 elements.
+const MAX_LEVEL: usize = 32;
+/// Index of the head node, which has every level and no key.
+const HEAD: usize = 0;
+/// Stands in for a missing link.
+const NIL: usize = usize::MAX;
+
+#[derive(Clone, Copy)]
+struct Level {
+    next: usize,
+    /// How many positions the link skips, so ranks can be summed on the way down.
+    span: usize,
+}
+
+#[derive(Clone)]
+struct Node<K> {
+    /// `None` for the head and for freed slots.
+    key: Option<K>,
+    levels: Vec<Level>,
+    prev: usize,
+}
+
+/// An ordered set that, like Redis' zskiplist, keeps the number of positions
+/// each link skips, so finding a key's rank or the key at a rank takes
+/// O(log n) instead of a walk from the front. Nodes live in one `Vec` and
+/// link to each other by index.
+#[derive(Clone)]
+pub struct SkipList<K> {
+    nodes: Vec<Node<K>>,
+    /// Slots of removed nodes, reused by later inserts.
+    free: Vec<usize>,
+    tail: usize,
+    /// Levels in use by the head.
+    level: usize,
+    len: usize,
+}
+
+impl<K: Ord> Default for SkipList<K> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K: Ord> SkipList<K> {
+    pub fn new() -> Self {
+        SkipList {
+            nodes: vec![Node {
+                key: None,
+                levels: vec![Level { next: NIL, span: 0 }; MAX_LEVEL],
+                prev: NIL,
+            }],
+            free: Vec::new(),
+            tail: NIL,
+            level: 1,
+            len: 0,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Approximate bytes each element takes besides its key's heap memory.
+    pub fn node_size() -> usize {
+        // A node has 4/3 levels on average.
+        size_of::<Node<K>>() + size_of::<Level>() * 4 / 3
+    }
+
+    fn key(&self, node: usize) -> &K {
+        self.nodes[node]
+            .key
+            .as_ref()
+            .expect("linked nodes have keys")
+    }
+
+    fn link(&self, node: usize, level: usize) -> Level {
+        self.nodes[node].levels[level]
+    }
+
+    /// Finds, on every level, the last node whose key is `before` the position
+    /// sought, along with that node's 1-based rank (0 for the head). `before`
+    /// must hold for a prefix of the keys and for none after it.
+    fn seek(&self, before: impl Fn(&K) -> bool) -> ([usize; MAX_LEVEL], [usize; MAX_LEVEL]) {
+        let mut update = [HEAD; MAX_LEVEL];
+        let mut rank = [0; MAX_LEVEL];
+        let mut x = HEAD;
+        for i in (0..self.level).rev() {
+            rank[i] = if i + 1 == self.level { 0 } else { rank[i + 1] };
+            loop {
+                let Level { next, span } = self.link(x, i);
+                if next == NIL || !before(self.key(next)) {
+                    break;
+                }
+                rank[i] += span;
+                x = next;
+            }
+            update[i] = x;
+        }
+        (update, rank)
+    }
+
+    fn random_level() -> usize {
+        let mut level = 1;
+        while level < MAX_LEVEL && fastrand::u8(..4) == 0 {
+            level += 1;
+        }
+        level
+    }
+
+    /// Inserts `key`, which must not be in the list yet.
+    pub fn insert(&mut self, key: K) {
+        let (mut update, mut rank) = self.seek(|k| k < &key);
+        let level = Self::random_level();
+        if level > self.level {
+            for i in self.level..level {
+                rank[i] = 0;
+                update[i] = HEAD;
+                self.nodes[HEAD].levels[i].span = self.len;
+            }
+            self.level = level;
+        }
+        let node = Node {
+            key: Some(key),
+            levels: vec![Level { next: NIL, span: 0 }; level],
+            prev: if update[0] == HEAD { NIL } else { update[0] },
+        };
+        let x = match self.free.pop() {
+            Some(slot) => {
+                self.nodes[slot] = node;
+                slot
+            }
+            None => {
+                self.nodes.push(node);
+                self.nodes.len() - 1
+            }
+        };
+        for i in 0..level {
+            let before = self.link(update[i], i);
+            self.nodes[x].levels[i] = Level {
+                next: before.next,
+                span: before.span - (rank[0] - rank[i]),
+            };
+            self.nodes[update[i]].levels[i] = Level {
+                next: x,
+                span: rank[0] - rank[i] + 1,
+            };
+        }
+        for (i, &before) in update.iter().enumerate().take(self.level).skip(level) {
+            self.nodes[before].levels[i].span += 1;
+        }
+        match self.link(x, 0).next {
+            NIL => self.tail = x,
+            next => self.nodes[next].prev = x,
+        }
+        self.len += 1;
+    }
+
+    /// Removes `key` and returns it, if it was in the list.
+    pub fn remove(&mut self, key: &K) -> Option<K> {
+        let (update, _) = self.seek(|k| k < key);
+        let x = self.link(update[0], 0).next;
+        if x == NIL || self.key(x) != key {
+            return None;
+        }
+        for (i, &before) in update.iter().enumerate().take(self.level) {
+            if self.link(before, i).next == x {
+                let removed = self.link(x, i);
+                let link = &mut self.nodes[before].levels[i];
+                link.span = link.span + removed.span - 1;
+                link.next = removed.next;
+            } else {
+                self.nodes[before].levels[i].span -= 1;
+            }
+        }
+        let prev = self.nodes[x].prev;
+        match self.link(x, 0).next {
+            NIL => self.tail = prev,
+            next => self.nodes[next].prev = prev,
+        }
+        while self.level > 1 && self.link(HEAD, self.level - 1).next == NIL {
+            self.level -= 1;
+        }
+        self.len -= 1;
+        self.free.push(x);
+        let node = &mut self.nodes[x];
+        node.levels = Vec::new();
+        node.key.take()
+    }
+
+    /// Returns how many keys are ordered before `key`, which is the 0-based
+    /// rank of `key` if it is in the list.
+    pub fn rank_of(&self, key: &K) -> usize {
+        self.partition_point(|k| k < key)
+    }
+
+    /// Returns how many keys `pred` holds for, given that it holds for a prefix
+    /// of the keys and for none after it, like `slice::partition_point`.
+    pub fn partition_point(&self, pred: impl Fn(&K) -> bool) -> usize {
+        self.seek(pred).1[0]
+    }
+
+    /// Returns the node at 0-based `rank`.
+    fn node_at(&self, rank: usize) -> usize {
+        let target = rank + 1;
+        let mut traversed = 0;
+        let mut x = HEAD;
+        for i in (0..self.level).rev() {
+            loop {
+                let Level { next, span } = self.link(x, i);
+                if next == NIL || traversed + span > target {
+                    break;
+                }
+                traversed += span;
+                x = next;
+            }
+            if traversed == target {
+                return x;
+            }
+        }
+        unreachable!("rank {rank} is out of bounds");
+    }
+
+    /// Returns the key at 0-based `rank`, if there is one.
+    pub fn get(&self, rank: usize) -> Option<&K> {
+        (rank < self.len).then(|| self.key(self.node_at(rank)))
+    }
+
+    pub fn first(&self) -> Option<&K> {
+        self.get(0)
+    }
+
+    pub fn last(&self) -> Option<&K> {
+        (self.tail != NIL).then(|| self.key(self.tail))
+    }
+
+    /// Iterates the keys in order.
+    pub fn iter(&self) -> Iter<'_, K> {
+        self.iter_ranks(0, self.len)
+    }
+
+    /// Iterates the keys from 0-based rank `start` up to, not including, `end`.
+    pub fn iter_ranks(&self, start: usize, end: usize) -> Iter<'_, K> {
+        let end = end.min(self.len);
+        if start >= end {
+            return Iter {
+                list: self,
+                front: NIL,
+                back: NIL,
+                remaining: 0,
+            };
+        }
+        Iter {
+            list: self,
+            front: self.node_at(start),
+            back: if end == self.len {
+                self.tail
+            } else {
+                self.node_at(end - 1)
+            },
+            remaining: end - start,
+        }
+    }
+}
+
+pub struct Iter<'a, K> {
+    list: &'a SkipList<K>,
+    front: usize,
+    back: usize,
+    remaining: usize,
+}
+
+impl<'a, K: Ord> Iterator for Iter<'a, K> {
+    type Item = &'a K;
+
+    fn next(&mut self) -> Option<&'a K> {
+        if self.remaining == 0 {
+            return None;
+        }
+        let node = self.front;
+        self.front = self.list.link(node, 0).next;
+        self.remaining -= 1;
+        Some(self.list.key(node))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<'a, K: Ord> DoubleEndedIterator for Iter<'a, K> {
+    fn next_back(&mut self) -> Option<&'a K> {
+        if self.remaining == 0 {
+            return None;
+        }
+        let node = self.back;
+        self.back = self.list.nodes[node].prev;
+        self.remaining -= 1;
+        Some(self.list.key(node))
+    }
+}
+
+impl<K: Ord> ExactSizeIterator for Iter<'_, K> {}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+
+    use super::*;
+
+    /// Checks every read operation of `list` against the `model` it should match.
+    fn assert_matches(list: &SkipList<(u32, u32)>, model: &BTreeSet<(u32, u32)>) {
+        let keys: Vec<_> = model.iter().copied().collect();
+        assert_eq!(list.len(), keys.len());
+        assert_eq!(list.iter().copied().collect::<Vec<_>>(), keys);
+        assert!(list.iter().rev().copied().eq(keys.iter().rev().copied()));
+        assert_eq!(list.first(), keys.first());
+        assert_eq!(list.last(), keys.last());
+        for (rank, key) in keys.iter().enumerate() {
+            assert_eq!(list.get(rank), Some(key));
+            assert_eq!(list.rank_of(key), rank);
+        }
+        assert_eq!(list.get(keys.len()), None);
+        // Absent keys rank where they would be inserted.
+        for key in [(0, 0), (7, u32::MAX), (u32::MAX, 0)] {
+            assert_eq!(list.rank_of(&key), keys.partition_point(|k| k < &key));
+        }
+    }
+
+    #[test]
+    fn matches_a_btree_set_model() {
+        for round in 0..20 {
+            let mut list = SkipList::new();
+            let mut model = BTreeSet::new();
+            // Few distinct scores, so many keys share one and order by member.
+            let scores = 1 + round;
+            for _ in 0..2000 {
+                let key = (fastrand::u32(..scores), fastrand::u32(..50));
+                if fastrand::u8(..3) > 0 {
+                    if model.insert(key) {
+                        list.insert(key);
+                    }
+                } else {
+                    assert_eq!(list.remove(&key), model.take(&key));
+                }
+            }
+            assert_matches(&list, &model);
+        }
+    }
+
+    #[test]
+    fn iterates_rank_ranges_both_ways() {
+        let mut list = SkipList::new();
+        for i in 0..300u32 {
+            list.insert((i % 7, i));
+        }
+        let keys: Vec<_> = list.iter().copied().collect();
+        for _ in 0..500 {
+            let start = fastrand::usize(..=keys.len() + 2);
+            let end = fastrand::usize(..=keys.len() + 2);
+            let expected: &[(u32, u32)] = if start < end.min(keys.len()) {
+                &keys[start..end.min(keys.len())]
+            } else {
+                &[]
+            };
+            let iter = list.iter_ranks(start, end);
+            assert_eq!(iter.len(), expected.len());
+            assert!(iter.copied().eq(expected.iter().copied()));
+            let rev = list.iter_ranks(start, end).rev();
+            assert!(rev.copied().eq(expected.iter().rev().copied()));
+        }
+        // Iterating from both ends meets in the middle.
+        let mut iter = list.iter_ranks(10, 14);
+        assert_eq!(iter.next(), Some(&keys[10]));
+        assert_eq!(iter.next_back(), Some(&keys[13]));
+        assert_eq!(iter.next(), Some(&keys[11]));
+        assert_eq!(iter.next_back(), Some(&keys[12]));
+        assert_eq!(iter.next(), None);
+        assert_eq!(iter.next_back(), None);
+    }
+
+    #[test]
+    fn removes_the_head_and_tail() {
+        let mut list = SkipList::new();
+        let mut model = BTreeSet::new();
+        for i in 0..100u32 {
+            list.insert((i / 10, i));
+            model.insert((i / 10, i));
+        }
+        while !model.is_empty() {
+            let key = if model.len() % 2 == 0 {
+                *model.first().unwrap()
+            } else {
+                *model.last().unwrap()
+            };
+            assert_eq!(list.remove(&key), model.take(&key));
+            assert_matches(&list, &model);
+        }
+        assert!(list.is_empty());
+        assert_eq!(list.first(), None);
+        assert_eq!(list.last(), None);
+        assert_eq!(list.remove(&(0, 0)), None);
+        // Freed slots are reused by later inserts.
+        list.insert((1, 1));
+        list.insert((0, 0));
+        assert_eq!(list.iter().copied().collect::<Vec<_>>(), [(0, 0), (1, 1)]);
+        assert_eq!(list.nodes.len(), 101);
+    }
+
+    #[test]
+    fn finds_partition_points() {
+        let mut list = SkipList::new();
+        for i in 0..50u32 {
+            list.insert((i / 5, i));
+        }
+        assert_eq!(list.partition_point(|_| true), 50);
+        assert_eq!(list.partition_point(|_| false), 0);
+        assert_eq!(list.partition_point(|(score, _)| *score < 3), 15);
+        assert_eq!(list.partition_point(|(score, _)| *score <= 3), 20);
+    }
+}
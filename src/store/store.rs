@@ -1,22 +1,35 @@
-use std::collections::{HashMap, HashSet};
-use std::hash::Hash;
-use std::num::NonZero;
-use std::sync::{Arc, OnceLock, RwLock};
+use std::borrow::Borrow;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::hash::{BuildHasher, Hash, RandomState};
+use std::mem::size_of;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, OnceLock, PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard};
 use std::thread;
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
-use lru::LruCache;
+use indexmap::{IndexMap, IndexSet};
+use smallvec::SmallVec;
+
+use crate::config::{
+    EVICTION_POLICIES, LFU_DECAY_TIME, LFU_LOG_FACTOR, MAXMEMORY, MAXMEMORY_POLICY,
+    MAXMEMORY_SAMPLES,
+};
+use crate::store::memory::{MemSize, element_size, estimate};
+use crate::store::stream::Stream;
+use crate::store::zset::ZSet;
 
 #[derive(Clone)]
 pub enum StoreVal {
-    Str(String),
-    Hash(HashMap<String, String>),
-    Set(HashSet<String>),
-    List(Vec<String>),
+    Str(Vec<u8>),
+    Hash(HashMap<Vec<u8>, Vec<u8>>),
+    Set(HashSet<Vec<u8>>),
+    List(VecDeque<Vec<u8>>),
+    ZSet(ZSet),
+    Stream(Stream),
 }
 
 impl StoreVal {
-    pub fn get_str(&self) -> Option<&String> {
+    pub fn get_str(&self) -> Option<&Vec<u8>> {
         if let StoreVal::Str(s) = self {
             Some(s)
         } else {
@@ -24,7 +37,15 @@ impl StoreVal {
         }
     }
 
-    pub fn get_hash(&self) -> Option<&HashMap<String, String>> {
+    pub fn get_str_mut(&mut self) -> Option<&mut Vec<u8>> {
+        if let StoreVal::Str(s) = self {
+            Some(s)
+        } else {
+            None
+        }
+    }
+
+    pub fn get_hash(&self) -> Option<&HashMap<Vec<u8>, Vec<u8>>> {
         if let StoreVal::Hash(h) = self {
             Some(h)
         } else {
@@ -32,7 +53,23 @@ impl StoreVal {
         }
     }
 
-    pub fn get_set(&self) -> Option<&HashSet<String>> {
+    pub fn get_hash_mut(&mut self) -> Option<&mut HashMap<Vec<u8>, Vec<u8>>> {
+        if let StoreVal::Hash(h) = self {
+            Some(h)
+        } else {
+            None
+        }
+    }
+
+    pub fn get_set(&self) -> Option<&HashSet<Vec<u8>>> {
+        if let StoreVal::Set(s) = self {
+            Some(s)
+        } else {
+            None
+        }
+    }
+
+    pub fn get_set_mut(&mut self) -> Option<&mut HashSet<Vec<u8>>> {
         if let StoreVal::Set(s) = self {
             Some(s)
         } else {
@@ -40,71 +77,481 @@ impl StoreVal {
         }
     }
 
-    pub fn get_list(&self) -> Option<&Vec<String>> {
+    pub fn get_list(&self) -> Option<&VecDeque<Vec<u8>>> {
         if let StoreVal::List(l) = self {
             Some(l)
         } else {
             None
         }
     }
+
+    pub fn get_list_mut(&mut self) -> Option<&mut VecDeque<Vec<u8>>> {
+        if let StoreVal::List(l) = self {
+            Some(l)
+        } else {
+            None
+        }
+    }
+
+    pub fn get_zset(&self) -> Option<&ZSet> {
+        if let StoreVal::ZSet(z) = self {
+            Some(z)
+        } else {
+            None
+        }
+    }
+
+    pub fn get_zset_mut(&mut self) -> Option<&mut ZSet> {
+        if let StoreVal::ZSet(z) = self {
+            Some(z)
+        } else {
+            None
+        }
+    }
+
+    pub fn get_stream(&self) -> Option<&Stream> {
+        if let StoreVal::Stream(s) = self {
+            Some(s)
+        } else {
+            None
+        }
+    }
+
+    pub fn get_stream_mut(&mut self) -> Option<&mut Stream> {
+        if let StoreVal::Stream(s) = self {
+            Some(s)
+        } else {
+            None
+        }
+    }
+}
+
+impl MemSize for StoreVal {
+    fn heap_size(&self) -> usize {
+        match self {
+            StoreVal::Str(s) => s.heap_size(),
+            StoreVal::Hash(h) => estimate(h.iter(), h.len(), |(f, v)| {
+                element_size(f) + element_size(v)
+            }),
+            StoreVal::Set(s) => estimate(s.iter(), s.len(), element_size),
+            StoreVal::List(l) => estimate(l.iter(), l.len(), element_size),
+            StoreVal::ZSet(z) => z.heap_size(),
+            StoreVal::Stream(s) => s.heap_size(),
+        }
+    }
 }
 
 /// Value stored in the cache with optional TTL
-#[derive(Clone)]
 struct Value<V> {
     val: V,
-    ttl: Option<u64>, // Unix timestamp in seconds
+    ttl: Option<u64>, // Unix timestamp in milliseconds
+    /// Heap bytes `val` was last measured at, as charged to the shard's `used`.
+    size: usize,
+    /// The shard's `tick` when the entry was last read or written. Readers bump
+    /// it through a shared reference, so lookups never reorder the shard.
+    access: AtomicU64,
+    /// Logarithmic access frequency counter for the LFU policies in the low
+    /// 8 bits, and above them the Unix minute it was last decayed at.
+    lfu: AtomicU64,
 }
 
-/// The Store itself
-pub struct Store<K, V>
-where
-    K: Eq + Hash + Clone,
-{
-    cache: RwLock<LruCache<K, Value<V>>>,
+/// Counter a new entry starts at, so it isn't evicted before it has had a chance to be read.
+const LFU_INIT_VAL: u64 = 5;
+const LFU_MAX: u64 = 255;
+
+impl<V: MemSize> Value<V> {
+    fn new(val: V, ttl: Option<u64>) -> Self {
+        Value {
+            size: val.heap_size(),
+            val,
+            ttl,
+            access: AtomicU64::new(0),
+            lfu: AtomicU64::new(current_unix_minutes() << 8 | LFU_INIT_VAL),
+        }
+    }
 }
 
-static GLOBAL_STORE: OnceLock<Arc<Store<String, StoreVal>>> = OnceLock::new();
-static GLOBAL_LOCK: RwLock<()> = RwLock::new(());
+impl<V> Value<V> {
+    fn is_expired(&self) -> bool {
+        self.ttl.is_some_and(|ttl| ttl <= current_unix_millis())
+    }
 
-// Global guards to implement explicit read/write lock API
-static mut READ_GUARD: Option<std::sync::RwLockReadGuard<'static, ()>> = None;
-static mut WRITE_GUARD: Option<std::sync::RwLockWriteGuard<'static, ()>> = None;
+    /// The frequency counter, less one for every `lfu-decay-time` minutes
+    /// since it was last decayed.
+    fn frequency(&self, now_minutes: u64) -> u64 {
+        let lfu = self.lfu.load(Ordering::Relaxed);
+        let periods = match LFU_DECAY_TIME.get() {
+            0 => 0,
+            decay => now_minutes.saturating_sub(lfu >> 8) / decay,
+        };
+        (lfu & LFU_MAX).saturating_sub(periods)
+    }
 
-/// Acquire a read lock
-pub fn read_lock() {
-    unsafe {
-        READ_GUARD = Some(GLOBAL_LOCK.read().unwrap());
+    /// Decays the frequency counter, then increments it with a probability that
+    /// shrinks as it grows, scaled by `lfu-log-factor`, so 8 bits can tell
+    /// rarely read keys from ones read millions of times.
+    fn count_access(&self) {
+        let now = current_unix_minutes();
+        let mut counter = self.frequency(now);
+        if counter < LFU_MAX {
+            let base = counter.saturating_sub(LFU_INIT_VAL);
+            let p = 1.0 / (base * LFU_LOG_FACTOR.get() + 1) as f64;
+            if fastrand::f64() < p {
+                counter += 1;
+            }
+        }
+        self.lfu.store(now << 8 | counter, Ordering::Relaxed);
     }
 }
 
-/// Release a read lock
-pub fn read_unlock() {
-    unsafe {
-        READ_GUARD = None; // dropping the guard unlocks
+/// Whether the current `maxmemory-policy` is an LFU one. Access frequency is
+/// only counted while it is, which spares every read a clock lookup otherwise.
+pub fn counts_frequency() -> bool {
+    EVICTION_POLICIES[MAXMEMORY_POLICY.get() as usize].ends_with("lfu")
+}
+
+/// Number of independently locked partitions the keyspace is split into.
+const SHARDS: usize = 16;
+
+/// How keys are picked for eviction, parsed from `maxmemory-policy`.
+#[derive(Clone, Copy)]
+struct Eviction {
+    /// Only keys with a TTL may be evicted.
+    volatile: bool,
+    rank: Rank,
+}
+
+/// Which of the sampled entries is evicted: the one ranked lowest.
+#[derive(Clone, Copy)]
+enum Rank {
+    Lru,
+    Lfu,
+    Random,
+    Ttl,
+}
+
+impl Eviction {
+    /// The current policy, or `None` for `noeviction`.
+    fn current() -> Option<Self> {
+        let (keys, rank) = EVICTION_POLICIES[MAXMEMORY_POLICY.get() as usize].split_once('-')?;
+        let rank = match rank {
+            "lru" => Rank::Lru,
+            "lfu" => Rank::Lfu,
+            "ttl" => Rank::Ttl,
+            _ => Rank::Random,
+        };
+        Some(Eviction {
+            volatile: keys == "volatile",
+            rank,
+        })
+    }
+
+    fn rank<V>(&self, value: &Value<V>, now_minutes: u64) -> u64 {
+        match self.rank {
+            Rank::Lru => value.access.load(Ordering::Relaxed),
+            Rank::Lfu => value.frequency(now_minutes),
+            Rank::Random => 0,
+            Rank::Ttl => value.ttl.unwrap_or(u64::MAX),
+        }
     }
 }
 
-/// Acquire a write lock
-pub fn write_lock() {
-    unsafe {
-        WRITE_GUARD = Some(GLOBAL_LOCK.write().unwrap());
+/// One partition of the keyspace, with its own expiry and memory bookkeeping.
+struct Shard<K, V> {
+    /// Indexable so eviction can sample entries at random.
+    entries: IndexMap<K, Value<V>>,
+    /// Keys in `entries` that have a TTL, so the cleaner doesn't have to scan
+    /// every key. Indexable so the volatile policies can sample them.
+    expires: IndexSet<K>,
+    /// Approximate bytes held by the entries, keys included.
+    used: usize,
+    /// Logical clock stamped on entries as they are accessed. Eviction only
+    /// compares entries within a shard, so it needn't be wall time.
+    tick: AtomicU64,
+}
+
+impl<K, V> Shard<K, V>
+where
+    K: Eq + Hash + Clone + MemSize,
+    V: MemSize,
+{
+    fn new() -> Self {
+        Shard {
+            entries: IndexMap::new(),
+            expires: IndexSet::new(),
+            used: 0,
+            tick: AtomicU64::new(0),
+        }
+    }
+
+    /// Bytes charged for an entry whose key owns `key_size` heap bytes.
+    fn entry_size(key_size: usize, value: &Value<V>) -> usize {
+        size_of::<(K, Value<V>)>() + key_size + value.size
+    }
+
+    /// Returns the entry for `key` unless it has expired, marking it as accessed.
+    /// Expired entries are left for a writer or the cleaner to drop.
+    fn live<Q>(&self, key: &Q) -> Option<&Value<V>>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let value = self.peek(key)?;
+        self.touch(value);
+        Some(value)
+    }
+
+    /// Like `live`, but without marking the entry as accessed.
+    fn peek<Q>(&self, key: &Q) -> Option<&Value<V>>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.entries.get(key).filter(|value| !value.is_expired())
+    }
+
+    /// Like `live`, but drops the entry first if it has expired.
+    fn live_mut<Q>(&mut self, key: &Q) -> Option<&mut Value<V>>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let index = self.live_index(key)?;
+        Some(&mut self.entries[index])
+    }
+
+    /// Like `live_mut`, but returns the position of the entry.
+    fn live_index<Q>(&mut self, key: &Q) -> Option<usize>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let index = self.entries.get_index_of(key)?;
+        if self.entries[index].is_expired() {
+            self.remove(key);
+            return None;
+        }
+        let now = self.tick.fetch_add(1, Ordering::Relaxed);
+        let value = &mut self.entries[index];
+        *value.access.get_mut() = now;
+        if counts_frequency() {
+            value.count_access();
+        }
+        Some(index)
     }
+
+    fn touch(&self, value: &Value<V>) {
+        let now = self.tick.fetch_add(1, Ordering::Relaxed);
+        value.access.store(now, Ordering::Relaxed);
+        if counts_frequency() {
+            value.count_access();
+        }
+    }
+
+    /// Runs `f` on the live value at `key` and re-measures it afterwards.
+    fn with_mut<Q, R>(&mut self, key: &Q, f: impl FnOnce(&mut V) -> R) -> Option<R>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let index = self.live_index(key)?;
+        Some(self.update(index, f))
+    }
+
+    /// Runs `f` on the value at position `index` and re-measures it afterwards.
+    fn update<R>(&mut self, index: usize, f: impl FnOnce(&mut V) -> R) -> R {
+        let value = &mut self.entries[index];
+        let result = f(&mut value.val);
+        let size = value.val.heap_size();
+        let old = std::mem::replace(&mut value.size, size);
+        self.used = self.used + size - old;
+        result
+    }
+
+    /// Sets the live entry at `key` to expire at the Unix time in milliseconds
+    /// `at`, or never with `None`. A time that has already passed drops the
+    /// entry. Returns false if there is no such entry.
+    fn set_expiry<Q>(&mut self, key: &Q, at: Option<u64>) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        if self.live_mut(key).is_none() {
+            return false;
+        }
+        if at.is_some_and(|at| at <= current_unix_millis()) {
+            self.remove(key);
+            return true;
+        }
+        let (_, stored, value) = self.entries.get_full_mut(key).expect("entry is live");
+        value.ttl = at;
+        if at.is_some() {
+            self.expires.insert(stored.clone());
+        } else {
+            self.expires.swap_remove(key);
+        }
+        true
+    }
+
+    fn insert(&mut self, key: K, value: Value<V>) -> usize {
+        if value.ttl.is_some() {
+            self.expires.insert(key.clone());
+        } else {
+            self.expires.swap_remove(&key);
+        }
+        self.touch(&value);
+        // A replaced entry keeps its original key, so both sizes use the same one.
+        let key_size = key.heap_size();
+        self.used += Self::entry_size(key_size, &value);
+        let (index, old) = self.entries.insert_full(key, value);
+        if let Some(old) = old {
+            self.used -= Self::entry_size(key_size, &old);
+        }
+        index
+    }
+
+    /// Drops the entry `policy` ranks lowest among `maxmemory-samples` randomly
+    /// chosen ones, which approximates the policy without keeping entries
+    /// ordered. Returns false if the shard has no entry the policy may evict.
+    fn evict(&mut self, policy: Eviction) -> bool {
+        let pool = if policy.volatile {
+            self.expires.len()
+        } else {
+            self.entries.len()
+        };
+        if pool == 0 {
+            return false;
+        }
+        let now = current_unix_minutes();
+        let victim = (0..MAXMEMORY_SAMPLES.get())
+            .map(|_| {
+                let i = fastrand::usize(..pool);
+                if policy.volatile {
+                    self.entries
+                        .get_index_of(&self.expires[i])
+                        .expect("expiring keys are stored")
+                } else {
+                    i
+                }
+            })
+            .min_by_key(|&i| policy.rank(&self.entries[i], now))
+            .expect("at least one sample");
+        let (key, value) = self
+            .entries
+            .swap_remove_index(victim)
+            .expect("victim is in range");
+        self.expires.swap_remove(&key);
+        self.used -= Self::entry_size(key.heap_size(), &value);
+        true
+    }
+
+    fn remove<Q>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.expires.swap_remove(key);
+        let (key, value) = self.entries.swap_remove_entry(key)?;
+        self.used -= Self::entry_size(key.heap_size(), &value);
+        Some(value.val)
+    }
+
+    fn clean_expired(&mut self, now: u64) {
+        let expired: Vec<K> = self
+            .expires
+            .iter()
+            .filter(|k| {
+                self.entries
+                    .get(*k)
+                    .is_some_and(|v| v.ttl.is_some_and(|ttl| ttl <= now))
+            })
+            .cloned()
+            .collect();
+        for key in expired {
+            self.remove(&key);
+        }
+    }
+}
+
+/// The Store itself, split into shards by key hash so that commands on
+/// different keys don't contend for the same lock.
+pub struct Store<K, V>
+where
+    K: Eq + Hash + Clone,
+{
+    shards: Vec<RwLock<Shard<K, V>>>,
+    /// Each shard's `used`, published whenever its write lock is released,
+    /// so memory usage can be read without locking every shard.
+    used: Vec<AtomicUsize>,
+    /// Shard the next eviction is tried in, so evictions are spread over all of them.
+    next_victim: AtomicUsize,
+    evicted: AtomicU64,
+    hasher: RandomState,
 }
 
-/// Release a write lock
-pub fn write_unlock() {
-    unsafe {
-        WRITE_GUARD = None; // dropping the guard unlocks
+/// Guards on the shards a `Txn` or `View` covers, paired with their indexes.
+/// Most commands touch one or two keys, so these usually stay inline.
+type Locked<G> = SmallVec<[(usize, G); 2]>;
+
+/// Exclusive access to the shards holding a set of keys, taken together by
+/// `Store::lock`. Every write of a value goes through one, so a command's
+/// steps run atomically, and the shards are released when it is dropped,
+/// whichever way the command returns.
+pub struct Txn<'a, K, V>
+where
+    K: Eq + Hash + Clone,
+{
+    store: &'a Store<K, V>,
+    /// The locked shards, sorted by index.
+    shards: Locked<RwLockWriteGuard<'a, Shard<K, V>>>,
+}
+
+/// Shared access to the shards holding a set of keys, taken together by
+/// `Store::read` for commands that only look at values. Views over the same
+/// shards don't block each other.
+pub struct View<'a, K, V>
+where
+    K: Eq + Hash + Clone,
+{
+    store: &'a Store<K, V>,
+    /// The locked shards, sorted by index.
+    shards: Locked<RwLockReadGuard<'a, Shard<K, V>>>,
+}
+
+/// Lookups shared by `Txn` and `View`, so code that only reads values works under either.
+pub trait Lookup<K, V> {
+    /// Runs `f` on the value at `key` without cloning it.
+    fn with<Q, R>(&self, key: &Q, f: impl FnOnce(&V) -> R) -> Option<R>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized;
+
+    fn get<Q>(&self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+        V: Clone,
+    {
+        self.with(key, V::clone)
     }
+
+    /// Returns the Unix time in milliseconds `key` expires at, `Some(None)` if
+    /// it never does, or `None` if it doesn't exist. Doesn't count as an access.
+    fn expire_time<Q>(&self, key: &Q) -> Option<Option<u64>>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized;
 }
 
+/// A transaction over the global keyspace.
+pub type StoreTxn<'a> = Txn<'a, Vec<u8>, StoreVal>;
+
+static GLOBAL_STORE: OnceLock<Arc<Store<Vec<u8>, StoreVal>>> = OnceLock::new();
+
 /// Access the global store
-pub fn global_store() -> &'static Arc<Store<String, StoreVal>> {
+pub fn global_store() -> &'static Arc<Store<Vec<u8>, StoreVal>> {
     GLOBAL_STORE.get_or_init(|| {
-        let store = Arc::new(Store {
-            cache: RwLock::new(LruCache::new(NonZero::new(100_000).unwrap())),
-        });
+        let store = Arc::new(Store::new(SHARDS));
 
         // Background cleaner thread
         let store_clone = store.clone();
@@ -121,59 +568,591 @@ pub fn global_store() -> &'static Arc<Store<String, StoreVal>> {
 
 impl<K, V> Store<K, V>
 where
-    K: Eq + Hash + Clone,
-    V: Clone,
+    K: Eq + Hash + Clone + MemSize,
+    V: MemSize,
 {
-    fn clean_expired(&self) {
-        let mut cache = self.cache.write().unwrap();
-        let now = current_unix_time();
-        let keys_to_remove: Vec<K> = cache
-            .iter()
-            .filter_map(|(k, v)| match v.ttl {
-                Some(ttl) if ttl <= now => Some(k.clone()),
-                _ => None,
-            })
-            .collect();
+    /// Creates an empty store with its keys spread over `shards` partitions.
+    pub fn new(shards: usize) -> Self {
+        Store {
+            shards: (0..shards).map(|_| RwLock::new(Shard::new())).collect(),
+            used: (0..shards).map(|_| AtomicUsize::new(0)).collect(),
+            next_victim: AtomicUsize::new(0),
+            evicted: AtomicU64::new(0),
+            hasher: RandomState::new(),
+        }
+    }
+
+    fn shard_index<Q: Hash + ?Sized>(&self, key: &Q) -> usize {
+        self.hasher.hash_one(key) as usize % self.shards.len()
+    }
 
-        for key in keys_to_remove {
-            cache.pop(&key);
+    /// Returns the shard indexes holding `keys`, sorted and without duplicates.
+    /// Shards are always locked in this order, so two transactions over
+    /// overlapping keys can't deadlock.
+    fn shard_indexes<'k, Q>(&self, keys: impl IntoIterator<Item = &'k Q>) -> SmallVec<[usize; 2]>
+    where
+        Q: Hash + ?Sized + 'k,
+    {
+        let mut indexes: SmallVec<[usize; 2]> =
+            keys.into_iter().map(|k| self.shard_index(k)).collect();
+        indexes.sort_unstable();
+        indexes.dedup();
+        indexes
+    }
+
+    /// Locks the shards holding `keys` for a command that has to change them atomically.
+    pub fn lock<'k, Q>(&self, keys: impl IntoIterator<Item = &'k Q>) -> Txn<'_, K, V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + ?Sized + 'k,
+    {
+        Txn {
+            store: self,
+            shards: self
+                .shard_indexes(keys)
+                .into_iter()
+                .map(|i| {
+                    (
+                        i,
+                        self.shards[i]
+                            .write()
+                            .unwrap_or_else(PoisonError::into_inner),
+                    )
+                })
+                .collect(),
         }
     }
 
-    pub fn get(&self, key: &K) -> Option<V> {
-        let mut cache = self.cache.write().unwrap();
-        if let Some(value) = cache.get(key) {
-            if let Some(ttl) = value.ttl {
-                if ttl <= current_unix_time() {
-                    cache.pop(key);
-                    return None;
+    /// Locks the shards holding `keys` for a command that only reads them.
+    pub fn read<'k, Q>(&self, keys: impl IntoIterator<Item = &'k Q>) -> View<'_, K, V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + ?Sized + 'k,
+    {
+        View {
+            store: self,
+            shards: self
+                .shard_indexes(keys)
+                .into_iter()
+                .map(|i| {
+                    (
+                        i,
+                        self.shards[i]
+                            .read()
+                            .unwrap_or_else(PoisonError::into_inner),
+                    )
+                })
+                .collect(),
+        }
+    }
+
+    /// Drops expired keys, one shard at a time.
+    fn clean_expired(&self) {
+        let now = current_unix_millis();
+        for (shard, used) in self.shards.iter().zip(&self.used) {
+            let mut shard = shard.write().unwrap_or_else(PoisonError::into_inner);
+            shard.clean_expired(now);
+            used.store(shard.used, Ordering::Relaxed);
+        }
+    }
+
+    /// Approximate bytes held by all keys and values.
+    pub fn used_memory(&self) -> usize {
+        self.used.iter().map(|u| u.load(Ordering::Relaxed)).sum()
+    }
+
+    /// Number of keys evicted to stay within `maxmemory` since startup.
+    pub fn evicted_keys(&self) -> u64 {
+        self.evicted.load(Ordering::Relaxed)
+    }
+
+    /// Evicts keys by `maxmemory-policy` until memory usage is within `maxmemory`,
+    /// trying the shards in turn. Returns false if it's still over the limit
+    /// because the policy allows no more evictions. Must not be called while
+    /// holding a `Txn` or `View`.
+    pub fn free_memory(&self) -> bool {
+        let limit = MAXMEMORY.get() as usize;
+        if limit == 0 || self.used_memory() <= limit {
+            return true;
+        }
+        let Some(policy) = Eviction::current() else {
+            return false;
+        };
+        // Shards tried in a row without finding anything to evict.
+        let mut exhausted = 0;
+        while self.used_memory() > limit {
+            let i = self.next_victim.fetch_add(1, Ordering::Relaxed) % self.shards.len();
+            let mut shard = self.shards[i]
+                .write()
+                .unwrap_or_else(PoisonError::into_inner);
+            if shard.evict(policy) {
+                self.used[i].store(shard.used, Ordering::Relaxed);
+                self.evicted.fetch_add(1, Ordering::Relaxed);
+                exhausted = 0;
+            } else {
+                exhausted += 1;
+                if exhausted == self.shards.len() {
+                    return false;
                 }
             }
-            return Some(value.val.clone());
         }
-        None
+        true
     }
 
-    pub fn set(&self, key: K, val: V, ttl_seconds: Option<u64>) {
-        let ttl = ttl_seconds.map(|t| t + current_unix_time());
-        let mut cache = self.cache.write().unwrap();
-        cache.put(key, Value { val, ttl });
+    pub fn keys(&self) -> Vec<K> {
+        self.shards
+            .iter()
+            .flat_map(|shard| {
+                shard
+                    .read()
+                    .unwrap_or_else(PoisonError::into_inner)
+                    .entries
+                    .keys()
+                    .cloned()
+                    .collect::<Vec<_>>()
+            })
+            .collect()
     }
+}
+
+/// Returns the position of shard `index` among the sorted locked shards.
+/// Panics if the shard wasn't locked, i.e. the key wasn't given when locking.
+fn position<G>(shards: &[(usize, G)], index: usize) -> usize {
+    shards
+        .binary_search_by_key(&index, |(i, _)| *i)
+        .expect("key is not covered by this transaction")
+}
 
-    pub fn delete(&self, key: &K) {
-        let mut cache = self.cache.write().unwrap();
-        cache.pop(key);
+impl<K, V> Txn<'_, K, V>
+where
+    K: Eq + Hash + Clone + MemSize,
+    V: MemSize,
+{
+    /// Returns the locked shard holding `key`.
+    fn shard<Q>(&mut self, key: &Q) -> &mut Shard<K, V>
+    where
+        Q: Hash + ?Sized,
+    {
+        let pos = position(&self.shards, self.store.shard_index(key));
+        &mut self.shards[pos].1
     }
 
-    pub fn keys(&self) -> Vec<K> {
-        let cache = self.cache.read().unwrap();
-        cache.iter().map(|(k, _)| k.clone()).collect()
+    /// Runs `f` on the value at `key`, mutating it in place.
+    /// The entry keeps its expiration.
+    pub fn with_mut<Q, R>(&mut self, key: &Q, f: impl FnOnce(&mut V) -> R) -> Option<R>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.shard(key).with_mut(key, f)
+    }
+
+    /// Runs `f` on the value at `key`, mutating it in place, after first
+    /// inserting the one made by `default` if the key doesn't exist. An existing
+    /// entry keeps its expiration; an inserted one has none.
+    pub fn upsert<Q, R>(
+        &mut self,
+        key: &Q,
+        default: impl FnOnce() -> V,
+        f: impl FnOnce(&mut V) -> R,
+    ) -> R
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ToOwned<Owned = K> + ?Sized,
+    {
+        let shard = self.shard(key);
+        let index = match shard.live_index(key) {
+            Some(index) => index,
+            None => shard.insert(key.to_owned(), Value::new(default(), None)),
+        };
+        shard.update(index, f)
+    }
+
+    /// Stores `val` at `key`, expiring `ttl_ms` milliseconds from now if given.
+    pub fn set(&mut self, key: K, val: V, ttl_ms: Option<u64>) {
+        let ttl = ttl_ms.map(|t| current_unix_millis().saturating_add(t));
+        self.shard(&key).insert(key, Value::new(val, ttl));
+    }
+
+    /// Makes `key` expire at the Unix time in milliseconds `at`, or never with
+    /// `None`. A time that has already passed deletes the key. Returns false
+    /// if the key doesn't exist.
+    pub fn expire_at<Q>(&mut self, key: &Q, at: Option<u64>) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.shard(key).set_expiry(key, at)
+    }
+
+    pub fn delete<Q>(&mut self, key: &Q)
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.shard(key).remove(key);
+    }
+}
+
+impl<K, V> Drop for Txn<'_, K, V>
+where
+    K: Eq + Hash + Clone,
+{
+    /// Publishes the memory usage of the shards the transaction may have changed.
+    fn drop(&mut self) {
+        for (i, shard) in &self.shards {
+            self.store.used[*i].store(shard.used, Ordering::Relaxed);
+        }
+    }
+}
+
+impl<K, V> Lookup<K, V> for Txn<'_, K, V>
+where
+    K: Eq + Hash + Clone + MemSize,
+    V: MemSize,
+{
+    fn with<Q, R>(&self, key: &Q, f: impl FnOnce(&V) -> R) -> Option<R>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let pos = position(&self.shards, self.store.shard_index(key));
+        self.shards[pos].1.live(key).map(|value| f(&value.val))
+    }
+
+    fn expire_time<Q>(&self, key: &Q) -> Option<Option<u64>>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let pos = position(&self.shards, self.store.shard_index(key));
+        self.shards[pos].1.peek(key).map(|value| value.ttl)
     }
 }
 
-fn current_unix_time() -> u64 {
+impl<K, V> View<'_, K, V>
+where
+    K: Eq + Hash + Clone + MemSize,
+    V: MemSize,
+{
+    /// Returns the decayed access frequency counter of `key`, without counting
+    /// the lookup as an access.
+    pub fn frequency<Q>(&self, key: &Q) -> Option<u64>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let pos = position(&self.shards, self.store.shard_index(key));
+        let value = self.shards[pos].1.peek(key)?;
+        Some(value.frequency(current_unix_minutes()))
+    }
+}
+
+impl<K, V> Lookup<K, V> for View<'_, K, V>
+where
+    K: Eq + Hash + Clone + MemSize,
+    V: MemSize,
+{
+    fn with<Q, R>(&self, key: &Q, f: impl FnOnce(&V) -> R) -> Option<R>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let pos = position(&self.shards, self.store.shard_index(key));
+        self.shards[pos].1.live(key).map(|value| f(&value.val))
+    }
+
+    fn expire_time<Q>(&self, key: &Q) -> Option<Option<u64>>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let pos = position(&self.shards, self.store.shard_index(key));
+        self.shards[pos].1.peek(key).map(|value| value.ttl)
+    }
+}
+
+pub fn current_unix_millis() -> u64 {
     SystemTime::now()
         .duration_since(UNIX_EPOCH)
         .unwrap()
-        .as_secs()
+        .as_millis() as u64
+}
+
+fn current_unix_minutes() -> u64 {
+    current_unix_millis() / 60_000
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use std::sync::{Mutex, MutexGuard};
+
+    type TestStore = Store<Vec<u8>, Vec<u8>>;
+
+    /// Serializes the tests that change the eviction config, which is global.
+    static CONFIG: Mutex<()> = Mutex::new(());
+
+    /// Holds `CONFIG` and puts the eviction settings back to their defaults when dropped.
+    struct ConfigGuard {
+        _lock: MutexGuard<'static, ()>,
+    }
+
+    impl Drop for ConfigGuard {
+        fn drop(&mut self) {
+            configure(&[
+                ("maxmemory", "0"),
+                ("maxmemory-policy", "noeviction"),
+                ("maxmemory-samples", "5"),
+                ("lfu-log-factor", "10"),
+                ("lfu-decay-time", "1"),
+            ]);
+        }
+    }
+
+    fn lock_config() -> ConfigGuard {
+        ConfigGuard {
+            _lock: CONFIG.lock().unwrap_or_else(PoisonError::into_inner),
+        }
+    }
+
+    fn configure(pairs: &[(&str, &str)]) {
+        let pairs: Vec<(String, String)> = pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        Config::set(&pairs).unwrap();
+    }
+
+    /// Fills `store` with `n` keys holding 100 bytes each. Even keys `k{i}`
+    /// expire in `n - i` minutes, odd ones never.
+    fn fill(store: &TestStore, n: usize) -> Vec<Vec<u8>> {
+        let keys: Vec<Vec<u8>> = (0..n).map(|i| format!("k{i}").into_bytes()).collect();
+        let mut txn = store.lock(keys.iter().map(Vec::as_slice));
+        for (i, key) in keys.iter().enumerate() {
+            let ttl = (i % 2 == 0).then_some((n - i) as u64 * 60_000);
+            txn.set(key.clone(), vec![0; 100], ttl);
+        }
+        keys
+    }
+
+    fn live_keys(store: &TestStore, keys: &[Vec<u8>]) -> Vec<Vec<u8>> {
+        let view = store.read(keys.iter().map(Vec::as_slice));
+        keys.iter()
+            .filter(|k| view.with(k.as_slice(), |_| ()).is_some())
+            .cloned()
+            .collect()
+    }
+
+    fn counter(txn: &mut Txn<'_, Vec<u8>, Vec<u8>>, key: &[u8]) {
+        txn.upsert(
+            key,
+            || vec![0; 8],
+            |val| {
+                let n = u64::from_le_bytes(val[..8].try_into().unwrap());
+                val.copy_from_slice(&(n + 1).to_le_bytes());
+            },
+        );
+    }
+
+    #[test]
+    fn locks_keys_across_shards_without_losing_updates() {
+        let store = Arc::new(TestStore::new(4));
+        let keys: Vec<Vec<u8>> = (0..6).map(|i| format!("k{i}").into_bytes()).collect();
+        let threads: Vec<_> = (0..8)
+            .map(|t| {
+                let (store, keys) = (store.clone(), keys.clone());
+                thread::spawn(move || {
+                    for i in 0..500 {
+                        // Pairs given in either order, so shards are locked in both.
+                        let (a, b) = (&keys[(t + i) % 6], &keys[(t * 5 + i) % 6]);
+                        let mut txn = store.lock([a.as_slice(), b.as_slice()]);
+                        counter(&mut txn, a);
+                        counter(&mut txn, b);
+                    }
+                })
+            })
+            .collect();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+        let view = store.read(keys.iter().map(Vec::as_slice));
+        let total: u64 = keys
+            .iter()
+            .map(|k| {
+                view.with(k.as_slice(), |v| {
+                    u64::from_le_bytes(v[..8].try_into().unwrap())
+                })
+            })
+            .map(Option::unwrap_or_default)
+            .sum();
+        assert_eq!(total, 8 * 500 * 2);
+        drop(view);
+        let mut stored = store.keys();
+        stored.sort();
+        assert_eq!(stored, keys);
+    }
+
+    #[test]
+    fn mutates_in_place_and_keeps_memory_accounting_exact() {
+        let store = TestStore::new(4);
+        let (a, b) = (b"a".as_slice(), b"b".as_slice());
+        let mut txn = store.lock([a, b]);
+        assert_eq!(txn.with_mut(a, |v| v.len()), None);
+        assert_eq!(txn.upsert(a, Vec::new, |v| v.len()), 0);
+        txn.set(b.to_vec(), b"x".to_vec(), Some(60_000));
+        txn.upsert(b, Vec::new, |v| v.extend_from_slice(&[1; 1000]));
+        assert_eq!(txn.with(b, Vec::len), Some(1001));
+        assert!(txn.expire_time(b).unwrap().is_some());
+        drop(txn);
+        assert!(store.used_memory() > 1000);
+
+        let mut txn = store.lock([b]);
+        txn.with_mut(b, |v| {
+            v.clear();
+            v.shrink_to_fit();
+        });
+        drop(txn);
+        assert!(store.used_memory() < 1000);
+
+        let mut txn = store.lock([a, b]);
+        txn.delete(a);
+        txn.delete(b);
+        drop(txn);
+        assert_eq!(store.used_memory(), 0);
+    }
+
+    #[test]
+    fn evicts_only_what_the_policy_allows() {
+        let _config = lock_config();
+        let store = TestStore::new(2);
+        let keys = fill(&store, 10);
+        let volatile: Vec<Vec<u8>> = keys.iter().step_by(2).cloned().collect();
+        let persistent: Vec<Vec<u8>> = keys.iter().skip(1).step_by(2).cloned().collect();
+
+        configure(&[("maxmemory", "1")]);
+        assert!(!store.free_memory());
+        assert_eq!(live_keys(&store, &keys), keys);
+
+        configure(&[("maxmemory-policy", "volatile-random")]);
+        assert!(!store.free_memory());
+        assert_eq!(live_keys(&store, &keys), persistent);
+        assert_eq!(store.evicted_keys(), 5);
+
+        configure(&[("maxmemory-policy", "allkeys-random")]);
+        assert!(store.free_memory());
+        assert!(live_keys(&store, &keys).is_empty());
+        assert_eq!((store.used_memory(), store.evicted_keys()), (0, 10));
+
+        // Sampling every key of a single shard makes the policy exact.
+        let store = TestStore::new(1);
+        fill(&store, 10);
+        let limit = store.used_memory() - 1;
+        configure(&[
+            ("maxmemory", &limit.to_string()),
+            ("maxmemory-policy", "volatile-ttl"),
+            ("maxmemory-samples", "64"),
+        ]);
+        assert!(store.free_memory());
+        assert_eq!(store.evicted_keys(), 1);
+        let mut expected = keys.clone();
+        expected.retain(|k| k != &volatile[4]);
+        assert_eq!(live_keys(&store, &keys), expected);
+    }
+
+    #[test]
+    fn evicts_the_least_recently_read_key() {
+        let _config = lock_config();
+        let store = TestStore::new(1);
+        let keys = fill(&store, 3);
+        // Reads under a shared lock still count as accesses.
+        let view = store.read([keys[0].as_slice(), keys[2].as_slice()]);
+        view.with(keys[2].as_slice(), |_| ());
+        view.with(keys[0].as_slice(), |_| ());
+        drop(view);
+        let limit = store.used_memory() - 1;
+        configure(&[
+            ("maxmemory", &limit.to_string()),
+            ("maxmemory-policy", "allkeys-lru"),
+            ("maxmemory-samples", "64"),
+        ]);
+        assert!(store.free_memory());
+        assert_eq!(live_keys(&store, &keys), [keys[0].clone(), keys[2].clone()]);
+    }
+
+    #[test]
+    fn evicts_the_least_frequently_read_key() {
+        let _config = lock_config();
+        // Without the log factor or decay, every access adds exactly one.
+        configure(&[
+            ("maxmemory-policy", "allkeys-lfu"),
+            ("maxmemory-samples", "64"),
+            ("lfu-log-factor", "0"),
+            ("lfu-decay-time", "0"),
+        ]);
+        let store = TestStore::new(1);
+        let keys = fill(&store, 3);
+        let view = store.read(keys.iter().map(Vec::as_slice));
+        for (key, reads) in keys.iter().zip([2, 3, 0]) {
+            for _ in 0..reads {
+                view.with(key.as_slice(), |_| ());
+            }
+        }
+        let frequencies: Vec<_> = keys.iter().map(|k| view.frequency(k.as_slice())).collect();
+        assert_eq!(frequencies, [Some(8), Some(9), Some(6)]);
+        assert_eq!(view.frequency(b"missing".as_slice()), None);
+        drop(view);
+        let limit = store.used_memory() - 1;
+        configure(&[("maxmemory", &limit.to_string())]);
+        assert!(store.free_memory());
+        assert_eq!(live_keys(&store, &keys), keys[..2]);
+    }
+
+    #[test]
+    fn expires_keys_at_millisecond_precision() {
+        let store = TestStore::new(4);
+        let (a, b, c) = (b"a".as_slice(), b"b".as_slice(), b"c".as_slice());
+        let mut txn = store.lock([a, b, c]);
+        txn.set(a.to_vec(), b"1".to_vec(), Some(0));
+        assert_eq!(txn.with(a, Vec::clone), None);
+        assert_eq!(txn.expire_time(a), None);
+        // An expired key is replaced by a fresh one, without the old TTL.
+        txn.upsert(a, Vec::new, |v| v.push(2));
+        assert_eq!(txn.get(a), Some(vec![2]));
+        assert_eq!(txn.expire_time(a), Some(None));
+
+        let before = current_unix_millis();
+        txn.set(b.to_vec(), b"1".to_vec(), Some(1500));
+        let at = txn.expire_time(b).flatten().unwrap();
+        assert!((before + 1500..=current_unix_millis() + 1500).contains(&at));
+        assert!(txn.expire_at(b, None));
+        assert_eq!(txn.expire_time(b), Some(None));
+        assert!(txn.expire_at(b, Some(before - 1)));
+        assert_eq!(txn.expire_time(b), None);
+        assert!(!txn.expire_at(b, None));
+
+        txn.set(c.to_vec(), vec![0; 100], Some(1));
+        drop(txn);
+        thread::sleep(Duration::from_millis(5));
+        assert_eq!(store.keys().len(), 2);
+        store.clean_expired();
+        assert_eq!(store.keys(), [a.to_vec()]);
+        let mut txn = store.lock([a]);
+        txn.delete(a);
+        drop(txn);
+        assert_eq!(store.used_memory(), 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "not covered")]
+    fn refuses_keys_outside_the_transaction() {
+        let store = TestStore::new(64);
+        let key = (0..)
+            .map(|i: u32| i.to_be_bytes().to_vec())
+            .find(|k| store.shard_index(k.as_slice()) != store.shard_index(b"a".as_slice()))
+            .unwrap();
+        let mut txn = store.lock([b"a".as_slice()]);
+        txn.set(key, vec![], None);
+    }
 }
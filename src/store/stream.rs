@@ -0,0 +1,634 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::mem::size_of;
+
+use crate::store::memory::{MemSize, element_size, estimate};
+
+/// A stream entry ID: milliseconds plus a sequence number within that millisecond.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct StreamId {
+    pub ms: u64,
+    pub seq: u64,
+}
+
+impl StreamId {
+    pub const MIN: StreamId = StreamId { ms: 0, seq: 0 };
+    pub const MAX: StreamId = StreamId {
+        ms: u64::MAX,
+        seq: u64::MAX,
+    };
+
+    /// Parses `ms-seq`, or a bare `ms` with `default_seq` as the sequence.
+    pub fn parse(s: &str, default_seq: u64) -> Option<StreamId> {
+        let (ms, seq) = match s.split_once('-') {
+            Some((ms, seq)) => (ms, seq.parse().ok()?),
+            None => (s, default_seq),
+        };
+        Some(StreamId {
+            ms: ms.parse().ok()?,
+            seq,
+        })
+    }
+
+    /// Returns the smallest ID greater than this one.
+    pub fn next(self) -> Option<StreamId> {
+        match self.seq.checked_add(1) {
+            Some(seq) => Some(StreamId { ms: self.ms, seq }),
+            None => Some(StreamId {
+                ms: self.ms.checked_add(1)?,
+                seq: 0,
+            }),
+        }
+    }
+
+    /// Returns the largest ID smaller than this one.
+    pub fn prev(self) -> Option<StreamId> {
+        match self.seq.checked_sub(1) {
+            Some(seq) => Some(StreamId { ms: self.ms, seq }),
+            None => Some(StreamId {
+                ms: self.ms.checked_sub(1)?,
+                seq: u64::MAX,
+            }),
+        }
+    }
+}
+
+impl fmt::Display for StreamId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}-{}", self.ms, self.seq)
+    }
+}
+
+/// The ID requested for a new entry, as given to XADD.
+#[derive(Clone, Copy)]
+pub enum NewId {
+    /// `*`: generated from the current time.
+    Auto,
+    /// `ms-*`: the given milliseconds with a generated sequence number.
+    AutoSeq(u64),
+    Explicit(StreamId),
+}
+
+/// How entries are evicted from the head of a stream.
+#[derive(Clone, Copy)]
+pub enum Trim {
+    /// Keep at most this many entries.
+    MaxLen(usize),
+    /// Evict entries with an ID lower than this one.
+    MinId(StreamId),
+}
+
+pub type Fields = Vec<(Vec<u8>, Vec<u8>)>;
+
+/// An entry delivered to a consumer that has not been acknowledged yet.
+#[derive(Clone)]
+pub struct PendingEntry {
+    pub consumer: String,
+    /// Unix time in milliseconds of the last delivery.
+    pub delivered_at: u64,
+    pub delivery_count: u64,
+}
+
+#[derive(Clone, Default)]
+pub struct Consumer {
+    /// Unix time in milliseconds of the last interaction with the consumer.
+    pub seen_time: u64,
+    /// Unix time in milliseconds of the last successful read or claim, if any.
+    pub active_time: Option<u64>,
+    /// IDs in the group's pending entry list owned by this consumer.
+    pub pending: BTreeSet<StreamId>,
+}
+
+/// A consumer group: a read cursor plus the entries delivered but not yet acknowledged.
+#[derive(Clone, Default)]
+pub struct ConsumerGroup {
+    pub last_delivered: StreamId,
+    pub pending: BTreeMap<StreamId, PendingEntry>,
+    pub consumers: BTreeMap<String, Consumer>,
+}
+
+impl ConsumerGroup {
+    pub fn new(last_delivered: StreamId) -> Self {
+        Self {
+            last_delivered,
+            ..Default::default()
+        }
+    }
+
+    /// Returns the consumer called `name`, creating it if needed, and marks it as seen.
+    pub fn touch_consumer(&mut self, name: &str, now: u64) -> &mut Consumer {
+        let consumer = self.consumers.entry(name.to_string()).or_default();
+        consumer.seen_time = now;
+        consumer
+    }
+
+    /// Removes a consumer together with its pending entries.
+    /// Returns the number of pending entries it had, or `None` if it did not exist.
+    pub fn remove_consumer(&mut self, name: &str) -> Option<usize> {
+        let consumer = self.consumers.remove(name)?;
+        for id in &consumer.pending {
+            self.pending.remove(id);
+        }
+        Some(consumer.pending.len())
+    }
+
+    /// Records `id` as delivered to `consumer` at `now`, taking it over from any
+    /// previous owner. The delivery count is set to `count`, or incremented if `None`.
+    pub fn deliver(&mut self, id: StreamId, consumer: &str, now: u64, count: Option<u64>) {
+        let previous = self
+            .pending
+            .get(&id)
+            .map(|p| (p.consumer.clone(), p.delivery_count));
+        if let Some((owner, _)) = &previous
+            && let Some(c) = self.consumers.get_mut(owner)
+        {
+            c.pending.remove(&id);
+        }
+        let c = self.touch_consumer(consumer, now);
+        c.active_time = Some(now);
+        c.pending.insert(id);
+        let delivery_count =
+            count.unwrap_or_else(|| previous.map_or(1, |(_, n)| n.saturating_add(1)));
+        self.pending.insert(
+            id,
+            PendingEntry {
+                consumer: consumer.to_string(),
+                delivered_at: now,
+                delivery_count,
+            },
+        );
+    }
+
+    /// Removes `id` from the pending entry list. Returns false if it was not pending.
+    pub fn ack(&mut self, id: StreamId) -> bool {
+        let Some(entry) = self.pending.remove(&id) else {
+            return false;
+        };
+        if let Some(c) = self.consumers.get_mut(&entry.consumer) {
+            c.pending.remove(&id);
+        }
+        true
+    }
+}
+
+/// Options of XCLAIM that control how claimed entries are recorded.
+#[derive(Clone, Copy, Default)]
+pub struct ClaimOptions {
+    /// Only claim entries idle for at least this many milliseconds.
+    pub min_idle: u64,
+    /// Record the entries as idle for this many milliseconds instead of zero.
+    pub idle: Option<u64>,
+    /// Unix time in milliseconds to record as the delivery time instead of now.
+    pub time: Option<u64>,
+    pub retry_count: Option<u64>,
+    /// Create pending entries for IDs that exist in the stream but are not pending.
+    pub force: bool,
+    /// Leave the delivery count unchanged.
+    pub just_id: bool,
+    /// Move the group's cursor forward to this ID.
+    pub last_id: Option<StreamId>,
+}
+
+/// An append-only log of field-value entries ordered by ID.
+#[derive(Clone, Default)]
+pub struct Stream {
+    entries: BTreeMap<StreamId, Fields>,
+    last_id: StreamId,
+    max_deleted_id: StreamId,
+    entries_added: u64,
+    groups: BTreeMap<String, ConsumerGroup>,
+}
+
+impl Stream {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// The ID of the last entry ever added, even if it was deleted since.
+    pub fn last_id(&self) -> StreamId {
+        self.last_id
+    }
+
+    /// The highest ID ever removed with XDEL.
+    pub fn max_deleted_id(&self) -> StreamId {
+        self.max_deleted_id
+    }
+
+    /// The number of entries ever added, including deleted and trimmed ones.
+    pub fn entries_added(&self) -> u64 {
+        self.entries_added
+    }
+
+    pub fn get(&self, id: StreamId) -> Option<&Fields> {
+        self.entries.get(&id)
+    }
+
+    pub fn first_entry(&self) -> Option<(&StreamId, &Fields)> {
+        self.entries.first_key_value()
+    }
+
+    pub fn last_entry(&self) -> Option<(&StreamId, &Fields)> {
+        self.entries.last_key_value()
+    }
+
+    /// Resolves `id` against the top of the stream.
+    /// Returns `None` if the resulting ID would not be greater than the last one.
+    pub fn next_id(&self, id: NewId, now_ms: u64) -> Option<StreamId> {
+        let last = self.last_id;
+        let id = match id {
+            NewId::Auto if now_ms > last.ms => StreamId { ms: now_ms, seq: 0 },
+            NewId::Auto => last.next()?,
+            NewId::AutoSeq(ms) if ms > last.ms => StreamId { ms, seq: 0 },
+            NewId::AutoSeq(ms) if ms == last.ms => last.next().filter(|id| id.ms == ms)?,
+            NewId::AutoSeq(_) => return None,
+            NewId::Explicit(id) => id,
+        };
+        // 0-0 is never a valid entry ID, so an empty stream accepts anything above it.
+        (id > last).then_some(id)
+    }
+
+    /// Appends an entry. `id` must come from `next_id`.
+    pub fn insert(&mut self, id: StreamId, fields: Fields) {
+        self.entries.insert(id, fields);
+        self.last_id = id;
+        self.entries_added += 1;
+    }
+
+    pub fn remove(&mut self, id: StreamId) -> bool {
+        let removed = self.entries.remove(&id).is_some();
+        if removed && id > self.max_deleted_id {
+            self.max_deleted_id = id;
+        }
+        removed
+    }
+
+    /// Iterates the entries with IDs between `start` and `end` inclusive,
+    /// ascending or, with `rev`, descending.
+    pub fn range(
+        &self,
+        start: StreamId,
+        end: StreamId,
+        rev: bool,
+    ) -> Box<dyn Iterator<Item = (&StreamId, &Fields)> + '_> {
+        if start > end {
+            return Box::new(std::iter::empty());
+        }
+        let range = self.entries.range(start..=end);
+        if rev {
+            Box::new(range.rev())
+        } else {
+            Box::new(range)
+        }
+    }
+
+    /// Evicts entries from the head of the stream according to `trim`,
+    /// removing at most `limit` entries (0 means no limit).
+    /// Returns the number of entries removed.
+    pub fn trim(&mut self, trim: Trim, limit: usize) -> usize {
+        let limit = if limit == 0 { usize::MAX } else { limit };
+        let mut removed = 0;
+        while removed < limit {
+            let Some((&first, _)) = self.entries.first_key_value() else {
+                break;
+            };
+            let evict = match trim {
+                Trim::MaxLen(max) => self.entries.len() > max,
+                Trim::MinId(min) => first < min,
+            };
+            if !evict {
+                break;
+            }
+            self.entries.pop_first();
+            removed += 1;
+        }
+        removed
+    }
+
+    pub fn groups(&self) -> impl Iterator<Item = (&String, &ConsumerGroup)> {
+        self.groups.iter()
+    }
+
+    pub fn group(&self, name: &str) -> Option<&ConsumerGroup> {
+        self.groups.get(name)
+    }
+
+    pub fn group_mut(&mut self, name: &str) -> Option<&mut ConsumerGroup> {
+        self.groups.get_mut(name)
+    }
+
+    /// Creates a group whose cursor starts at `last_delivered`.
+    /// Returns false if a group with that name already exists.
+    pub fn create_group(&mut self, name: &str, last_delivered: StreamId) -> bool {
+        if self.groups.contains_key(name) {
+            return false;
+        }
+        self.groups
+            .insert(name.to_string(), ConsumerGroup::new(last_delivered));
+        true
+    }
+
+    pub fn destroy_group(&mut self, name: &str) -> bool {
+        self.groups.remove(name).is_some()
+    }
+
+    /// Delivers up to `count` entries past the group's cursor to `consumer` and
+    /// advances the cursor. Unless `no_ack` is set, the entries become pending.
+    /// Returns `None` if the group does not exist.
+    pub fn read_group(
+        &mut self,
+        group: &str,
+        consumer: &str,
+        count: usize,
+        no_ack: bool,
+        now: u64,
+    ) -> Option<Vec<(StreamId, Fields)>> {
+        let g = self.groups.get_mut(group)?;
+        g.touch_consumer(consumer, now);
+        let entries: Vec<(StreamId, Fields)> = match g.last_delivered.next() {
+            Some(start) => self
+                .entries
+                .range(start..)
+                .take(count)
+                .map(|(id, fields)| (*id, fields.clone()))
+                .collect(),
+            None => vec![],
+        };
+        for (id, _) in &entries {
+            g.last_delivered = *id;
+            if no_ack {
+                g.touch_consumer(consumer, now).active_time = Some(now);
+            } else {
+                g.deliver(*id, consumer, now, Some(1));
+            }
+        }
+        Some(entries)
+    }
+
+    /// Returns up to `count` of the entries pending for `consumer` with IDs greater
+    /// than `after`. Entries deleted from the stream since have no fields.
+    /// Returns `None` if the group does not exist.
+    pub fn read_group_history(
+        &mut self,
+        group: &str,
+        consumer: &str,
+        after: StreamId,
+        count: usize,
+        now: u64,
+    ) -> Option<Vec<(StreamId, Option<Fields>)>> {
+        let g = self.groups.get_mut(group)?;
+        let c = g.touch_consumer(consumer, now);
+        let Some(start) = after.next() else {
+            return Some(vec![]);
+        };
+        Some(
+            c.pending
+                .range(start..)
+                .take(count)
+                .map(|id| (*id, self.entries.get(id).cloned()))
+                .collect(),
+        )
+    }
+
+    /// Transfers ownership of the pending entries `ids` to `consumer`.
+    /// IDs no longer in the stream are dropped from the pending entry list.
+    /// Returns the claimed entries, or `None` if the group does not exist.
+    pub fn claim(
+        &mut self,
+        group: &str,
+        consumer: &str,
+        ids: &[StreamId],
+        opts: &ClaimOptions,
+        now: u64,
+    ) -> Option<Vec<(StreamId, Fields)>> {
+        let g = self.groups.get_mut(group)?;
+        g.touch_consumer(consumer, now);
+        if let Some(last) = opts.last_id
+            && last > g.last_delivered
+        {
+            g.last_delivered = last;
+        }
+        let delivered_at = opts
+            .time
+            .or_else(|| opts.idle.map(|idle| now.saturating_sub(idle)))
+            .unwrap_or(now);
+        let mut claimed = Vec::new();
+        for &id in ids {
+            let Some(fields) = self.entries.get(&id) else {
+                g.ack(id);
+                continue;
+            };
+            match g.pending.get(&id) {
+                Some(p) if now.saturating_sub(p.delivered_at) < opts.min_idle => continue,
+                Some(_) => {}
+                None if opts.force => {}
+                None => continue,
+            }
+            let count = opts.retry_count.or_else(|| {
+                opts.just_id
+                    .then(|| g.pending.get(&id).map_or(0, |p| p.delivery_count))
+            });
+            g.deliver(id, consumer, delivered_at, count);
+            claimed.push((id, fields.clone()));
+        }
+        Some(claimed)
+    }
+
+    /// Scans the pending entry list from `start` and claims up to `count` entries
+    /// idle for at least `opts.min_idle` milliseconds. Only `min_idle` and `just_id`
+    /// of `opts` apply. Returns the cursor to continue
+    /// from (0-0 when the scan is complete), the claimed entries and the IDs that
+    /// were dropped because they are no longer in the stream.
+    /// Returns `None` if the group does not exist.
+    pub fn auto_claim(
+        &mut self,
+        group: &str,
+        consumer: &str,
+        start: StreamId,
+        count: usize,
+        opts: &ClaimOptions,
+        now: u64,
+    ) -> Option<AutoClaim> {
+        let g = self.groups.get_mut(group)?;
+        g.touch_consumer(consumer, now);
+        // Like Redis, scan at most ten pending entries per requested claim.
+        let mut attempts = count.saturating_mul(10);
+        let mut claimed = Vec::new();
+        let mut deleted = Vec::new();
+        let mut from = Some(start);
+        while let Some(at) = from {
+            if attempts == 0 || claimed.len() >= count {
+                break;
+            }
+            let Some((&id, pel)) = g.pending.range(at..).next() else {
+                from = None;
+                break;
+            };
+            let (delivered_at, delivery_count) = (pel.delivered_at, pel.delivery_count);
+            attempts -= 1;
+            from = id.next();
+            let Some(fields) = self.entries.get(&id) else {
+                g.ack(id);
+                deleted.push(id);
+                continue;
+            };
+            if now.saturating_sub(delivered_at) < opts.min_idle {
+                continue;
+            }
+            g.deliver(id, consumer, now, opts.just_id.then_some(delivery_count));
+            claimed.push((id, fields.clone()));
+        }
+        let cursor = from
+            .and_then(|at| g.pending.range(at..).next())
+            .map_or(StreamId::MIN, |(id, _)| *id);
+        Some(AutoClaim {
+            cursor,
+            claimed,
+            deleted,
+        })
+    }
+}
+
+impl MemSize for Stream {
+    /// Entries are sampled; consumer groups are charged per pending entry and consumer.
+    fn heap_size(&self) -> usize {
+        let entries = estimate(self.entries.values(), self.len(), |fields| {
+            size_of::<(StreamId, Fields)>()
+                + fields
+                    .iter()
+                    .map(|(f, v)| element_size(f) + element_size(v))
+                    .sum::<usize>()
+        });
+        let groups: usize = self
+            .groups
+            .iter()
+            .map(|(name, group)| {
+                size_of::<(String, ConsumerGroup)>()
+                    + name.heap_size()
+                    + group.pending.len()
+                        * (size_of::<(StreamId, PendingEntry)>() + size_of::<StreamId>())
+                    + group
+                        .consumers
+                        .keys()
+                        .map(|c| size_of::<(String, Consumer)>() + c.heap_size())
+                        .sum::<usize>()
+            })
+            .sum();
+        entries + groups
+    }
+}
+
+/// The outcome of an XAUTOCLAIM scan.
+pub struct AutoClaim {
+    pub cursor: StreamId,
+    pub claimed: Vec<(StreamId, Fields)>,
+    pub deleted: Vec<StreamId>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn id(ms: u64) -> StreamId {
+        StreamId { ms, seq: 0 }
+    }
+
+    /// A stream with entries 1..=n, all delivered to consumer "a" of group "g" at time 0.
+    fn delivered(n: u64) -> Stream {
+        let mut stream = Stream::new();
+        for ms in 1..=n {
+            stream.insert(id(ms), vec![(b"f".to_vec(), ms.to_string().into_bytes())]);
+        }
+        stream.create_group("g", StreamId::MIN);
+        let read = stream.read_group("g", "a", n as usize, false, 0).unwrap();
+        assert_eq!(read.len(), n as usize);
+        stream
+    }
+
+    fn owners(stream: &Stream) -> Vec<(StreamId, String, u64)> {
+        let g = stream.group("g").unwrap();
+        for (name, c) in &g.consumers {
+            for pending in &c.pending {
+                assert_eq!(&g.pending[pending].consumer, name);
+            }
+        }
+        let owned: usize = g.consumers.values().map(|c| c.pending.len()).sum();
+        assert_eq!(owned, g.pending.len());
+        g.pending
+            .iter()
+            .map(|(id, p)| (*id, p.consumer.clone(), p.delivery_count))
+            .collect()
+    }
+
+    #[test]
+    fn tracks_pending_entries_per_consumer() {
+        let mut stream = delivered(3);
+        assert_eq!(stream.group("g").unwrap().last_delivered, id(3));
+        let g = stream.group_mut("g").unwrap();
+        assert!(g.ack(id(2)));
+        assert!(!g.ack(id(2)));
+        g.deliver(id(3), "b", 5, None);
+        assert_eq!(
+            owners(&stream),
+            [(id(1), "a".into(), 1), (id(3), "b".into(), 2)]
+        );
+        let g = stream.group_mut("g").unwrap();
+        assert_eq!(g.remove_consumer("a"), Some(1));
+        assert_eq!(g.remove_consumer("a"), None);
+        assert_eq!(owners(&stream), [(id(3), "b".into(), 2)]);
+    }
+
+    #[test]
+    fn claims_idle_entries_and_drops_deleted_ones() {
+        let mut stream = delivered(3);
+        stream.remove(id(2));
+        let opts = ClaimOptions {
+            min_idle: 10,
+            ..Default::default()
+        };
+        let ids = [id(1), id(2), id(3)];
+        assert!(stream.claim("g", "b", &ids, &opts, 5).unwrap().is_empty());
+        let claimed = stream.claim("g", "b", &ids, &opts, 10).unwrap();
+        assert_eq!(
+            claimed.iter().map(|(id, _)| *id).collect::<Vec<_>>(),
+            [id(1), id(3)]
+        );
+        assert_eq!(
+            owners(&stream),
+            [(id(1), "b".into(), 2), (id(3), "b".into(), 2)]
+        );
+        assert!(stream.claim("missing", "b", &ids, &opts, 10).is_none());
+    }
+
+    #[test]
+    fn auto_claim_stops_after_count_or_ten_scans_per_claim() {
+        let mut stream = delivered(30);
+        stream.remove(id(2));
+        let opts = ClaimOptions::default();
+        let scan = stream.auto_claim("g", "b", id(1), 2, &opts, 1).unwrap();
+        assert_eq!(
+            scan.claimed.iter().map(|(id, _)| *id).collect::<Vec<_>>(),
+            [id(1), id(3)]
+        );
+        assert_eq!(scan.deleted, [id(2)]);
+        assert_eq!(scan.cursor, id(4));
+
+        // Nothing is idle long enough, so the scan stops after 10 * count entries.
+        let opts = ClaimOptions {
+            min_idle: 100,
+            ..Default::default()
+        };
+        let scan = stream.auto_claim("g", "b", id(4), 1, &opts, 1).unwrap();
+        assert!(scan.claimed.is_empty());
+        assert_eq!(scan.cursor, id(14));
+        let scan = stream.auto_claim("g", "b", id(25), 1, &opts, 1).unwrap();
+        assert_eq!(scan.cursor, StreamId::MIN);
+    }
+}
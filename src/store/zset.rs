@@ -0,0 +1,263 @@
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::mem::size_of;
+
+use crate::store::memory::{MemSize, element_size, estimate};
+use crate::store::skiplist::SkipList;
+
+/// A score with a total order, so it can be used as part of an index key.
+#[derive(Clone, Copy, Debug)]
+pub struct Score(pub f64);
+
+impl PartialEq for Score {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Score {}
+
+impl PartialOrd for Score {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Score {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.total_cmp(&other.0)
+    }
+}
+
+/// One end of a score range, as given to ZRANGE BYSCORE or ZCOUNT.
+#[derive(Clone, Copy)]
+pub struct ScoreBound {
+    pub value: f64,
+    pub exclusive: bool,
+}
+
+impl ScoreBound {
+    pub fn admits_min(&self, score: f64) -> bool {
+        if self.exclusive {
+            score > self.value
+        } else {
+            score >= self.value
+        }
+    }
+
+    pub fn admits_max(&self, score: f64) -> bool {
+        if self.exclusive {
+            score < self.value
+        } else {
+            score <= self.value
+        }
+    }
+}
+
+/// One end of a lexicographical range, as given to ZRANGE BYLEX.
+#[derive(Clone)]
+pub enum LexBound {
+    NegInf,
+    PosInf,
+    Inclusive(Vec<u8>),
+    Exclusive(Vec<u8>),
+}
+
+impl LexBound {
+    pub fn admits_min(&self, member: &[u8]) -> bool {
+        match self {
+            LexBound::NegInf => true,
+            LexBound::PosInf => false,
+            LexBound::Inclusive(m) => member >= m.as_slice(),
+            LexBound::Exclusive(m) => member > m.as_slice(),
+        }
+    }
+
+    pub fn admits_max(&self, member: &[u8]) -> bool {
+        match self {
+            LexBound::NegInf => false,
+            LexBound::PosInf => true,
+            LexBound::Inclusive(m) => member <= m.as_slice(),
+            LexBound::Exclusive(m) => member < m.as_slice(),
+        }
+    }
+}
+
+pub type ZIter<'a> = Box<dyn Iterator<Item = (&'a Vec<u8>, f64)> + 'a>;
+
+/// A sorted set: a member to score map plus an index ordered by (score, member).
+/// The index is a skiplist so ranks are found without walking the set.
+#[derive(Clone, Default)]
+pub struct ZSet {
+    scores: HashMap<Vec<u8>, f64>,
+    index: SkipList<(Score, Vec<u8>)>,
+}
+
+impl ZSet {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.scores.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.scores.is_empty()
+    }
+
+    pub fn score(&self, member: &[u8]) -> Option<f64> {
+        self.scores.get(member).copied()
+    }
+
+    /// Sets the score of `member` and returns its previous score, if any.
+    pub fn insert(&mut self, member: Vec<u8>, score: f64) -> Option<f64> {
+        // -0.0 and 0.0 must sort as the same score.
+        let score = if score == 0.0 { 0.0 } else { score };
+        let old = self.scores.insert(member.clone(), score);
+        if let Some(old) = old {
+            self.index.remove(&(Score(old), member.clone()));
+        }
+        self.index.insert((Score(score), member));
+        old
+    }
+
+    /// Removes `member` and returns its score, if it was present.
+    pub fn remove(&mut self, member: &[u8]) -> Option<f64> {
+        let score = self.scores.remove(member)?;
+        self.index.remove(&(Score(score), member.to_vec()));
+        Some(score)
+    }
+
+    /// Returns the 0-based position of `member` in ascending score order.
+    pub fn rank(&self, member: &[u8]) -> Option<usize> {
+        let score = self.score(member)?;
+        Some(self.index.rank_of(&(Score(score), member.to_vec())))
+    }
+
+    /// Returns the member at 0-based `rank` in ascending score order, with its score.
+    pub fn get_by_rank(&self, rank: usize) -> Option<(&Vec<u8>, f64)> {
+        self.index.get(rank).map(|(s, m)| (m, s.0))
+    }
+
+    /// Iterates members in ascending (score, member) order.
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = (&Vec<u8>, f64)> {
+        self.index.iter().map(|(s, m)| (m, s.0))
+    }
+
+    /// Iterates the members from 0-based rank `start` up to, not including, `end`.
+    pub fn iter_ranks(
+        &self,
+        start: usize,
+        end: usize,
+    ) -> impl DoubleEndedIterator<Item = (&Vec<u8>, f64)> {
+        self.index.iter_ranks(start, end).map(|(s, m)| (m, s.0))
+    }
+
+    /// Iterates the members whose scores fall between `min` and `max`,
+    /// ascending or, with `rev`, descending.
+    pub fn range_by_score(&self, min: ScoreBound, max: ScoreBound, rev: bool) -> ZIter<'_> {
+        let start = self.index.partition_point(|(s, _)| !min.admits_min(s.0));
+        let end = self.index.partition_point(|(s, _)| max.admits_max(s.0));
+        self.ranks(start, end, rev)
+    }
+
+    /// Iterates the members that fall between `min` and `max` lexicographically.
+    /// Like Redis, this assumes all members share the same score.
+    pub fn range_by_lex(&self, min: LexBound, max: LexBound, rev: bool) -> ZIter<'_> {
+        let start = self.index.partition_point(|(_, m)| !min.admits_min(m));
+        let end = self.index.partition_point(|(_, m)| max.admits_max(m));
+        self.ranks(start, end, rev)
+    }
+
+    fn ranks(&self, start: usize, end: usize, rev: bool) -> ZIter<'_> {
+        if rev {
+            Box::new(self.iter_ranks(start, end).rev())
+        } else {
+            Box::new(self.iter_ranks(start, end))
+        }
+    }
+
+    /// Removes and returns the member with the lowest score.
+    pub fn pop_first(&mut self) -> Option<(Vec<u8>, f64)> {
+        let first = self.index.first()?.clone();
+        let (score, member) = self.index.remove(&first)?;
+        self.scores.remove(&member);
+        Some((member, score.0))
+    }
+
+    /// Removes and returns the member with the highest score.
+    pub fn pop_last(&mut self) -> Option<(Vec<u8>, f64)> {
+        let last = self.index.last()?.clone();
+        let (score, member) = self.index.remove(&last)?;
+        self.scores.remove(&member);
+        Some((member, score.0))
+    }
+}
+
+impl MemSize for ZSet {
+    /// Each member is held twice, once in `scores` and once in an `index` node.
+    fn heap_size(&self) -> usize {
+        estimate(self.scores.keys(), self.len(), |member| {
+            2 * element_size(member) + size_of::<f64>() + SkipList::<(Score, Vec<u8>)>::node_size()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn members(iter: ZIter<'_>) -> Vec<&[u8]> {
+        iter.map(|(m, _)| m.as_slice()).collect()
+    }
+
+    fn bound(value: f64, exclusive: bool) -> ScoreBound {
+        ScoreBound { value, exclusive }
+    }
+
+    #[test]
+    fn ranks_and_ranges_members_with_equal_scores() {
+        let mut zset = ZSet::new();
+        for (member, score) in [("d", 2.0), ("a", 1.0), ("c", 2.0), ("b", 2.0), ("e", 3.0)] {
+            assert_eq!(zset.insert(member.into(), score), None);
+        }
+        assert_eq!(zset.insert(b"e".to_vec(), -0.0), Some(3.0));
+        let ranks: Vec<_> = ["e", "a", "b", "c", "d"]
+            .iter()
+            .map(|m| zset.rank(m.as_bytes()))
+            .collect();
+        assert_eq!(ranks, [Some(0), Some(1), Some(2), Some(3), Some(4)]);
+        assert_eq!(zset.rank(b"x"), None);
+
+        let by_score = |min, max, rev| members(zset.range_by_score(min, max, rev));
+        assert_eq!(
+            by_score(bound(2.0, false), bound(2.0, false), false),
+            [b"b", b"c", b"d"]
+        );
+        assert_eq!(
+            by_score(bound(0.0, false), bound(2.0, true), true),
+            [b"a", b"e"]
+        );
+        assert_eq!(
+            by_score(bound(1.0, true), bound(f64::INFINITY, false), true),
+            [b"d", b"c", b"b"]
+        );
+        assert!(by_score(bound(3.0, false), bound(1.0, false), false).is_empty());
+
+        assert_eq!(zset.remove(b"a"), Some(1.0));
+        assert_eq!(zset.pop_first(), Some((b"e".to_vec(), 0.0)));
+        assert_eq!(zset.pop_last(), Some((b"d".to_vec(), 2.0)));
+        let lex = |min, max, rev| members(zset.range_by_lex(min, max, rev));
+        assert_eq!(lex(LexBound::NegInf, LexBound::PosInf, false), [b"b", b"c"]);
+        assert_eq!(
+            lex(LexBound::Exclusive(b"b".to_vec()), LexBound::PosInf, false),
+            [b"c"]
+        );
+        assert_eq!(
+            lex(LexBound::NegInf, LexBound::Inclusive(b"b".to_vec()), true),
+            [b"b"]
+        );
+        assert!(lex(LexBound::PosInf, LexBound::NegInf, false).is_empty());
+    }
+}
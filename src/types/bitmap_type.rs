@@ -0,0 +1,473 @@
+use crate::store::store::{Lookup, StoreTxn, StoreVal, global_store};
+use crate::types::WRONG_TYPE;
+
+/// Bitmaps are limited to 512MB, like Redis strings.
+pub const MAX_BIT_OFFSET: u64 = (512 << 23) - 1;
+
+/// Unit of the range given to BITCOUNT and BITPOS.
+#[derive(Clone, Copy, PartialEq)]
+pub enum BitUnit {
+    Byte,
+    Bit,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum BitOp {
+    And,
+    Or,
+    Xor,
+    Not,
+    /// Bits set in the first source but in none of the others.
+    Diff,
+}
+
+/// What BITFIELD does when a SET or INCRBY goes out of range.
+#[derive(Clone, Copy)]
+pub enum Overflow {
+    Wrap,
+    Sat,
+    Fail,
+}
+
+/// A BITFIELD integer type such as `i8` or `u16`.
+#[derive(Clone, Copy)]
+pub struct FieldType {
+    pub signed: bool,
+    pub bits: u32,
+}
+
+impl FieldType {
+    /// Parses `i1`..`i64` or `u1`..`u63`.
+    pub fn parse(s: &str) -> Option<FieldType> {
+        let signed = match s.as_bytes().first()? {
+            b'i' | b'I' => true,
+            b'u' | b'U' => false,
+            _ => return None,
+        };
+        let bits: u32 = s[1..].parse().ok()?;
+        let max = if signed { 64 } else { 63 };
+        (1..=max)
+            .contains(&bits)
+            .then_some(FieldType { signed, bits })
+    }
+
+    fn min(self) -> i128 {
+        if self.signed {
+            -(1i128 << (self.bits - 1))
+        } else {
+            0
+        }
+    }
+
+    fn max(self) -> i128 {
+        if self.signed {
+            (1i128 << (self.bits - 1)) - 1
+        } else {
+            (1i128 << self.bits) - 1
+        }
+    }
+
+    /// Fits `value` into the type according to `overflow`.
+    /// Returns `None` if it does not fit and `overflow` is `Fail`.
+    fn fit(self, value: i128, overflow: Overflow) -> Option<i64> {
+        if (self.min()..=self.max()).contains(&value) {
+            return Some(value as i64);
+        }
+        match overflow {
+            Overflow::Fail => None,
+            Overflow::Sat => Some(value.clamp(self.min(), self.max()) as i64),
+            Overflow::Wrap => {
+                let wrapped = value.rem_euclid(1i128 << self.bits);
+                Some(if wrapped > self.max() {
+                    (wrapped - (1i128 << self.bits)) as i64
+                } else {
+                    wrapped as i64
+                })
+            }
+        }
+    }
+
+    /// Interprets the low `bits` bits of `raw` as a value of this type.
+    fn decode(self, raw: u64) -> i64 {
+        if self.signed && self.bits < 64 && raw & (1 << (self.bits - 1)) != 0 {
+            (raw | (u64::MAX << self.bits)) as i64
+        } else {
+            raw as i64
+        }
+    }
+}
+
+/// A single BITFIELD operation. Offsets are in bits.
+#[derive(Clone, Copy)]
+pub enum FieldOp {
+    Get(FieldType, u64),
+    Set(FieldType, u64, i64, Overflow),
+    IncrBy(FieldType, u64, i64, Overflow),
+}
+
+pub struct BitmapType;
+
+impl BitmapType {
+    /// Sets or clears the bit at `offset` and returns its previous value.
+    pub fn setbit(key: &[u8], offset: u64, bit: bool) -> Result<u8, String> {
+        Self::with_bytes_mut(&mut global_store().lock([key]), key, |bytes| {
+            let old = get_bit(bytes, offset);
+            set_bits(bytes, offset, 1, bit as u64);
+            old
+        })
+    }
+
+    pub fn getbit(key: &[u8], offset: u64) -> Result<u8, String> {
+        let result = Self::with_bytes(&global_store().read([key]), key, |bytes| {
+            get_bit(bytes, offset)
+        });
+        result.map(|bit| bit.unwrap_or(0))
+    }
+
+    /// Counts the set bits, optionally only within the inclusive `range`.
+    pub fn bitcount(key: &[u8], range: Option<(i64, i64, BitUnit)>) -> Result<i64, String> {
+        let result = Self::with_bytes(&global_store().read([key]), key, |bytes| {
+            let Some((start, end)) = bit_range(bytes, range) else {
+                return 0;
+            };
+            count_bits(bytes, start, end) as i64
+        });
+        result.map(|n| n.unwrap_or(0))
+    }
+
+    /// Returns the position of the first bit equal to `bit`, optionally only within
+    /// `range`, or -1 if there is none.
+    pub fn bitpos(
+        key: &[u8],
+        bit: bool,
+        range: Option<(i64, Option<i64>, BitUnit)>,
+    ) -> Result<i64, String> {
+        let result = Self::with_bytes(&global_store().read([key]), key, |bytes| {
+            let end_given = matches!(range, Some((_, Some(_), _)));
+            let range = range.map(|(start, end, unit)| (start, end.unwrap_or(-1), unit));
+            let Some((start, end)) = bit_range(bytes, range) else {
+                return -1;
+            };
+            match find_bit(bytes, start, end, bit) {
+                Some(pos) => pos as i64,
+                // Looking for a clear bit past the end of the string finds the padding.
+                None if !bit && !end_given => bytes.len() as i64 * 8,
+                None => -1,
+            }
+        });
+        result.map(|pos| pos.unwrap_or(if bit { -1 } else { 0 }))
+    }
+
+    /// Stores the result of `op` over the strings at `keys` in `destination`.
+    /// Returns the length of the result, which is deleted if it is empty.
+    pub fn bitop(op: BitOp, destination: &[u8], keys: &[Vec<u8>]) -> Result<i64, String> {
+        let mut txn = global_store().lock(keys.iter().map(Vec::as_slice).chain([destination]));
+        Self::combine(&txn, op, keys).map(|bytes| {
+            let len = bytes.len() as i64;
+            if bytes.is_empty() {
+                txn.delete(destination);
+            } else {
+                txn.set(destination.to_vec(), StoreVal::Str(bytes), None);
+            }
+            len
+        })
+    }
+
+    /// Runs BITFIELD operations in order. Each yields a value, or `None` when a
+    /// write failed under `Overflow::Fail`.
+    pub fn bitfield(key: &[u8], ops: &[FieldOp]) -> Result<Vec<Option<i64>>, String> {
+        let read_only = ops.iter().all(|op| matches!(op, FieldOp::Get(..)));
+        if read_only {
+            let result = Self::with_bytes(&global_store().read([key]), key, |bytes| {
+                ops.iter()
+                    .map(|op| match *op {
+                        FieldOp::Get(ty, offset) => {
+                            Some(ty.decode(get_bits(bytes, offset, ty.bits)))
+                        }
+                        _ => None,
+                    })
+                    .collect()
+            });
+            return result.map(|values| values.unwrap_or_else(|| vec![Some(0); ops.len()]));
+        }
+        Self::with_bytes_mut(&mut global_store().lock([key]), key, |bytes| {
+            ops.iter().map(|op| Self::apply_field(bytes, op)).collect()
+        })
+    }
+
+    fn apply_field(bytes: &mut Vec<u8>, op: &FieldOp) -> Option<i64> {
+        match *op {
+            FieldOp::Get(ty, offset) => Some(ty.decode(get_bits(bytes, offset, ty.bits))),
+            FieldOp::Set(ty, offset, value, overflow) => {
+                let old = ty.decode(get_bits(bytes, offset, ty.bits));
+                let new = ty.fit(value as i128, overflow)?;
+                set_bits(bytes, offset, ty.bits, new as u64);
+                Some(old)
+            }
+            FieldOp::IncrBy(ty, offset, incr, overflow) => {
+                let old = ty.decode(get_bits(bytes, offset, ty.bits));
+                let new = ty.fit(old as i128 + incr as i128, overflow)?;
+                set_bits(bytes, offset, ty.bits, new as u64);
+                Some(new)
+            }
+        }
+    }
+
+    /// Applies `op` byte by byte over the sources, padding shorter ones with zeros.
+    fn combine(
+        txn: &impl Lookup<Vec<u8>, StoreVal>,
+        op: BitOp,
+        keys: &[Vec<u8>],
+    ) -> Result<Vec<u8>, String> {
+        let mut sources = Vec::with_capacity(keys.len());
+        for key in keys {
+            sources.push(Self::with_bytes(txn, key, |bytes| bytes.clone())?.unwrap_or_default());
+        }
+        let len = sources.iter().map(Vec::len).max().unwrap_or(0);
+        let byte = |src: &Vec<u8>, i: usize| src.get(i).copied().unwrap_or(0);
+        let Some((first, rest)) = sources.split_first() else {
+            return Ok(vec![]);
+        };
+        Ok((0..len)
+            .map(|i| {
+                let others = rest.iter().map(|src| byte(src, i));
+                match op {
+                    BitOp::And => others.fold(byte(first, i), |acc, b| acc & b),
+                    BitOp::Or => others.fold(byte(first, i), |acc, b| acc | b),
+                    BitOp::Xor => others.fold(byte(first, i), |acc, b| acc ^ b),
+                    BitOp::Not => !byte(first, i),
+                    BitOp::Diff => byte(first, i) & !others.fold(0, |acc, b| acc | b),
+                }
+            })
+            .collect())
+    }
+
+    /// Runs `f` on the string stored at `key` without copying it.
+    /// Returns `None` if the key does not exist.
+    fn with_bytes<R>(
+        txn: &impl Lookup<Vec<u8>, StoreVal>,
+        key: &[u8],
+        f: impl FnOnce(&Vec<u8>) -> R,
+    ) -> Result<Option<R>, String> {
+        txn.with(key, |val| {
+            val.get_str().map(f).ok_or_else(|| WRONG_TYPE.to_string())
+        })
+        .transpose()
+    }
+
+    /// Runs `f` on the string stored at `key`, creating an empty one if it is missing.
+    fn with_bytes_mut<R>(
+        txn: &mut StoreTxn,
+        key: &[u8],
+        f: impl FnOnce(&mut Vec<u8>) -> R,
+    ) -> Result<R, String> {
+        txn.upsert(
+            key,
+            || StoreVal::Str(Vec::new()),
+            |val| {
+                val.get_str_mut()
+                    .map(f)
+                    .ok_or_else(|| WRONG_TYPE.to_string())
+            },
+        )
+    }
+}
+
+/// Resolves an inclusive BITCOUNT/BITPOS range to bit positions within `bytes`.
+/// Negative offsets count from the end. Returns `None` if the range is empty.
+fn bit_range(bytes: &[u8], range: Option<(i64, i64, BitUnit)>) -> Option<(u64, u64)> {
+    let (start, end, unit) = range.unwrap_or((0, -1, BitUnit::Byte));
+    let len = match unit {
+        BitUnit::Byte => bytes.len() as i64,
+        BitUnit::Bit => bytes.len() as i64 * 8,
+    };
+    let resolve = |i: i64| if i < 0 { (len + i).max(0) } else { i };
+    let (start, end) = (resolve(start), resolve(end).min(len - 1));
+    if len == 0 || start > end {
+        return None;
+    }
+    Some(match unit {
+        BitUnit::Byte => (start as u64 * 8, end as u64 * 8 + 7),
+        BitUnit::Bit => (start as u64, end as u64),
+    })
+}
+
+/// Counts the set bits between the inclusive bit positions `start` and `end`.
+fn count_bits(bytes: &[u8], start: u64, end: u64) -> u64 {
+    let (first, last) = (start / 8, end / 8);
+    let count = |range: std::ops::RangeInclusive<u64>| {
+        range.filter(|&i| get_bit(bytes, i) == 1).count() as u64
+    };
+    if first == last {
+        return count(start..=end);
+    }
+    let middle: u64 = bytes[(first + 1) as usize..last as usize]
+        .iter()
+        .map(|b| b.count_ones() as u64)
+        .sum();
+    count(start..=first * 8 + 7) + middle + count(last * 8..=end)
+}
+
+/// Returns the first position between `start` and `end` inclusive holding `bit`.
+fn find_bit(bytes: &[u8], start: u64, end: u64, bit: bool) -> Option<u64> {
+    // Whole bytes made only of the other bit can be skipped.
+    let skip = if bit { 0x00 } else { 0xff };
+    let mut i = start;
+    while i <= end {
+        if i.is_multiple_of(8) && i + 7 <= end && bytes.get((i / 8) as usize) == Some(&skip) {
+            i += 8;
+            continue;
+        }
+        if get_bit(bytes, i) == bit as u8 {
+            return Some(i);
+        }
+        i += 1;
+    }
+    None
+}
+
+fn get_bit(bytes: &[u8], offset: u64) -> u8 {
+    let byte = bytes.get((offset / 8) as usize).copied().unwrap_or(0);
+    (byte >> (7 - offset % 8)) & 1
+}
+
+/// Reads `bits` bits starting at `offset`, most significant first.
+/// Bits past the end of the string read as zero.
+fn get_bits(bytes: &[u8], offset: u64, bits: u32) -> u64 {
+    (0..bits as u64).fold(0, |acc, i| (acc << 1) | get_bit(bytes, offset + i) as u64)
+}
+
+/// Writes the low `bits` bits of `value` starting at `offset`, growing the string
+/// with zero bytes as needed.
+fn set_bits(bytes: &mut Vec<u8>, offset: u64, bits: u32, value: u64) {
+    let needed = ((offset + bits as u64).div_ceil(8)) as usize;
+    if bytes.len() < needed {
+        bytes.resize(needed, 0);
+    }
+    for i in 0..bits as u64 {
+        let bit = (value >> (bits as u64 - 1 - i)) & 1;
+        let pos = offset + i;
+        let mask = 1u8 << (7 - pos % 8);
+        let byte = &mut bytes[(pos / 8) as usize];
+        if bit == 1 {
+            *byte |= mask;
+        } else {
+            *byte &= !mask;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn naive_bits(bytes: &[u8]) -> Vec<u8> {
+        (0..bytes.len() as u64 * 8)
+            .map(|i| get_bit(bytes, i))
+            .collect()
+    }
+
+    #[test]
+    fn counts_and_finds_bits_like_a_bit_by_bit_scan() {
+        let mut rng = fastrand::Rng::with_seed(9);
+        for _ in 0..200 {
+            let bytes: Vec<u8> = (0..rng.usize(1..8))
+                .map(|_| match rng.u8(..4) {
+                    0 => 0x00,
+                    1 => 0xff,
+                    _ => rng.u8(..),
+                })
+                .collect();
+            let bits = naive_bits(&bytes);
+            let start = rng.u64(..bits.len() as u64);
+            let end = rng.u64(start..bits.len() as u64);
+            let window = &bits[start as usize..=end as usize];
+            let ones = window.iter().filter(|&&b| b == 1).count() as u64;
+            assert_eq!(count_bits(&bytes, start, end), ones);
+            for bit in [false, true] {
+                let expected = window.iter().position(|&b| b == bit as u8);
+                assert_eq!(
+                    find_bit(&bytes, start, end, bit),
+                    expected.map(|i| start + i as u64)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn resolves_negative_and_out_of_range_offsets() {
+        let bytes = [0u8; 4];
+        assert_eq!(bit_range(&bytes, None), Some((0, 31)));
+        assert_eq!(
+            bit_range(&bytes, Some((-2, -1, BitUnit::Byte))),
+            Some((16, 31))
+        );
+        assert_eq!(
+            bit_range(&bytes, Some((5, -3, BitUnit::Bit))),
+            Some((5, 29))
+        );
+        assert_eq!(
+            bit_range(&bytes, Some((-100, 100, BitUnit::Byte))),
+            Some((0, 31))
+        );
+        assert_eq!(bit_range(&bytes, Some((3, 1, BitUnit::Byte))), None);
+        assert_eq!(bit_range(&[], None), None);
+    }
+
+    #[test]
+    fn writes_and_reads_unaligned_fields() {
+        let mut bytes = Vec::new();
+        set_bits(&mut bytes, 5, 13, 0x1abc);
+        assert_eq!(bytes.len(), 3);
+        assert_eq!(get_bits(&bytes, 5, 13), 0x1abc);
+        assert_eq!(get_bits(&bytes, 0, 5), 0);
+        assert_eq!(get_bits(&bytes, 18, 40), 0);
+        set_bits(&mut bytes, 0, 64, u64::MAX);
+        assert_eq!(bytes, [0xff; 8]);
+    }
+
+    #[test]
+    fn fits_field_values_by_overflow_policy() {
+        let i8 = FieldType::parse("i8").unwrap();
+        let u4 = FieldType::parse("u4").unwrap();
+        assert!(FieldType::parse("u64").is_none() && FieldType::parse("i0").is_none());
+        assert_eq!(i8.fit(127, Overflow::Fail), Some(127));
+        assert_eq!(i8.fit(128, Overflow::Fail), None);
+        assert_eq!(i8.fit(128, Overflow::Wrap), Some(-128));
+        assert_eq!(i8.fit(-200, Overflow::Sat), Some(-128));
+        assert_eq!(u4.fit(17, Overflow::Wrap), Some(1));
+        assert_eq!(u4.fit(-1, Overflow::Wrap), Some(15));
+        assert_eq!(u4.fit(-1, Overflow::Sat), Some(0));
+        assert_eq!(i8.decode(0xff), -1);
+        assert_eq!(u4.decode(0xf), 15);
+    }
+
+    #[test]
+    fn runs_bit_commands_on_stored_strings() {
+        let (a, b, dest) = (
+            b"bitmap_type::a".as_slice(),
+            b"bitmap_type::b".as_slice(),
+            b"bitmap_type::dest".as_slice(),
+        );
+        assert_eq!(BitmapType::setbit(a, 7, true), Ok(0));
+        assert_eq!(BitmapType::setbit(a, 7, true), Ok(1));
+        assert_eq!(BitmapType::setbit(b, 14, true), Ok(0));
+        assert_eq!(BitmapType::getbit(b, 1000), Ok(0));
+        assert_eq!(BitmapType::bitcount(b, None), Ok(1));
+        let keys = [a.to_vec(), b.to_vec()];
+        assert_eq!(BitmapType::bitop(BitOp::Or, dest, &keys), Ok(2));
+        assert_eq!(BitmapType::bitcount(dest, None), Ok(2));
+        assert_eq!(BitmapType::bitop(BitOp::And, dest, &keys), Ok(2));
+        assert_eq!(BitmapType::bitcount(dest, None), Ok(0));
+        let u8 = FieldType::parse("u8").unwrap();
+        let ops = [
+            FieldOp::IncrBy(u8, 16, 300, Overflow::Fail),
+            FieldOp::IncrBy(u8, 16, 300, Overflow::Sat),
+            FieldOp::Get(u8, 16),
+        ];
+        assert_eq!(
+            BitmapType::bitfield(a, &ops),
+            Ok(vec![None, Some(255), Some(255)])
+        );
+    }
+}
@@ -0,0 +1,242 @@
+use std::collections::HashMap;
+
+use crate::store::store::{Lookup, StoreTxn, StoreVal, global_store};
+use crate::types::string_type::{OVERFLOW, format_float};
+use crate::types::{WRONG_TYPE, random_picks};
+
+/// A field and its value.
+pub type FieldValue = (Vec<u8>, Vec<u8>);
+
+type Hash = HashMap<Vec<u8>, Vec<u8>>;
+
+pub struct HashType;
+
+impl HashType {
+    /// Sets the given field/value pairs in the hash stored at `key`.
+    /// Returns the number of fields that were newly added.
+    pub fn hset(key: &[u8], pairs: &[FieldValue]) -> Result<i64, String> {
+        let mut txn = global_store().lock([key]);
+        Self::hash_or_insert(&mut txn, key, |hash| {
+            pairs
+                .iter()
+                .filter(|(field, value)| hash.insert(field.clone(), value.clone()).is_none())
+                .count() as i64
+        })
+    }
+
+    /// Sets `field` only if it does not exist yet. Returns whether it was set.
+    pub fn hsetnx(key: &[u8], field: &[u8], value: &[u8]) -> Result<bool, String> {
+        let mut txn = global_store().lock([key]);
+        Self::hash_or_insert(&mut txn, key, |hash| {
+            if hash.contains_key(field) {
+                return false;
+            }
+            hash.insert(field.to_vec(), value.to_vec());
+            true
+        })
+    }
+
+    pub fn hget(key: &[u8], field: &[u8]) -> Result<Option<Vec<u8>>, String> {
+        let view = global_store().read([key]);
+        Ok(Self::with_hash(&view, key, |h| h.get(field).cloned())?.flatten())
+    }
+
+    /// Returns the value of every requested field, `None` for missing ones.
+    pub fn hmget(key: &[u8], fields: &[Vec<u8>]) -> Result<Vec<Option<Vec<u8>>>, String> {
+        let view = global_store().read([key]);
+        let values = Self::with_hash(&view, key, |h| {
+            fields.iter().map(|f| h.get(f).cloned()).collect()
+        })?;
+        Ok(values.unwrap_or_else(|| vec![None; fields.len()]))
+    }
+
+    /// Removes the given fields and returns how many existed.
+    /// The key is deleted once the hash becomes empty.
+    pub fn hdel(key: &[u8], fields: &[Vec<u8>]) -> Result<i64, String> {
+        let mut txn = global_store().lock([key]);
+        let removed = Self::with_hash_mut(&mut txn, key, |h| {
+            fields.iter().filter(|f| h.remove(*f).is_some()).count() as i64
+        })?;
+        Ok(removed.unwrap_or(0))
+    }
+
+    pub fn hexists(key: &[u8], field: &[u8]) -> Result<bool, String> {
+        let view = global_store().read([key]);
+        Ok(Self::with_hash(&view, key, |h| h.contains_key(field))?.unwrap_or(false))
+    }
+
+    pub fn hgetall(key: &[u8]) -> Result<Vec<FieldValue>, String> {
+        let view = global_store().read([key]);
+        let pairs = Self::with_hash(&view, key, |h| {
+            h.iter().map(|(f, v)| (f.clone(), v.clone())).collect()
+        })?;
+        Ok(pairs.unwrap_or_default())
+    }
+
+    pub fn hkeys(key: &[u8]) -> Result<Vec<Vec<u8>>, String> {
+        let view = global_store().read([key]);
+        Ok(Self::with_hash(&view, key, |h| h.keys().cloned().collect())?.unwrap_or_default())
+    }
+
+    pub fn hvals(key: &[u8]) -> Result<Vec<Vec<u8>>, String> {
+        let view = global_store().read([key]);
+        Ok(Self::with_hash(&view, key, |h| h.values().cloned().collect())?.unwrap_or_default())
+    }
+
+    pub fn hlen(key: &[u8]) -> Result<i64, String> {
+        let view = global_store().read([key]);
+        Ok(Self::with_hash(&view, key, |h| h.len() as i64)?.unwrap_or(0))
+    }
+
+    /// Returns the length of the value stored at `field`, or 0 if it does not exist.
+    pub fn hstrlen(key: &[u8], field: &[u8]) -> Result<i64, String> {
+        let view = global_store().read([key]);
+        let len = Self::with_hash(&view, key, |h| h.get(field).map(Vec::len))?;
+        Ok(len.flatten().unwrap_or(0) as i64)
+    }
+
+    /// Increments the integer stored at `field` by `incr` and returns the result.
+    pub fn hincrby(key: &[u8], field: &[u8], incr: i64) -> Result<i64, String> {
+        Self::update_field(key, field, |current| {
+            let n = match current {
+                Some(v) => std::str::from_utf8(v)
+                    .ok()
+                    .and_then(|v| v.parse::<i64>().ok())
+                    .ok_or_else(|| "ERR hash value is not an integer".to_string())?,
+                None => 0,
+            };
+            let n = n.checked_add(incr).ok_or_else(|| OVERFLOW.to_string())?;
+            Ok((n.to_string().into_bytes(), n))
+        })
+    }
+
+    /// Increments the float stored at `field` by `incr` and returns the result.
+    pub fn hincrbyfloat(key: &[u8], field: &[u8], incr: f64) -> Result<String, String> {
+        Self::update_field(key, field, |current| {
+            let n = match current {
+                Some(v) => std::str::from_utf8(v)
+                    .ok()
+                    .and_then(|v| v.parse::<f64>().ok())
+                    .ok_or_else(|| "ERR hash value is not a float".to_string())?,
+                None => 0.0,
+            } + incr;
+            if !n.is_finite() {
+                return Err("ERR increment would produce NaN or Infinity".to_string());
+            }
+            let s = format_float(n);
+            Ok((s.clone().into_bytes(), s))
+        })
+    }
+
+    /// Returns random fields with their values.
+    /// A positive `count` returns distinct fields, a negative one may repeat them.
+    pub fn hrandfield(key: &[u8], count: i64) -> Result<Vec<FieldValue>, String> {
+        let view = global_store().read([key]);
+        let picked = Self::with_hash(&view, key, |hash| -> Result<Vec<FieldValue>, String> {
+            if count >= 0 {
+                let picked = if count as u64 >= hash.len() as u64 {
+                    hash.iter().collect()
+                } else {
+                    fastrand::choose_multiple(hash.iter(), count as usize)
+                };
+                return Ok(picked
+                    .into_iter()
+                    .map(|(f, v)| (f.clone(), v.clone()))
+                    .collect());
+            }
+            let entries: Vec<_> = hash.iter().collect();
+            Ok(random_picks(entries.len(), count.unsigned_abs())?
+                .map(|i| (entries[i].0.clone(), entries[i].1.clone()))
+                .collect())
+        })?;
+        Ok(picked.transpose()?.unwrap_or_default())
+    }
+
+    /// Applies `f` to the current value of `field` and stores the new value it returns.
+    fn update_field<T>(
+        key: &[u8],
+        field: &[u8],
+        f: impl FnOnce(Option<&Vec<u8>>) -> Result<(Vec<u8>, T), String>,
+    ) -> Result<T, String> {
+        let mut txn = global_store().lock([key]);
+        let current = Self::with_hash(&txn, key, |h| h.get(field).cloned())?.flatten();
+        let (value, reply) = f(current.as_ref())?;
+        Self::hash_or_insert(&mut txn, key, |h| h.insert(field.to_vec(), value))?;
+        Ok(reply)
+    }
+
+    /// Runs `f` on the hash stored at `key` without copying it.
+    /// Returns `None` if the key does not exist.
+    fn with_hash<R>(
+        txn: &impl Lookup<Vec<u8>, StoreVal>,
+        key: &[u8],
+        f: impl FnOnce(&Hash) -> R,
+    ) -> Result<Option<R>, String> {
+        txn.with(key, |val| {
+            val.get_hash().map(f).ok_or_else(|| WRONG_TYPE.to_string())
+        })
+        .transpose()
+    }
+
+    /// Runs `f` on the hash stored at `key`, mutating it in place.
+    /// The key is deleted once the hash becomes empty.
+    fn with_hash_mut<R>(
+        txn: &mut StoreTxn,
+        key: &[u8],
+        f: impl FnOnce(&mut Hash) -> R,
+    ) -> Result<Option<R>, String> {
+        let result = txn.with_mut(key, |val| {
+            let hash = val.get_hash_mut().ok_or_else(|| WRONG_TYPE.to_string())?;
+            let r = f(hash);
+            Ok::<_, String>((r, hash.is_empty()))
+        });
+        match result.transpose()? {
+            Some((r, empty)) => {
+                if empty {
+                    txn.delete(key);
+                }
+                Ok(Some(r))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Runs `f` on the hash stored at `key`, creating an empty one if it is missing.
+    /// `f` must leave at least one field in a hash it creates.
+    fn hash_or_insert<R>(
+        txn: &mut StoreTxn,
+        key: &[u8],
+        f: impl FnOnce(&mut Hash) -> R,
+    ) -> Result<R, String> {
+        txn.upsert(
+            key,
+            || StoreVal::Hash(Hash::new()),
+            |val| {
+                val.get_hash_mut()
+                    .map(f)
+                    .ok_or_else(|| WRONG_TYPE.to_string())
+            },
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hrandfield_returns_every_field_for_huge_counts() {
+        let key = b"hash_type::hrandfield_huge_count";
+        let pairs: Vec<FieldValue> = (0..3)
+            .map(|i| (format!("f{i}").into_bytes(), format!("v{i}").into_bytes()))
+            .collect();
+        HashType::hset(key, &pairs).unwrap();
+        for count in [3, 4, 1_000_000_000, i64::MAX / 2] {
+            let mut fields = HashType::hrandfield(key, count).unwrap();
+            fields.sort();
+            assert_eq!(fields, pairs, "count {count}");
+        }
+        assert_eq!(HashType::hrandfield(key, 2).unwrap().len(), 2);
+        assert_eq!(HashType::hrandfield(key, -5).unwrap().len(), 5);
+    }
+}
@@ -0,0 +1,79 @@
+use crate::store::store::{Lookup, counts_frequency, current_unix_millis, global_store};
+
+const LFU_NOT_SELECTED: &str = "ERR An LFU maxmemory policy is not selected, access frequency not tracked. Please note that when switching between policies at runtime LRU and LFU data will take some time to adjust.";
+
+/// The NX, XX, GT and LT options of EXPIRE and its variants,
+/// which restrict when a key's expiration may be changed.
+#[derive(Clone, Copy, Default)]
+pub struct ExpireCondition {
+    /// Only if the key has no expiration.
+    pub nx: bool,
+    /// Only if the key already has an expiration.
+    pub xx: bool,
+    /// Only if the new expiration is later than the current one.
+    pub gt: bool,
+    /// Only if the new expiration is sooner than the current one.
+    pub lt: bool,
+}
+
+impl ExpireCondition {
+    /// A key without an expiration counts as expiring never, so GT
+    /// never applies to it and LT always does.
+    fn allows(&self, current: Option<u64>, at: u64) -> bool {
+        match current {
+            None => !self.xx && !self.gt,
+            Some(current) => !self.nx && (!self.gt || at > current) && (!self.lt || at < current),
+        }
+    }
+}
+
+/// Commands that work on keys regardless of the type of value they hold.
+pub struct KeyType;
+
+impl KeyType {
+    /// Makes `key` expire at the Unix time in milliseconds `at` if `condition`
+    /// allows it. Returns whether the expiration was changed.
+    pub fn expire_at(key: &[u8], at: u64, condition: ExpireCondition) -> bool {
+        let mut txn = global_store().lock([key]);
+        match txn.expire_time(key) {
+            Some(current) if condition.allows(current, at) => txn.expire_at(key, Some(at)),
+            _ => false,
+        }
+    }
+
+    /// Returns the Unix time in milliseconds `key` expires at,
+    /// -1 if it has no expiration and -2 if it does not exist.
+    pub fn expire_time(key: &[u8]) -> i64 {
+        match global_store().read([key]).expire_time(key) {
+            Some(Some(at)) => at as i64,
+            Some(None) => -1,
+            None => -2,
+        }
+    }
+
+    /// Returns the milliseconds left until `key` expires,
+    /// -1 if it has no expiration and -2 if it does not exist.
+    pub fn ttl(key: &[u8]) -> i64 {
+        match Self::expire_time(key) {
+            at if at < 0 => at,
+            at => (at - current_unix_millis() as i64).max(0),
+        }
+    }
+
+    /// Removes the expiration of `key`. Returns false if it had none or does not exist.
+    pub fn persist(key: &[u8]) -> bool {
+        let mut txn = global_store().lock([key]);
+        matches!(txn.expire_time(key), Some(Some(_))) && txn.expire_at(key, None)
+    }
+
+    /// Returns the LFU access frequency counter of `key`, or `None` if it does not exist.
+    pub fn object_freq(key: &[u8]) -> Result<Option<u64>, String> {
+        let Some(freq) = global_store().read([key]).frequency(key) else {
+            return Ok(None);
+        };
+        if !counts_frequency() {
+            return Err(LFU_NOT_SELECTED.to_string());
+        }
+        Ok(Some(freq))
+    }
+}
@@ -0,0 +1,327 @@
+use std::collections::VecDeque;
+
+use crate::store::store::{Lookup, StoreTxn, StoreVal, global_store};
+use crate::types::{WRONG_TYPE, normalize_range};
+
+/// Which end of a list an operation works on.
+#[derive(Clone, Copy, PartialEq)]
+pub enum ListEnd {
+    Left,
+    Right,
+}
+
+impl ListEnd {
+    pub fn parse(s: &str) -> Option<ListEnd> {
+        if s.eq_ignore_ascii_case("LEFT") {
+            Some(ListEnd::Left)
+        } else if s.eq_ignore_ascii_case("RIGHT") {
+            Some(ListEnd::Right)
+        } else {
+            None
+        }
+    }
+}
+
+/// The key popped from by LMPOP, along with the popped elements.
+pub type Popped = (Vec<u8>, Vec<Vec<u8>>);
+
+pub struct ListType;
+
+impl ListType {
+    /// Pushes `values` one after another onto the given end of the list.
+    /// With `only_if_exists` nothing happens when the key is missing.
+    /// Returns the length of the list after the push.
+    pub fn push(
+        key: &[u8],
+        values: &[Vec<u8>],
+        end: ListEnd,
+        only_if_exists: bool,
+    ) -> Result<i64, String> {
+        Self::push_values(
+            &mut global_store().lock([key]),
+            key,
+            values,
+            end,
+            only_if_exists,
+        )
+    }
+
+    /// Removes up to `count` elements from the given end.
+    /// Returns `None` if the key does not exist.
+    pub fn pop(key: &[u8], count: usize, end: ListEnd) -> Result<Option<Vec<Vec<u8>>>, String> {
+        Self::with_list_mut(&mut global_store().lock([key]), key, |list| {
+            Self::take(list, count, end)
+        })
+    }
+
+    pub fn llen(key: &[u8]) -> Result<i64, String> {
+        let len = Self::with_list(&global_store().read([key]), key, |l| l.len() as i64);
+        Ok(len?.unwrap_or(0))
+    }
+
+    /// Returns the elements between the inclusive indices `start` and `stop`.
+    pub fn lrange(key: &[u8], start: i64, stop: i64) -> Result<Vec<Vec<u8>>, String> {
+        let view = global_store().read([key]);
+        let range = Self::with_list(&view, key, |list| {
+            match normalize_range(start, stop, list.len()) {
+                Some((s, e)) => list.range(s..=e).cloned().collect(),
+                None => vec![],
+            }
+        });
+        Ok(range?.unwrap_or_default())
+    }
+
+    pub fn lindex(key: &[u8], index: i64) -> Result<Option<Vec<u8>>, String> {
+        let value = Self::with_list(&global_store().read([key]), key, |list| {
+            normalize_index(index, list.len()).map(|i| list[i].clone())
+        });
+        Ok(value?.flatten())
+    }
+
+    pub fn lset(key: &[u8], index: i64, value: &[u8]) -> Result<(), String> {
+        Self::with_list_mut(&mut global_store().lock([key]), key, |list| {
+            let i = normalize_index(index, list.len())
+                .ok_or_else(|| "ERR index out of range".to_string())?;
+            list[i] = value.to_vec();
+            Ok(())
+        })
+        .and_then(|r| r.unwrap_or_else(|| Err("ERR no such key".to_string())))
+    }
+
+    /// Inserts `value` before or after the first occurrence of `pivot`.
+    /// Returns the new length, -1 if the pivot was not found and 0 if the key is missing.
+    pub fn linsert(key: &[u8], before: bool, pivot: &[u8], value: &[u8]) -> Result<i64, String> {
+        let result = Self::with_list_mut(&mut global_store().lock([key]), key, |list| {
+            let Some(pos) = list.iter().position(|v| v == pivot) else {
+                return -1;
+            };
+            let at = if before { pos } else { pos + 1 };
+            list.insert(at, value.to_vec());
+            list.len() as i64
+        });
+        result.map(|len| len.unwrap_or(0))
+    }
+
+    /// Removes occurrences of `value`: the first `count` from the head when positive,
+    /// the last `|count|` from the tail when negative, all of them when zero.
+    /// Returns the number of removed elements.
+    pub fn lrem(key: &[u8], count: i64, value: &[u8]) -> Result<i64, String> {
+        let result = Self::with_list_mut(&mut global_store().lock([key]), key, |list| {
+            let limit = if count == 0 {
+                usize::MAX
+            } else {
+                count.unsigned_abs() as usize
+            };
+            let mut removed = 0;
+            if count >= 0 {
+                list.retain(|v| {
+                    if removed < limit && v == value {
+                        removed += 1;
+                        false
+                    } else {
+                        true
+                    }
+                });
+            } else {
+                let mut i = list.len();
+                while i > 0 && removed < limit {
+                    i -= 1;
+                    if list[i] == value {
+                        list.remove(i);
+                        removed += 1;
+                    }
+                }
+            }
+            removed as i64
+        });
+        result.map(|n| n.unwrap_or(0))
+    }
+
+    /// Keeps only the elements between the inclusive indices `start` and `stop`.
+    pub fn ltrim(key: &[u8], start: i64, stop: i64) -> Result<(), String> {
+        let mut txn = global_store().lock([key]);
+        let result = Self::with_list_mut(&mut txn, key, |list| {
+            match normalize_range(start, stop, list.len()) {
+                Some((s, e)) => {
+                    list.truncate(e + 1);
+                    list.drain(..s);
+                }
+                None => list.clear(),
+            }
+        });
+        result.map(|_| ())
+    }
+
+    /// Returns the indices of elements equal to `element`.
+    /// `rank` picks which match to start from (negative searches from the tail),
+    /// `count` limits the number of matches (0 means all) and `maxlen` limits
+    /// how many elements are compared (0 means all).
+    pub fn lpos(
+        key: &[u8],
+        element: &[u8],
+        rank: i64,
+        count: usize,
+        maxlen: usize,
+    ) -> Result<Vec<i64>, String> {
+        let skip = rank.unsigned_abs() as usize - 1;
+        let limit = if count == 0 { usize::MAX } else { count };
+        let positions = Self::with_list(&global_store().read([key]), key, |list| {
+            let len = list.len();
+            let maxlen = if maxlen == 0 { len } else { maxlen.min(len) };
+            let indices: Box<dyn Iterator<Item = usize>> = if rank > 0 {
+                Box::new(0..maxlen)
+            } else {
+                Box::new((len - maxlen..len).rev())
+            };
+            indices
+                .filter(|&i| list[i] == element)
+                .skip(skip)
+                .take(limit)
+                .map(|i| i as i64)
+                .collect()
+        });
+        Ok(positions?.unwrap_or_default())
+    }
+
+    /// Atomically pops an element from one end of `source` and pushes it onto
+    /// one end of `destination`. Returns the moved element.
+    pub fn lmove(
+        source: &[u8],
+        destination: &[u8],
+        from: ListEnd,
+        to: ListEnd,
+    ) -> Result<Option<Vec<u8>>, String> {
+        let mut txn = global_store().lock([source, destination]);
+        Self::move_element(&mut txn, source, destination, from, to)
+    }
+
+    /// Pops up to `count` elements from the first non-empty list among `keys`.
+    /// Returns the key that was popped from along with the elements.
+    pub fn lmpop(keys: &[Vec<u8>], end: ListEnd, count: usize) -> Result<Option<Popped>, String> {
+        Self::pop_first(&mut global_store().lock(keys), keys, end, count)
+    }
+
+    fn move_element(
+        txn: &mut StoreTxn,
+        source: &[u8],
+        destination: &[u8],
+        from: ListEnd,
+        to: ListEnd,
+    ) -> Result<Option<Vec<u8>>, String> {
+        if source == destination {
+            return Ok(Self::with_list_mut(txn, source, |list| {
+                let moved = Self::take(list, 1, from).pop();
+                if let Some(v) = &moved {
+                    Self::put(list, v.clone(), to);
+                }
+                moved
+            })?
+            .flatten());
+        }
+        // Check the destination type before anything is popped from the source.
+        Self::with_list(txn, destination, |_| ())?;
+        let moved =
+            Self::with_list_mut(txn, source, |list| Self::take(list, 1, from).pop())?.flatten();
+        if let Some(v) = &moved {
+            Self::push_values(txn, destination, std::slice::from_ref(v), to, false)?;
+        }
+        Ok(moved)
+    }
+
+    fn push_values(
+        txn: &mut StoreTxn,
+        key: &[u8],
+        values: &[Vec<u8>],
+        end: ListEnd,
+        only_if_exists: bool,
+    ) -> Result<i64, String> {
+        if only_if_exists && Self::with_list(txn, key, |_| ())?.is_none() {
+            return Ok(0);
+        }
+        txn.upsert(
+            key,
+            || StoreVal::List(VecDeque::new()),
+            |val| {
+                let list = val.get_list_mut().ok_or_else(|| WRONG_TYPE.to_string())?;
+                for v in values {
+                    Self::put(list, v.clone(), end);
+                }
+                Ok(list.len() as i64)
+            },
+        )
+    }
+
+    fn pop_first(
+        txn: &mut StoreTxn,
+        keys: &[Vec<u8>],
+        end: ListEnd,
+        count: usize,
+    ) -> Result<Option<Popped>, String> {
+        for key in keys {
+            if let Some(popped) =
+                Self::with_list_mut(txn, key, |list| Self::take(list, count, end))?
+            {
+                return Ok(Some((key.clone(), popped)));
+            }
+        }
+        Ok(None)
+    }
+
+    fn take(list: &mut VecDeque<Vec<u8>>, count: usize, end: ListEnd) -> Vec<Vec<u8>> {
+        let count = count.min(list.len());
+        match end {
+            ListEnd::Left => list.drain(..count).collect(),
+            ListEnd::Right => list.drain(list.len() - count..).rev().collect(),
+        }
+    }
+
+    fn put(list: &mut VecDeque<Vec<u8>>, value: Vec<u8>, end: ListEnd) {
+        match end {
+            ListEnd::Left => list.push_front(value),
+            ListEnd::Right => list.push_back(value),
+        }
+    }
+
+    /// Runs `f` on the list stored at `key` without copying it.
+    /// Returns `None` if the key does not exist.
+    fn with_list<R>(
+        txn: &impl Lookup<Vec<u8>, StoreVal>,
+        key: &[u8],
+        f: impl FnOnce(&VecDeque<Vec<u8>>) -> R,
+    ) -> Result<Option<R>, String> {
+        txn.with(key, |val| {
+            val.get_list().map(f).ok_or_else(|| WRONG_TYPE.to_string())
+        })
+        .transpose()
+    }
+
+    /// Runs `f` on the list stored at `key`, mutating it in place.
+    /// The key is deleted once the list becomes empty.
+    fn with_list_mut<R>(
+        txn: &mut StoreTxn,
+        key: &[u8],
+        f: impl FnOnce(&mut VecDeque<Vec<u8>>) -> R,
+    ) -> Result<Option<R>, String> {
+        let result = txn.with_mut(key, |val| {
+            let list = val.get_list_mut().ok_or_else(|| WRONG_TYPE.to_string())?;
+            let r = f(list);
+            Ok::<_, String>((r, list.is_empty()))
+        });
+        match result.transpose()? {
+            Some((r, empty)) => {
+                if empty {
+                    txn.delete(key);
+                }
+                Ok(Some(r))
+            }
+            None => Ok(None),
+        }
+    }
+}
+
+/// Resolves a possibly negative index against a list of length `len`.
+fn normalize_index(index: i64, len: usize) -> Option<usize> {
+    let len = len as i64;
+    let i = if index < 0 { len + index } else { index };
+    (0..len).contains(&i).then_some(i as usize)
+}
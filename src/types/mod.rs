@@ -1,2 +1,42 @@
+pub mod bitmap_type;
+pub mod hash_type;
+pub mod key_type;
+pub mod list_type;
 pub mod set_type;
+pub mod stream_type;
 pub mod string_type;
+pub mod zset_type;
+
+use crate::config::PROTO_MAX_MULTIBULK_LEN;
+
+pub const WRONG_TYPE: &str = "WRONGTYPE Operation against a key holding the wrong kind of value";
+
+/// Returns `count` random indexes below `len`, possibly repeating, for the
+/// negative counts of HRANDFIELD, SRANDMEMBER and ZRANDMEMBER. Those replies
+/// aren't bounded by the collection's size, so they are capped at
+/// `proto-max-multibulk-len` elements instead.
+pub(crate) fn random_picks(len: usize, count: u64) -> Result<impl Iterator<Item = usize>, String> {
+    if count > PROTO_MAX_MULTIBULK_LEN.get() {
+        return Err(
+            "ERR count exceeds maximum allowed reply size (proto-max-multibulk-len)".to_string(),
+        );
+    }
+    Ok((0..count).map(move |_| fastrand::usize(..len)))
+}
+
+/// Resolves an inclusive, possibly negative range over `len` elements, clamping it.
+/// Returns `None` if the range is empty.
+pub(crate) fn normalize_range(start: i64, stop: i64, len: usize) -> Option<(usize, usize)> {
+    let len = len as i64;
+    let start = if start < 0 {
+        (len + start).max(0)
+    } else {
+        start
+    };
+    let stop = if stop < 0 {
+        len + stop
+    } else {
+        stop.min(len - 1)
+    };
+    (start <= stop && start < len).then_some((start as usize, stop as usize))
+}
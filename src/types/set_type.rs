@@ -1,84 +1,246 @@
 use std::collections::HashSet;
 
-use crate::store::store::{
-    StoreVal, global_store, read_lock, read_unlock, write_lock, write_unlock,
-};
+use crate::store::store::{Lookup, StoreTxn, StoreVal, global_store};
+use crate::types::{WRONG_TYPE, random_picks};
+
+/// The set operation applied across several keys.
+#[derive(Clone, Copy)]
+pub enum SetOp {
+    Diff,
+    Inter,
+    Union,
+}
 
 pub struct SetType;
 
 impl SetType {
     /// Add members to the set stored at `key`.
     /// Returns the number of new elements added.
-    pub fn sadd(key: &str, values: &[String]) -> i64 {
-        write_lock();
-        let store = global_store();
-        let mut set: HashSet<String> = store
-            .get(&key.to_string())
-            .unwrap()
-            .get_set()
-            .unwrap()
-            .to_owned();
-        let mut count = 0;
-
-        for value in values {
-            if set.insert(value.clone()) {
-                count += 1;
-            }
-        }
+    pub fn sadd(key: &[u8], values: &[Vec<u8>]) -> Result<i64, String> {
+        let mut txn = global_store().lock([key]);
+        Self::set_or_insert(&mut txn, key, |set| Self::insert_all(set, values))
+    }
 
-        store.set(key.to_string(), StoreVal::Set(set), None);
-        write_unlock();
-        count
+    /// Removes members from the set stored at `key`.
+    /// Returns the number of members that were removed.
+    pub fn srem(key: &[u8], values: &[Vec<u8>]) -> Result<i64, String> {
+        let result = Self::with_set_mut(&mut global_store().lock([key]), key, |set| {
+            values.iter().filter(|v| set.remove(*v)).count() as i64
+        });
+        result.map(|n| n.unwrap_or(0))
     }
 
     /// Returns the number of elements in the set stored at `key`.
-    pub fn scard(key: &str) -> i64 {
-        read_lock();
-        let store = global_store();
-        let set: HashSet<String> = store
-            .get(&key.to_string())
-            .unwrap()
-            .get_set()
-            .unwrap()
-            .to_owned();
-        read_unlock();
-        set.len() as i64
+    pub fn scard(key: &[u8]) -> Result<i64, String> {
+        let result = Self::with_set(&global_store().read([key]), key, |set| set.len() as i64);
+        result.map(|n| n.unwrap_or(0))
+    }
+
+    pub fn smembers(key: &[u8]) -> Result<Vec<Vec<u8>>, String> {
+        let result = Self::with_set(&global_store().read([key]), key, |set| {
+            set.iter().cloned().collect()
+        });
+        result.map(Option::unwrap_or_default)
+    }
+
+    /// Returns true if `value` is a member of the set stored at `key`.
+    pub fn sismember(key: &[u8], value: &[u8]) -> Result<bool, String> {
+        let result = Self::with_set(&global_store().read([key]), key, |set| set.contains(value));
+        result.map(|found| found.unwrap_or(false))
+    }
+
+    /// Returns, for every value, whether it is a member of the set stored at `key`.
+    pub fn smismember(key: &[u8], values: &[Vec<u8>]) -> Result<Vec<bool>, String> {
+        let result = Self::with_set(&global_store().read([key]), key, |set| {
+            values.iter().map(|v| set.contains(v)).collect()
+        });
+        result.map(|found| found.unwrap_or_else(|| vec![false; values.len()]))
+    }
+
+    /// Removes and returns up to `count` random members.
+    pub fn spop(key: &[u8], count: usize) -> Result<Vec<Vec<u8>>, String> {
+        let result = Self::with_set_mut(&mut global_store().lock([key]), key, |set| {
+            let popped = Self::sample_distinct(set, count);
+            for v in &popped {
+                set.remove(v);
+            }
+            popped
+        });
+        result.map(Option::unwrap_or_default)
+    }
+
+    /// Returns random members without removing them.
+    /// A positive `count` returns distinct members, a negative one may repeat them.
+    pub fn srandmember(key: &[u8], count: i64) -> Result<Vec<Vec<u8>>, String> {
+        let result = Self::with_set(
+            &global_store().read([key]),
+            key,
+            |set| -> Result<Vec<Vec<u8>>, String> {
+                if count >= 0 {
+                    return Ok(Self::sample_distinct(set, count as usize));
+                }
+                let members: Vec<&Vec<u8>> = set.iter().collect();
+                Ok(random_picks(members.len(), count.unsigned_abs())?
+                    .map(|i| members[i].clone())
+                    .collect())
+            },
+        )?;
+        Ok(result.transpose()?.unwrap_or_default())
+    }
+
+    /// Moves `member` from the set at `source` to the set at `destination`.
+    /// Returns false if the member was not in the source set.
+    pub fn smove(source: &[u8], destination: &[u8], member: &[u8]) -> Result<bool, String> {
+        let mut txn = global_store().lock([source, destination]);
+        Self::move_member(&mut txn, source, destination, member)
     }
 
     /// Returns the difference between the first set and all subsequent sets.
-    pub fn sdiff(keys: &[String]) -> Vec<String> {
-        read_lock();
-        let store = global_store();
-        if keys.is_empty() {
-            read_unlock();
-            return vec![];
-        }
+    pub fn sdiff(keys: &[Vec<u8>]) -> Result<Vec<Vec<u8>>, String> {
+        Self::combine_read(keys, SetOp::Diff)
+    }
+
+    pub fn sinter(keys: &[Vec<u8>]) -> Result<Vec<Vec<u8>>, String> {
+        Self::combine_read(keys, SetOp::Inter)
+    }
 
-        let base_set: HashSet<String> = store.get(&keys[0]).unwrap().get_set().unwrap().to_owned();
-        let mut result_set = base_set.clone();
+    pub fn sunion(keys: &[Vec<u8>]) -> Result<Vec<Vec<u8>>, String> {
+        Self::combine_read(keys, SetOp::Union)
+    }
+
+    /// Returns the size of the intersection, capped at `limit` (0 means no limit).
+    pub fn sintercard(keys: &[Vec<u8>], limit: usize) -> Result<i64, String> {
+        let result = Self::combine(&global_store().read(keys), keys, SetOp::Inter);
+        let len = result?.len();
+        Ok(if limit == 0 { len } else { len.min(limit) } as i64)
+    }
+
+    /// Stores the result of `op` over `keys` in `destination`, replacing it.
+    /// Returns the size of the resulting set.
+    pub fn store(destination: &[u8], keys: &[Vec<u8>], op: SetOp) -> Result<i64, String> {
+        let mut txn = global_store().lock(keys.iter().map(Vec::as_slice).chain([destination]));
+        Self::combine(&txn, keys, op).map(|set| {
+            let len = set.len() as i64;
+            if set.is_empty() {
+                txn.delete(destination);
+            } else {
+                txn.set(destination.to_vec(), StoreVal::Set(set), None);
+            }
+            len
+        })
+    }
 
-        for key in &keys[1..] {
-            let other_set: HashSet<String> = store.get(key).unwrap().get_set().unwrap().to_owned();
-            for val in other_set {
-                result_set.remove(&val);
+    fn combine_read(keys: &[Vec<u8>], op: SetOp) -> Result<Vec<Vec<u8>>, String> {
+        let result = Self::combine(&global_store().read(keys), keys, op);
+        Ok(result?.into_iter().collect())
+    }
+
+    /// Applies `op` across the sets stored at `keys`. Missing keys are empty sets.
+    fn combine(
+        txn: &impl Lookup<Vec<u8>, StoreVal>,
+        keys: &[Vec<u8>],
+        op: SetOp,
+    ) -> Result<HashSet<Vec<u8>>, String> {
+        let Some((first, rest)) = keys.split_first() else {
+            return Ok(HashSet::new());
+        };
+        let mut result = Self::with_set(txn, first, |set| set.clone())?.unwrap_or_default();
+        for key in rest {
+            let applied = Self::with_set(txn, key, |other| match op {
+                SetOp::Diff => result.retain(|v| !other.contains(v)),
+                SetOp::Inter => result.retain(|v| other.contains(v)),
+                SetOp::Union => result.extend(other.iter().cloned()),
+            })?;
+            if applied.is_none() && matches!(op, SetOp::Inter) {
+                result.clear();
             }
         }
+        Ok(result)
+    }
 
-        read_unlock();
-        result_set.into_iter().collect()
+    fn move_member(
+        txn: &mut StoreTxn,
+        source: &[u8],
+        destination: &[u8],
+        member: &[u8],
+    ) -> Result<bool, String> {
+        if source == destination {
+            return Ok(Self::with_set(txn, source, |set| set.contains(member))?.unwrap_or(false));
+        }
+        // Check the destination type before anything is removed from the source.
+        Self::with_set(txn, destination, |_| ())?;
+        let removed = Self::with_set_mut(txn, source, |set| set.remove(member))?.unwrap_or(false);
+        if removed {
+            Self::set_or_insert(txn, destination, |set| set.insert(member.to_vec()))?;
+        }
+        Ok(removed)
     }
 
-    /// Returns true if `value` is a member of the set stored at `key`.
-    pub fn sismember(key: &str, value: &str) -> bool {
-        read_lock();
-        let store = global_store();
-        let set: HashSet<String> = store
-            .get(&key.to_string())
-            .unwrap()
-            .get_set()
-            .unwrap()
-            .to_owned();
-        read_unlock();
-        set.contains(value)
+    fn insert_all(set: &mut HashSet<Vec<u8>>, values: &[Vec<u8>]) -> i64 {
+        values.iter().filter(|v| set.insert((*v).clone())).count() as i64
+    }
+
+    fn sample_distinct(set: &HashSet<Vec<u8>>, count: usize) -> Vec<Vec<u8>> {
+        if count >= set.len() {
+            return set.iter().cloned().collect();
+        }
+        fastrand::choose_multiple(set.iter(), count)
+            .into_iter()
+            .cloned()
+            .collect()
+    }
+
+    /// Runs `f` on the set stored at `key` without copying it.
+    /// Returns `None` if the key does not exist.
+    fn with_set<R>(
+        txn: &impl Lookup<Vec<u8>, StoreVal>,
+        key: &[u8],
+        f: impl FnOnce(&HashSet<Vec<u8>>) -> R,
+    ) -> Result<Option<R>, String> {
+        txn.with(key, |val| {
+            val.get_set().map(f).ok_or_else(|| WRONG_TYPE.to_string())
+        })
+        .transpose()
+    }
+
+    /// Runs `f` on the set stored at `key`, mutating it in place.
+    /// The key is deleted once the set becomes empty.
+    fn with_set_mut<R>(
+        txn: &mut StoreTxn,
+        key: &[u8],
+        f: impl FnOnce(&mut HashSet<Vec<u8>>) -> R,
+    ) -> Result<Option<R>, String> {
+        let result = txn.with_mut(key, |val| {
+            let set = val.get_set_mut().ok_or_else(|| WRONG_TYPE.to_string())?;
+            let r = f(set);
+            Ok::<_, String>((r, set.is_empty()))
+        });
+        match result.transpose()? {
+            Some((r, empty)) => {
+                if empty {
+                    txn.delete(key);
+                }
+                Ok(Some(r))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Runs `f` on the set stored at `key`, creating an empty one if it is missing.
+    /// `f` must leave at least one member in a set it creates.
+    fn set_or_insert<R>(
+        txn: &mut StoreTxn,
+        key: &[u8],
+        f: impl FnOnce(&mut HashSet<Vec<u8>>) -> R,
+    ) -> Result<R, String> {
+        txn.upsert(
+            key,
+            || StoreVal::Set(HashSet::new()),
+            |val| {
+                val.get_set_mut()
+                    .map(f)
+                    .ok_or_else(|| WRONG_TYPE.to_string())
+            },
+        )
     }
 }
@@ -0,0 +1,489 @@
+use crate::store::store::{Lookup, StoreTxn, StoreVal, current_unix_millis, global_store};
+use crate::store::stream::{
+    AutoClaim, ClaimOptions, ConsumerGroup, Fields, NewId, Stream, StreamId, Trim,
+};
+use crate::types::WRONG_TYPE;
+
+/// `MAXLEN`/`MINID` trimming as given to XADD and XTRIM.
+#[derive(Clone, Copy)]
+pub struct TrimOptions {
+    pub by: Trim,
+    /// The most entries to evict in one call; 0 means no limit.
+    pub limit: usize,
+}
+
+pub type Entry = (StreamId, Fields);
+
+/// An entry read through a consumer group; entries deleted since delivery have no fields.
+pub type GroupEntry = (StreamId, Option<Fields>);
+
+/// The entries read from each stream key by XREAD or XREADGROUP.
+pub type StreamReply<E> = Vec<(Vec<u8>, Vec<E>)>;
+
+const KEY_REQUIRED: &str = "ERR The XGROUP subcommand requires the key to exist. \
+Note that for CREATE you may want to use the MKSTREAM option to create an empty stream automatically.";
+
+/// The summary form of XPENDING.
+pub struct PendingSummary {
+    pub count: usize,
+    /// The lowest and highest pending IDs, if anything is pending.
+    pub range: Option<(StreamId, StreamId)>,
+    /// Consumers with at least one pending entry, and how many they have.
+    pub consumers: Vec<(String, usize)>,
+}
+
+/// A pending entry as listed by the extended form of XPENDING.
+pub struct PendingInfo {
+    pub id: StreamId,
+    pub consumer: String,
+    /// Milliseconds since the last delivery.
+    pub idle: u64,
+    pub delivery_count: u64,
+}
+
+pub struct StreamInfo {
+    pub length: usize,
+    pub groups: usize,
+    pub last_id: StreamId,
+    pub max_deleted_id: StreamId,
+    pub entries_added: u64,
+    pub first: Option<Entry>,
+    pub last: Option<Entry>,
+}
+
+pub struct GroupInfo {
+    pub name: String,
+    pub consumers: usize,
+    pub pending: usize,
+    pub last_delivered: StreamId,
+}
+
+pub struct ConsumerInfo {
+    pub name: String,
+    pub pending: usize,
+    /// Milliseconds since the consumer was last seen.
+    pub idle: u64,
+    /// Milliseconds since the consumer last read or claimed an entry, if it ever did.
+    pub inactive: Option<u64>,
+}
+
+pub struct StreamType;
+
+impl StreamType {
+    /// Appends an entry to the stream at `key`, creating the stream unless `no_mkstream`
+    /// is set, then applies `trim`. Returns the new entry ID, or `None` if the stream
+    /// did not exist and `no_mkstream` was set.
+    pub fn xadd(
+        key: &[u8],
+        id: NewId,
+        fields: Fields,
+        no_mkstream: bool,
+        trim: Option<TrimOptions>,
+    ) -> Result<Option<StreamId>, String> {
+        if let NewId::Explicit(StreamId::MIN) = id {
+            return Err("ERR The ID specified in XADD must be greater than 0-0".to_string());
+        }
+        let mut txn = global_store().lock([key]);
+        Self::with_stream(&txn, key, |_| ()).and_then(|exists| {
+            if exists.is_none() {
+                if no_mkstream {
+                    return Ok(None);
+                }
+                txn.set(key.to_vec(), StoreVal::Stream(Stream::new()), None);
+            }
+            Self::with_stream_mut(&mut txn, key, |stream| {
+                let id = stream.next_id(id, current_unix_millis()).ok_or_else(|| {
+                    "ERR The ID specified in XADD is equal or smaller than the target stream top item"
+                        .to_string()
+                })?;
+                stream.insert(id, fields);
+                if let Some(trim) = trim {
+                    stream.trim(trim.by, trim.limit);
+                }
+                Ok(id)
+            })?
+            .transpose()
+        })
+    }
+
+    pub fn xlen(key: &[u8]) -> Result<i64, String> {
+        let result = Self::with_stream(&global_store().read([key]), key, |stream| {
+            stream.len() as i64
+        });
+        result.map(|n| n.unwrap_or(0))
+    }
+
+    /// Returns up to `count` entries with IDs between `start` and `end` inclusive,
+    /// in descending order with `rev`.
+    pub fn xrange(
+        key: &[u8],
+        start: StreamId,
+        end: StreamId,
+        count: Option<usize>,
+        rev: bool,
+    ) -> Result<Vec<Entry>, String> {
+        let result = Self::with_stream(&global_store().read([key]), key, |stream| {
+            stream
+                .range(start, end, rev)
+                .take(count.unwrap_or(usize::MAX))
+                .map(|(id, fields)| (*id, fields.clone()))
+                .collect()
+        });
+        result.map(Option::unwrap_or_default)
+    }
+
+    /// Deletes entries by ID and returns the number actually deleted.
+    pub fn xdel(key: &[u8], ids: &[StreamId]) -> Result<i64, String> {
+        let result = Self::with_stream_mut(&mut global_store().lock([key]), key, |stream| {
+            ids.iter().filter(|id| stream.remove(**id)).count() as i64
+        });
+        result.map(|n| n.unwrap_or(0))
+    }
+
+    /// Trims the stream at `key` and returns the number of evicted entries.
+    pub fn xtrim(key: &[u8], trim: TrimOptions) -> Result<i64, String> {
+        let result = Self::with_stream_mut(&mut global_store().lock([key]), key, |stream| {
+            stream.trim(trim.by, trim.limit) as i64
+        });
+        result.map(|n| n.unwrap_or(0))
+    }
+
+    /// Reads up to `count` entries after the given ID from each stream.
+    /// Streams with no new entries are left out of the result.
+    pub fn xread(
+        streams: &[(Vec<u8>, StreamId)],
+        count: Option<usize>,
+    ) -> Result<StreamReply<Entry>, String> {
+        let view = global_store().read(streams.iter().map(|(key, _)| key));
+        streams
+            .iter()
+            .map(|(key, after)| {
+                let entries = Self::with_stream(&view, key, |stream| match after.next() {
+                    Some(start) => stream
+                        .range(start, StreamId::MAX, false)
+                        .take(count.unwrap_or(usize::MAX))
+                        .map(|(id, fields)| (*id, fields.clone()))
+                        .collect(),
+                    None => vec![],
+                })?;
+                Ok((key.clone(), entries.unwrap_or_default()))
+            })
+            .filter(|r| !matches!(r, Ok((_, entries)) if entries.is_empty()))
+            .collect()
+    }
+
+    /// Creates a consumer group starting after `start`, or after the last entry if
+    /// `None`. With `mkstream` a missing stream is created empty.
+    pub fn xgroup_create(
+        key: &[u8],
+        group: &str,
+        start: Option<StreamId>,
+        mkstream: bool,
+    ) -> Result<(), String> {
+        let mut txn = global_store().lock([key]);
+        Self::with_stream(&txn, key, |_| ()).and_then(|exists| {
+            if exists.is_none() && mkstream {
+                txn.set(key.to_vec(), StoreVal::Stream(Stream::new()), None);
+            }
+            Self::with_existing(&mut txn, key, |stream| {
+                let start = start.unwrap_or(stream.last_id());
+                if stream.create_group(group, start) {
+                    Ok(())
+                } else {
+                    Err("BUSYGROUP Consumer Group name already exists".to_string())
+                }
+            })
+        })
+    }
+
+    pub fn xgroup_destroy(key: &[u8], group: &str) -> Result<bool, String> {
+        Self::with_existing(&mut global_store().lock([key]), key, |stream| {
+            Ok(stream.destroy_group(group))
+        })
+    }
+
+    /// Moves the group's cursor to `start`, or to the last entry if `None`.
+    pub fn xgroup_setid(key: &[u8], group: &str, start: Option<StreamId>) -> Result<(), String> {
+        Self::with_existing(&mut global_store().lock([key]), key, |stream| {
+            let start = start.unwrap_or(stream.last_id());
+            Self::existing_group(key, group, stream)?.last_delivered = start;
+            Ok(())
+        })
+    }
+
+    /// Returns true if the consumer was created, false if it already existed.
+    pub fn xgroup_createconsumer(key: &[u8], group: &str, consumer: &str) -> Result<bool, String> {
+        Self::with_existing(&mut global_store().lock([key]), key, |stream| {
+            let g = Self::existing_group(key, group, stream)?;
+            let created = !g.consumers.contains_key(consumer);
+            g.touch_consumer(consumer, current_unix_millis());
+            Ok(created)
+        })
+    }
+
+    /// Deletes a consumer and returns the number of pending entries it had.
+    pub fn xgroup_delconsumer(key: &[u8], group: &str, consumer: &str) -> Result<i64, String> {
+        Self::with_existing(&mut global_store().lock([key]), key, |stream| {
+            let g = Self::existing_group(key, group, stream)?;
+            Ok(g.remove_consumer(consumer).unwrap_or(0) as i64)
+        })
+    }
+
+    /// Reads from each stream on behalf of `consumer` in `group`. A `None` ID (`>`)
+    /// delivers new entries; an explicit ID re-reads the consumer's pending entries
+    /// after it. Streams without new entries are left out of the result.
+    pub fn xreadgroup(
+        group: &str,
+        consumer: &str,
+        streams: &[(Vec<u8>, Option<StreamId>)],
+        count: Option<usize>,
+        no_ack: bool,
+    ) -> Result<StreamReply<GroupEntry>, String> {
+        let count = count.unwrap_or(usize::MAX);
+        let now = current_unix_millis();
+        let mut txn = global_store().lock(streams.iter().map(|(key, _)| key));
+        Self::read_groups(&mut txn, group, consumer, streams, count, no_ack, now)
+    }
+
+    /// Acknowledges pending entries and returns the number acknowledged.
+    pub fn xack(key: &[u8], group: &str, ids: &[StreamId]) -> Result<i64, String> {
+        let result = Self::with_stream_mut(&mut global_store().lock([key]), key, |stream| {
+            stream
+                .group_mut(group)
+                .map(|g| ids.iter().filter(|id| g.ack(**id)).count() as i64)
+        });
+        result.map(|n| n.flatten().unwrap_or(0))
+    }
+
+    pub fn xpending(key: &[u8], group: &str) -> Result<PendingSummary, String> {
+        Self::with_group(&global_store().read([key]), key, group, |g| {
+            PendingSummary {
+                count: g.pending.len(),
+                range: g
+                    .pending
+                    .first_key_value()
+                    .zip(g.pending.last_key_value())
+                    .map(|((first, _), (last, _))| (*first, *last)),
+                consumers: g
+                    .consumers
+                    .iter()
+                    .filter(|(_, c)| !c.pending.is_empty())
+                    .map(|(name, c)| (name.clone(), c.pending.len()))
+                    .collect(),
+            }
+        })
+    }
+
+    /// Lists up to `count` pending entries between `start` and `end`, optionally only
+    /// those of `consumer` and those idle for at least `min_idle` milliseconds.
+    pub fn xpending_range(
+        key: &[u8],
+        group: &str,
+        start: StreamId,
+        end: StreamId,
+        count: usize,
+        consumer: Option<&str>,
+        min_idle: u64,
+    ) -> Result<Vec<PendingInfo>, String> {
+        let now = current_unix_millis();
+        Self::with_group(&global_store().read([key]), key, group, |g| {
+            if start > end {
+                return vec![];
+            }
+            g.pending
+                .range(start..=end)
+                .filter(|(_, p)| consumer.is_none_or(|c| p.consumer == c))
+                .map(|(id, p)| PendingInfo {
+                    id: *id,
+                    consumer: p.consumer.clone(),
+                    idle: now.saturating_sub(p.delivered_at),
+                    delivery_count: p.delivery_count,
+                })
+                .filter(|p| p.idle >= min_idle)
+                .take(count)
+                .collect()
+        })
+    }
+
+    /// Transfers pending entries to `consumer` and returns the claimed entries.
+    pub fn xclaim(
+        key: &[u8],
+        group: &str,
+        consumer: &str,
+        ids: &[StreamId],
+        opts: &ClaimOptions,
+    ) -> Result<Vec<Entry>, String> {
+        let now = current_unix_millis();
+        Self::with_stream_mut(&mut global_store().lock([key]), key, |stream| {
+            stream.claim(group, consumer, ids, opts, now)
+        })
+        .and_then(|claimed| claimed.flatten().ok_or_else(|| no_group(key, group)))
+    }
+
+    /// Claims up to `count` idle pending entries, scanning from `start`.
+    pub fn xautoclaim(
+        key: &[u8],
+        group: &str,
+        consumer: &str,
+        start: StreamId,
+        count: usize,
+        opts: &ClaimOptions,
+    ) -> Result<AutoClaim, String> {
+        let now = current_unix_millis();
+        Self::with_stream_mut(&mut global_store().lock([key]), key, |stream| {
+            stream.auto_claim(group, consumer, start, count, opts, now)
+        })
+        .and_then(|claimed| claimed.flatten().ok_or_else(|| no_group(key, group)))
+    }
+
+    pub fn xinfo_stream(key: &[u8]) -> Result<StreamInfo, String> {
+        let clone_entry = |(id, fields): (&StreamId, &Fields)| (*id, fields.clone());
+        let result = Self::with_stream(&global_store().read([key]), key, |stream| StreamInfo {
+            length: stream.len(),
+            groups: stream.groups().count(),
+            last_id: stream.last_id(),
+            max_deleted_id: stream.max_deleted_id(),
+            entries_added: stream.entries_added(),
+            first: stream.first_entry().map(clone_entry),
+            last: stream.last_entry().map(clone_entry),
+        });
+        result.and_then(|info| info.ok_or_else(|| "ERR no such key".to_string()))
+    }
+
+    pub fn xinfo_groups(key: &[u8]) -> Result<Vec<GroupInfo>, String> {
+        let result = Self::with_stream(&global_store().read([key]), key, |stream| {
+            stream
+                .groups()
+                .map(|(name, g)| GroupInfo {
+                    name: name.clone(),
+                    consumers: g.consumers.len(),
+                    pending: g.pending.len(),
+                    last_delivered: g.last_delivered,
+                })
+                .collect()
+        });
+        result.and_then(|groups| groups.ok_or_else(|| "ERR no such key".to_string()))
+    }
+
+    pub fn xinfo_consumers(key: &[u8], group: &str) -> Result<Vec<ConsumerInfo>, String> {
+        let now = current_unix_millis();
+        Self::with_group(&global_store().read([key]), key, group, |g| {
+            g.consumers
+                .iter()
+                .map(|(name, c)| ConsumerInfo {
+                    name: name.clone(),
+                    pending: c.pending.len(),
+                    idle: now.saturating_sub(c.seen_time),
+                    inactive: c.active_time.map(|t| now.saturating_sub(t)),
+                })
+                .collect()
+        })
+    }
+
+    /// Checks every group exists, then reads from each stream.
+    fn read_groups(
+        txn: &mut StoreTxn,
+        group: &str,
+        consumer: &str,
+        streams: &[(Vec<u8>, Option<StreamId>)],
+        count: usize,
+        no_ack: bool,
+        now: u64,
+    ) -> Result<StreamReply<GroupEntry>, String> {
+        for (key, _) in streams {
+            if Self::with_stream(txn, key, |stream| stream.group(group).is_some())? != Some(true) {
+                let key = String::from_utf8_lossy(key);
+                return Err(format!(
+                    "NOGROUP No such key '{key}' or consumer group '{group}' in XREADGROUP with GROUP option"
+                ));
+            }
+        }
+        let mut result = Vec::new();
+        for (key, from) in streams {
+            let entries = Self::with_stream_mut(txn, key, |stream| match from {
+                None => stream
+                    .read_group(group, consumer, count, no_ack, now)
+                    .map(|entries| {
+                        entries
+                            .into_iter()
+                            .map(|(id, fields)| (id, Some(fields)))
+                            .collect()
+                    }),
+                Some(after) => stream.read_group_history(group, consumer, *after, count, now),
+            })?
+            .flatten()
+            .unwrap_or_default();
+            if from.is_some() || !entries.is_empty() {
+                result.push((key.clone(), entries));
+            }
+        }
+        Ok(result)
+    }
+
+    /// Runs `f` on the stream at `key`, failing as XGROUP does if it does not exist.
+    fn with_existing<R>(
+        txn: &mut StoreTxn,
+        key: &[u8],
+        f: impl FnOnce(&mut Stream) -> Result<R, String>,
+    ) -> Result<R, String> {
+        Self::with_stream_mut(txn, key, f)?.unwrap_or_else(|| Err(KEY_REQUIRED.to_string()))
+    }
+
+    fn existing_group<'a>(
+        key: &[u8],
+        group: &str,
+        stream: &'a mut Stream,
+    ) -> Result<&'a mut ConsumerGroup, String> {
+        stream.group_mut(group).ok_or_else(|| {
+            let key = String::from_utf8_lossy(key);
+            format!("NOGROUP No such consumer group '{group}' for key name '{key}'")
+        })
+    }
+
+    /// Runs `f` on consumer group `group` of the stream at `key`.
+    fn with_group<R>(
+        txn: &impl Lookup<Vec<u8>, StoreVal>,
+        key: &[u8],
+        group: &str,
+        f: impl FnOnce(&ConsumerGroup) -> R,
+    ) -> Result<R, String> {
+        Self::with_stream(txn, key, |stream| stream.group(group).map(f))?
+            .flatten()
+            .ok_or_else(|| no_group(key, group))
+    }
+
+    /// Runs `f` on the stream stored at `key` without copying it.
+    /// Returns `None` if the key does not exist.
+    fn with_stream<R>(
+        txn: &impl Lookup<Vec<u8>, StoreVal>,
+        key: &[u8],
+        f: impl FnOnce(&Stream) -> R,
+    ) -> Result<Option<R>, String> {
+        txn.with(key, |val| {
+            val.get_stream()
+                .map(f)
+                .ok_or_else(|| WRONG_TYPE.to_string())
+        })
+        .transpose()
+    }
+
+    /// Runs `f` on the stream stored at `key`, mutating it in place.
+    /// Unlike other collections, an empty stream is kept.
+    fn with_stream_mut<R>(
+        txn: &mut StoreTxn,
+        key: &[u8],
+        f: impl FnOnce(&mut Stream) -> R,
+    ) -> Result<Option<R>, String> {
+        txn.with_mut(key, |val| {
+            val.get_stream_mut()
+                .map(f)
+                .ok_or_else(|| WRONG_TYPE.to_string())
+        })
+        .transpose()
+    }
+}
+
+fn no_group(key: &[u8], group: &str) -> String {
+    let key = String::from_utf8_lossy(key);
+    format!("NOGROUP No such key '{key}' or consumer group '{group}'")
+}
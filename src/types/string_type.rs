@@ -1,227 +1,324 @@
 use std::collections::HashMap;
 
-use crate::store::store::{
-    StoreVal, global_store, read_lock, read_unlock, write_lock, write_unlock,
-};
+use crate::config::PROTO_MAX_BULK_LEN;
+use crate::store::store::{Lookup, StoreTxn, StoreVal, current_unix_millis, global_store};
+use crate::types::WRONG_TYPE;
+
+pub(crate) const NOT_INTEGER: &str = "ERR value is not an integer or out of range";
+pub(crate) const NOT_FLOAT: &str = "ERR value is not a valid float";
+pub(crate) const OVERFLOW: &str = "ERR increment or decrement would overflow";
+
+/// Condition under which `SET` is allowed to write the key.
+#[derive(Clone, Copy, PartialEq)]
+pub enum SetCondition {
+    Always,
+    IfNotExists,
+    IfExists,
+}
 
 #[derive(Default)]
 pub struct StringType;
 
 impl StringType {
-    pub fn append(key: &str, value: &str) {
-        write_lock();
-        let store = global_store();
-        let current = store.get(&key.to_string());
-        let new_value = match current {
-            Some(v) => v.get_str().unwrap().to_string() + value,
-            None => value.to_string(),
-        };
-        store.set(key.to_string(), StoreVal::Str(new_value), None);
-        write_unlock();
+    /// Appends `value` to the string at `key` and returns the new length.
+    pub fn append(key: &[u8], value: &[u8]) -> Result<usize, String> {
+        let mut txn = global_store().lock([key]);
+        Self::bytes_or_insert(&mut txn, key, |bytes| {
+            bytes.extend_from_slice(value);
+            bytes.len()
+        })
     }
 
-    pub fn decr(key: &str) -> Result<(), String> {
+    pub fn decr(key: &[u8]) -> Result<i64, String> {
         Self::decr_by(key, 1)
     }
 
-    pub fn decr_by(key: &str, value: i64) -> Result<(), String> {
-        write_lock();
-        let store = global_store();
+    pub fn decr_by(key: &[u8], value: i64) -> Result<i64, String> {
+        let value = value.checked_neg().ok_or_else(|| OVERFLOW.to_string())?;
+        Self::incr_by(key, value)
+    }
 
-        let new_val = if let Some(StoreVal::Str(s)) = store.get(&key.to_string()) {
-            s.parse::<i64>()
-                .map_err(|_| "ERR value is not an integer or out of range".to_string())?
-                - value
-        } else {
-            -value
-        };
+    /// Returns the string at `key`, or `None` if the key does not exist.
+    pub fn get(key: &[u8]) -> Result<Option<Vec<u8>>, String> {
+        let view = global_store().read([key]);
+        Self::with_bytes(&view, key, Vec::clone)
+    }
 
-        store.set(key.to_string(), StoreVal::Str(new_val.to_string()), None);
-        write_unlock();
-        Ok(())
-    }
-
-    pub fn get(key: &str) -> Result<String, String> {
-        read_lock();
-        let store = global_store();
-        let result = store
-            .get(&key.to_string())
-            .ok_or_else(|| "ERR key not found".to_string())?;
-        read_unlock();
-        Ok(result.get_str().unwrap().to_owned())
-    }
-
-    pub fn get_del(key: &str) -> Result<String, String> {
-        write_lock();
-        let store = global_store();
-        let val = store
-            .get(&key.to_string())
-            .ok_or_else(|| "ERR key not found".to_string())?;
-        store.delete(&key.to_string());
-        write_unlock();
-        Ok(val.get_str().unwrap().to_owned())
-    }
-
-    pub fn get_ex(key: &str, exp_seconds: u64) -> Result<String, String> {
-        write_lock();
-        let store = global_store();
-        let val = store
-            .get(&key.to_string())
-            .ok_or_else(|| "ERR key not found".to_string())?;
-        store.set(key.to_string(), val.clone(), Some(exp_seconds));
-        write_unlock();
-        Ok(val.get_str().unwrap().to_owned())
-    }
-
-    pub fn get_range(key: &str, start: isize, end: isize) -> Result<String, String> {
-        read_lock();
-        let store = global_store();
-        let store_val = store
-            .get(&key.to_string())
-            .ok_or_else(|| "ERR key not found".to_string())?;
-        let val = store_val.get_str().unwrap();
-        let len = val.len() as isize;
-        if len == 0 {
-            read_unlock();
-            return Ok(String::new());
+    pub fn get_del(key: &[u8]) -> Result<Option<Vec<u8>>, String> {
+        let mut txn = global_store().lock([key]);
+        let value = Self::with_bytes(&txn, key, Vec::clone)?;
+        if value.is_some() {
+            txn.delete(key);
         }
-        let start_idx = ((start % len + len) % len) as usize;
-        let end_idx = ((end % len + len) % len) as usize;
-        if start_idx > end_idx {
-            read_unlock();
-            return Err("ERR start index greater than end index".to_string());
+        Ok(value)
+    }
+
+    /// Returns the string at `key` and makes it expire `ttl_ms` milliseconds
+    /// from now. `None` removes any existing expiration.
+    pub fn get_ex(key: &[u8], ttl_ms: Option<u64>) -> Result<Option<Vec<u8>>, String> {
+        let mut txn = global_store().lock([key]);
+        let value = Self::with_bytes(&txn, key, Vec::clone)?;
+        if value.is_some() {
+            txn.expire_at(
+                key,
+                ttl_ms.map(|ms| current_unix_millis().saturating_add(ms)),
+            );
         }
-        let slice = &val[start_idx..=end_idx];
-        read_unlock();
-        Ok(slice.to_string())
+        Ok(value)
     }
 
-    pub fn get_set(key: &str, value: &str) -> Result<String, String> {
-        write_lock();
-        let store = global_store();
-        let old_val = store
-            .get(&key.to_string())
-            .ok_or_else(|| "ERR key not found".to_string())?;
-        store.set(key.to_string(), StoreVal::Str(value.to_string()), None);
-        write_unlock();
-        Ok(old_val.get_str().unwrap().to_owned())
+    /// Returns the substring between the inclusive byte offsets `start` and `end`.
+    /// Negative offsets count from the end of the string.
+    pub fn get_range(key: &[u8], start: i64, end: i64) -> Result<Vec<u8>, String> {
+        let view = global_store().read([key]);
+        let range = Self::with_bytes(&view, key, |val| {
+            let len = val.len() as i64;
+            let start = if start < 0 {
+                (len + start).max(0)
+            } else {
+                start
+            };
+            let end = if end < 0 { len + end } else { end.min(len - 1) };
+            if len == 0 || start > end || start >= len {
+                return Vec::new();
+            }
+            val[start as usize..=end as usize].to_vec()
+        })?;
+        Ok(range.unwrap_or_default())
     }
 
-    pub fn incr(key: &str) -> Result<(), String> {
+    /// Sets `key` to `value` and returns the previous string, if any.
+    pub fn get_set(key: &[u8], value: &[u8]) -> Result<Option<Vec<u8>>, String> {
+        let mut txn = global_store().lock([key]);
+        let old = Self::with_bytes(&txn, key, Vec::clone)?;
+        txn.set(key.to_vec(), StoreVal::Str(value.into()), None);
+        Ok(old)
+    }
+
+    pub fn incr(key: &[u8]) -> Result<i64, String> {
         Self::incr_by(key, 1)
     }
 
-    pub fn incr_by(key: &str, value: i64) -> Result<(), String> {
-        write_lock();
-        let store = global_store();
-        let current = store.get(&key.to_string());
-        let new_val = match current {
-            Some(v) => {
-                let int_val = v
-                    .get_str()
-                    .unwrap()
-                    .parse::<i64>()
-                    .map_err(|_| "ERR value is not an integer or out of range".to_string())?;
-                int_val + value
-            }
-            None => value,
+    /// Increments the integer at `key` by `value` and returns the result.
+    pub fn incr_by(key: &[u8], value: i64) -> Result<i64, String> {
+        let mut txn = global_store().lock([key]);
+        let current = match Self::with_bytes(&txn, key, |s| parse::<i64>(s))? {
+            Some(n) => n.ok_or_else(|| NOT_INTEGER.to_string())?,
+            None => 0,
         };
-        store.set(key.to_string(), StoreVal::Str(new_val.to_string()), None);
-        write_unlock();
-        Ok(())
-    }
-
-    pub fn set(key: &str, value: &str) {
-        write_lock();
-        let store = global_store();
-        store.set(key.to_string(), StoreVal::Str(value.to_string()), None);
-        write_unlock();
-    }
-
-    pub fn set_ex(key: &str, value: &str, seconds: u64) {
-        write_lock();
-        let store = global_store();
-        store.set(
-            key.to_string(),
-            StoreVal::Str(value.to_string()),
-            Some(seconds),
-        );
-        write_unlock();
-    }
-
-    pub fn str_len(key: &str) -> Result<usize, String> {
-        read_lock();
-        let store = global_store();
-        let val = store
-            .get(&key.to_string())
-            .ok_or_else(|| "ERR key not found".to_string())?;
-        read_unlock();
-        Ok(val.get_str().unwrap().len())
-    }
-
-    pub fn mget(keys: &[String]) -> Vec<String> {
-        read_lock();
-        let store = global_store();
-        let values = keys
-            .iter()
-            .map(|k| store.get(k).unwrap().get_str().unwrap().to_owned())
-            .collect();
-        read_unlock();
-        values
-    }
-
-    pub fn mset(kv_pairs: &HashMap<String, String>) {
-        write_lock();
-        let store = global_store();
-        for (k, v) in kv_pairs {
-            store.set(k.clone(), StoreVal::Str(v.clone()), None);
+        let n = current
+            .checked_add(value)
+            .ok_or_else(|| OVERFLOW.to_string())?;
+        Self::bytes_or_insert(&mut txn, key, |bytes| *bytes = n.to_string().into_bytes())?;
+        Ok(n)
+    }
+
+    /// Increments the float at `key` by `value` and returns the result.
+    pub fn incr_by_float(key: &[u8], value: f64) -> Result<String, String> {
+        let mut txn = global_store().lock([key]);
+        let current = match Self::with_bytes(&txn, key, |s| parse::<f64>(s))? {
+            Some(n) => n.ok_or_else(|| NOT_FLOAT.to_string())?,
+            None => 0.0,
+        };
+        let n = current + value;
+        if !n.is_finite() {
+            return Err("ERR increment would produce NaN or Infinity".to_string());
         }
-        write_unlock();
-    }
-
-    pub fn lcs(key1: &str, key2: &str, command: Option<&str>) -> Result<String, String> {
-        read_lock();
-        let store = global_store();
-        let val1 = store
-            .get(&key1.to_string())
-            .ok_or_else(|| "ERR key not found".to_string())?;
-        let val2 = store
-            .get(&key2.to_string())
-            .ok_or_else(|| "ERR key not found".to_string())?;
-        read_unlock();
-
-        let (lcs_str, lcs_len) = find_lcs(&val1.get_str().unwrap(), &val2.get_str().unwrap());
-        if let Some(cmd) = command {
-            if cmd.eq_ignore_ascii_case("LEN") {
-                return Ok(lcs_len.to_string());
+        let n = format_float(n);
+        Self::bytes_or_insert(&mut txn, key, |bytes| *bytes = n.clone().into_bytes())?;
+        Ok(n)
+    }
+
+    pub fn set(key: &[u8], value: &[u8]) {
+        let mut txn = global_store().lock([key]);
+        txn.set(key.to_vec(), StoreVal::Str(value.into()), None);
+    }
+
+    /// Sets `key` to `value` if `condition` holds.
+    /// Returns whether the key was written, along with the previous string value.
+    /// Keys of other types are overwritten unless `get` asks for the old value.
+    pub fn set_with(
+        key: &[u8],
+        value: &[u8],
+        condition: SetCondition,
+        get: bool,
+        ttl_ms: Option<u64>,
+    ) -> Result<(bool, Option<Vec<u8>>), String> {
+        let mut txn = global_store().lock([key]);
+        // `Some(None)` is a key holding another type.
+        let current = txn.with(key, |val| val.get_str().cloned());
+        let exists = current.is_some();
+        let old = match current {
+            Some(Some(s)) => Ok(Some(s)),
+            Some(None) if get => Err(WRONG_TYPE.to_string()),
+            _ => Ok(None),
+        };
+        old.map(|old| {
+            let allowed = match condition {
+                SetCondition::Always => true,
+                SetCondition::IfNotExists => !exists,
+                SetCondition::IfExists => exists,
+            };
+            if allowed {
+                txn.set(key.to_vec(), StoreVal::Str(value.into()), ttl_ms);
             }
+            (allowed, old)
+        })
+    }
+
+    pub fn set_ex(key: &[u8], value: &[u8], ttl_ms: u64) {
+        let mut txn = global_store().lock([key]);
+        txn.set(key.to_vec(), StoreVal::Str(value.into()), Some(ttl_ms));
+    }
+
+    /// Overwrites part of the string at `key` starting at byte `offset`,
+    /// padding with zero bytes if needed. Returns the new length.
+    pub fn set_range(key: &[u8], offset: usize, value: &[u8]) -> Result<usize, String> {
+        let mut txn = global_store().lock([key]);
+        if value.is_empty() {
+            return Ok(Self::with_bytes(&txn, key, Vec::len)?.unwrap_or(0));
         }
-        Ok(lcs_str)
+        Self::bytes_or_insert(&mut txn, key, |bytes| {
+            let end = offset + value.len();
+            if bytes.len() < end {
+                bytes.resize(end, 0);
+            }
+            bytes[offset..end].copy_from_slice(value);
+            bytes.len()
+        })
+    }
+
+    pub fn str_len(key: &[u8]) -> Result<usize, String> {
+        let view = global_store().read([key]);
+        Ok(Self::with_bytes(&view, key, Vec::len)?.unwrap_or(0))
+    }
+
+    /// Returns the string for every key, with `None` for missing keys
+    /// and keys holding other types.
+    pub fn mget(keys: &[Vec<u8>]) -> Vec<Option<Vec<u8>>> {
+        let view = global_store().read(keys);
+        keys.iter()
+            .map(|k| view.with(k, |v| v.get_str().cloned()).flatten())
+            .collect()
+    }
+
+    pub fn mset(kv_pairs: &HashMap<Vec<u8>, Vec<u8>>) {
+        let mut txn = global_store().lock(kv_pairs.keys());
+        for (k, v) in kv_pairs {
+            txn.set(k.clone(), StoreVal::Str(v.clone()), None);
+        }
+    }
+
+    /// Returns the longest common subsequence of the strings at `key1` and `key2`.
+    /// Missing keys are treated as empty.
+    pub fn lcs(key1: &[u8], key2: &[u8]) -> Result<Vec<u8>, String> {
+        let (val1, val2) = Self::lcs_operands(key1, key2)?;
+        find_lcs(&val1, &val2)
+    }
+
+    /// Returns the length of the longest common subsequence of the strings at
+    /// `key1` and `key2`, as LCS LEN does.
+    pub fn lcs_len(key1: &[u8], key2: &[u8]) -> Result<usize, String> {
+        let (val1, val2) = Self::lcs_operands(key1, key2)?;
+        Ok(find_lcs_len(&val1, &val2))
+    }
+
+    /// Copies the strings at `key1` and `key2` so the shards aren't held during LCS.
+    fn lcs_operands(key1: &[u8], key2: &[u8]) -> Result<(Vec<u8>, Vec<u8>), String> {
+        let view = global_store().read([key1, key2]);
+        let val1 = Self::with_bytes(&view, key1, Vec::clone)?.unwrap_or_default();
+        let val2 = Self::with_bytes(&view, key2, Vec::clone)?.unwrap_or_default();
+        Ok((val1, val2))
+    }
+
+    /// Runs `f` on the string stored at `key` without copying it.
+    /// Returns `None` if the key does not exist.
+    fn with_bytes<R>(
+        txn: &impl Lookup<Vec<u8>, StoreVal>,
+        key: &[u8],
+        f: impl FnOnce(&Vec<u8>) -> R,
+    ) -> Result<Option<R>, String> {
+        txn.with(key, |val| {
+            val.get_str().map(f).ok_or_else(|| WRONG_TYPE.to_string())
+        })
+        .transpose()
+    }
+
+    /// Runs `f` on the string stored at `key`, changing it in place and creating
+    /// an empty one if it is missing. An existing key keeps its expiration.
+    fn bytes_or_insert<R>(
+        txn: &mut StoreTxn,
+        key: &[u8],
+        f: impl FnOnce(&mut Vec<u8>) -> R,
+    ) -> Result<R, String> {
+        txn.upsert(
+            key,
+            || StoreVal::Str(Vec::new()),
+            |val| {
+                val.get_str_mut()
+                    .map(f)
+                    .ok_or_else(|| WRONG_TYPE.to_string())
+            },
+        )
     }
 }
 
-/// Find Longest Common Subsequence
-fn find_lcs(a: &str, b: &str) -> (String, usize) {
-    let m = a.len();
-    let n = b.len();
-    let mut prev = vec![0; n + 1];
-    let mut curr = vec![0; n + 1];
+/// Parses a string value as a number, or `None` if it isn't one.
+fn parse<T: std::str::FromStr>(s: &[u8]) -> Option<T> {
+    std::str::from_utf8(s).ok()?.parse().ok()
+}
+
+/// Formats a float the way Redis replies to INCRBYFLOAT.
+pub fn format_float(n: f64) -> String {
+    if n.fract() == 0.0 && n.abs() < 1e17 {
+        format!("{}", n as i64)
+    } else {
+        format!("{}", n)
+    }
+}
 
-    let a_bytes = a.as_bytes();
-    let b_bytes = b.as_bytes();
+/// Length of the longest common subsequence, keeping only two rows of the table.
+fn find_lcs_len(a_bytes: &[u8], b_bytes: &[u8]) -> usize {
+    let n = b_bytes.len();
+    let mut prev = vec![0usize; n + 1];
+    let mut row = vec![0usize; n + 1];
+    for &a in a_bytes {
+        for (j, &b) in b_bytes.iter().enumerate() {
+            row[j + 1] = if a == b {
+                prev[j] + 1
+            } else {
+                prev[j + 1].max(row[j])
+            };
+        }
+        std::mem::swap(&mut prev, &mut row);
+    }
+    prev[n]
+}
 
+/// Find Longest Common Subsequence
+fn find_lcs(a_bytes: &[u8], b_bytes: &[u8]) -> Result<Vec<u8>, String> {
+    let m = a_bytes.len();
+    let n = b_bytes.len();
+
+    // Full table so the subsequence can be reconstructed. Like Redis, refuse
+    // to allocate more than proto-max-bulk-len for it.
+    let cells = (m + 1)
+        .checked_mul(n + 1)
+        .filter(|cells| (*cells as u64).saturating_mul(4) <= PROTO_MAX_BULK_LEN.get())
+        .ok_or("ERR Insufficient memory, transient memory for LCS exceeds proto-max-bulk-len")?;
+    let mut dp = vec![0u32; cells];
+    let at = |i: usize, j: usize| i * (n + 1) + j;
     for i in 0..m {
         for j in 0..n {
-            if a_bytes[i] == b_bytes[j] {
-                curr[j + 1] = prev[j] + 1;
+            dp[at(i + 1, j + 1)] = if a_bytes[i] == b_bytes[j] {
+                dp[at(i, j)] + 1
             } else {
-                curr[j + 1] = prev[j + 1].max(curr[j]);
-            }
+                dp[at(i, j + 1)].max(dp[at(i + 1, j)])
+            };
         }
-        std::mem::swap(&mut prev, &mut curr);
     }
 
-    let mut lcs_len = prev[n];
+    let mut lcs_len = dp[at(m, n)] as usize;
     let mut lcs = vec![0u8; lcs_len];
     let mut i = m;
     let mut j = n;
@@ -231,11 +328,11 @@ fn find_lcs(a: &str, b: &str) -> (String, usize) {
             lcs[lcs_len] = a_bytes[i - 1];
             i -= 1;
             j -= 1;
-        } else if prev[j] > prev[j - 1] {
+        } else if dp[at(i - 1, j)] >= dp[at(i, j - 1)] {
             i -= 1;
         } else {
             j -= 1;
         }
     }
-    (String::from_utf8(lcs).unwrap(), prev[n])
+    Ok(lcs)
 }
@@ -0,0 +1,396 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::store::store::{Lookup, StoreTxn, StoreVal, global_store};
+use crate::store::zset::{LexBound, ScoreBound, ZIter, ZSet};
+use crate::types::set_type::SetOp;
+use crate::types::{WRONG_TYPE, normalize_range, random_picks};
+
+/// Flags accepted by ZADD.
+#[derive(Default)]
+pub struct ZAddOptions {
+    pub nx: bool,
+    pub xx: bool,
+    pub gt: bool,
+    pub lt: bool,
+    pub ch: bool,
+    pub incr: bool,
+}
+
+/// How ZRANGE selects members.
+pub enum RangeBy {
+    Rank(i64, i64),
+    Score(ScoreBound, ScoreBound),
+    Lex(LexBound, LexBound),
+}
+
+pub struct RangeSpec {
+    pub by: RangeBy,
+    pub rev: bool,
+    /// `LIMIT offset count`; a negative count means "all remaining".
+    pub limit: Option<(i64, i64)>,
+}
+
+/// How scores of the same member are combined by ZUNIONSTORE/ZINTERSTORE.
+#[derive(Clone, Copy)]
+pub enum Aggregate {
+    Sum,
+    Min,
+    Max,
+}
+
+impl Aggregate {
+    fn apply(self, a: f64, b: f64) -> f64 {
+        match self {
+            Aggregate::Sum => {
+                let sum = a + b;
+                // inf + -inf is NaN; treat it as 0 like Redis does.
+                if sum.is_nan() { 0.0 } else { sum }
+            }
+            Aggregate::Min => a.min(b),
+            Aggregate::Max => a.max(b),
+        }
+    }
+}
+
+pub struct ZSetType;
+
+impl ZSetType {
+    /// Adds or updates members of the sorted set at `key`.
+    /// Returns the number of added (or, with CH, changed) members, and with INCR
+    /// the new score of the single member (`None` if the update was skipped).
+    pub fn zadd(
+        key: &[u8],
+        opts: &ZAddOptions,
+        pairs: &[(f64, Vec<u8>)],
+    ) -> Result<(i64, Option<f64>), String> {
+        Self::upsert(&mut global_store().lock([key]), key, |zset| {
+            Self::add_pairs(zset, opts, pairs)
+        })
+    }
+
+    /// Increments the score of `member` by `incr` and returns the new score.
+    pub fn zincrby(key: &[u8], incr: f64, member: &[u8]) -> Result<f64, String> {
+        let opts = ZAddOptions {
+            incr: true,
+            ..Default::default()
+        };
+        let (_, score) = Self::zadd(key, &opts, &[(incr, member.to_vec())])?;
+        Ok(score.unwrap_or(incr))
+    }
+
+    pub fn zrem(key: &[u8], members: &[Vec<u8>]) -> Result<i64, String> {
+        let result = Self::with_zset_mut(&mut global_store().lock([key]), key, |zset| {
+            members.iter().filter(|m| zset.remove(m).is_some()).count() as i64
+        });
+        result.map(|n| n.unwrap_or(0))
+    }
+
+    pub fn zscore(key: &[u8], member: &[u8]) -> Result<Option<f64>, String> {
+        let result = Self::with_zset(&global_store().read([key]), key, |zset| zset.score(member));
+        result.map(Option::flatten)
+    }
+
+    pub fn zmscore(key: &[u8], members: &[Vec<u8>]) -> Result<Vec<Option<f64>>, String> {
+        let result = Self::with_zset(&global_store().read([key]), key, |zset| {
+            members.iter().map(|m| zset.score(m)).collect()
+        });
+        result.map(|scores| scores.unwrap_or_else(|| vec![None; members.len()]))
+    }
+
+    pub fn zcard(key: &[u8]) -> Result<i64, String> {
+        let result = Self::with_zset(&global_store().read([key]), key, |zset| zset.len() as i64);
+        result.map(|n| n.unwrap_or(0))
+    }
+
+    /// Returns the number of members with a score between `min` and `max`.
+    pub fn zcount(key: &[u8], min: ScoreBound, max: ScoreBound) -> Result<i64, String> {
+        let result = Self::with_zset(&global_store().read([key]), key, |zset| {
+            zset.range_by_score(min, max, false).count()
+        });
+        result.map(|n| n.unwrap_or(0) as i64)
+    }
+
+    /// Returns the rank of `member` (from the highest score with `rev`) and its score.
+    pub fn zrank(key: &[u8], member: &[u8], rev: bool) -> Result<Option<(i64, f64)>, String> {
+        let result = Self::with_zset(&global_store().read([key]), key, |zset| {
+            let rank = zset.rank(member)?;
+            let rank = if rev { zset.len() - 1 - rank } else { rank };
+            Some((rank as i64, zset.score(member)?))
+        });
+        result.map(Option::flatten)
+    }
+
+    pub fn zrange(key: &[u8], spec: &RangeSpec) -> Result<Vec<(Vec<u8>, f64)>, String> {
+        let result = Self::with_zset(&global_store().read([key]), key, |zset| {
+            Self::collect_range(zset, spec)
+        });
+        result.map(Option::unwrap_or_default)
+    }
+
+    /// Stores the selected range of `source` in `destination`, replacing it.
+    /// Returns the number of members stored.
+    pub fn zrangestore(destination: &[u8], source: &[u8], spec: &RangeSpec) -> Result<i64, String> {
+        let mut txn = global_store().lock([source, destination]);
+        Self::with_zset(&txn, source, |zset| Self::collect_range(zset, spec)).map(|range| {
+            let mut zset = ZSet::new();
+            for (member, score) in range.unwrap_or_default() {
+                zset.insert(member, score);
+            }
+            Self::replace(&mut txn, destination, zset)
+        })
+    }
+
+    /// Removes and returns up to `count` members with the lowest
+    /// (or, with `max`, highest) scores.
+    pub fn zpop(key: &[u8], count: usize, max: bool) -> Result<Vec<(Vec<u8>, f64)>, String> {
+        let result = Self::with_zset_mut(&mut global_store().lock([key]), key, |zset| {
+            (0..count)
+                .map_while(|_| {
+                    if max {
+                        zset.pop_last()
+                    } else {
+                        zset.pop_first()
+                    }
+                })
+                .collect()
+        });
+        result.map(Option::unwrap_or_default)
+    }
+
+    /// Returns random members with their scores.
+    /// A positive `count` returns distinct members, a negative one may repeat them.
+    pub fn zrandmember(key: &[u8], count: i64) -> Result<Vec<(Vec<u8>, f64)>, String> {
+        let view = global_store().read([key]);
+        let picked = Self::with_zset(&view, key, |zset| -> Result<Vec<(Vec<u8>, f64)>, String> {
+            let ranks: Vec<usize> = if count < 0 {
+                random_picks(zset.len(), count.unsigned_abs())?.collect()
+            } else if count as usize >= zset.len() {
+                return Ok(zset.iter().map(|(m, s)| (m.clone(), s)).collect());
+            } else {
+                // Like Redis, draw random ranks until enough distinct ones are found,
+                // so only the picked members are visited.
+                let mut ranks = HashSet::with_capacity(count as usize);
+                while ranks.len() < count as usize {
+                    ranks.insert(fastrand::usize(..zset.len()));
+                }
+                ranks.into_iter().collect()
+            };
+            Ok(ranks
+                .into_iter()
+                .filter_map(|rank| zset.get_by_rank(rank))
+                .map(|(m, s)| (m.clone(), s))
+                .collect())
+        })?;
+        Ok(picked.transpose()?.unwrap_or_default())
+    }
+
+    /// Combines the sorted sets (or plain sets, scored 1) at `keys` with `op`
+    /// and stores the result in `destination`. Returns its cardinality.
+    pub fn zstore(
+        destination: &[u8],
+        keys: &[Vec<u8>],
+        weights: &[f64],
+        aggregate: Aggregate,
+        op: SetOp,
+    ) -> Result<i64, String> {
+        let mut txn = global_store().lock(keys.iter().map(Vec::as_slice).chain([destination]));
+        Self::combine(&txn, keys, weights, aggregate, op).map(|combined| {
+            let mut zset = ZSet::new();
+            for (member, score) in combined {
+                zset.insert(member, score);
+            }
+            Self::replace(&mut txn, destination, zset)
+        })
+    }
+
+    fn add_pairs(
+        zset: &mut ZSet,
+        opts: &ZAddOptions,
+        pairs: &[(f64, Vec<u8>)],
+    ) -> Result<(i64, Option<f64>), String> {
+        let mut added = 0;
+        let mut changed = 0;
+        let mut last = None;
+        for (score, member) in pairs {
+            last = None;
+            match zset.score(member) {
+                Some(old) => {
+                    if opts.nx {
+                        continue;
+                    }
+                    let new = if opts.incr { old + score } else { *score };
+                    if new.is_nan() {
+                        return Err("ERR resulting score is not a number (NaN)".to_string());
+                    }
+                    if (opts.gt && new <= old) || (opts.lt && new >= old) {
+                        continue;
+                    }
+                    if new != old {
+                        zset.insert(member.clone(), new);
+                        changed += 1;
+                    }
+                    last = Some(new);
+                }
+                None => {
+                    if opts.xx {
+                        continue;
+                    }
+                    zset.insert(member.clone(), *score);
+                    added += 1;
+                    last = Some(*score);
+                }
+            }
+        }
+        let count = if opts.ch { added + changed } else { added };
+        Ok((count, last))
+    }
+
+    fn collect_range(zset: &ZSet, spec: &RangeSpec) -> Vec<(Vec<u8>, f64)> {
+        let iter: ZIter<'_> = match &spec.by {
+            RangeBy::Rank(start, stop) => match normalize_range(*start, *stop, zset.len()) {
+                Some((s, e)) if spec.rev => {
+                    let last = zset.len() - 1;
+                    Box::new(zset.iter_ranks(last - e, last - s + 1).rev())
+                }
+                Some((s, e)) => Box::new(zset.iter_ranks(s, e + 1)),
+                None => return vec![],
+            },
+            RangeBy::Score(min, max) => zset.range_by_score(*min, *max, spec.rev),
+            RangeBy::Lex(min, max) => zset.range_by_lex(min.clone(), max.clone(), spec.rev),
+        };
+        let (offset, count) = spec.limit.unwrap_or((0, -1));
+        if offset < 0 {
+            return vec![];
+        }
+        let count = if count < 0 {
+            usize::MAX
+        } else {
+            count as usize
+        };
+        iter.skip(offset as usize)
+            .take(count)
+            .map(|(m, s)| (m.clone(), s))
+            .collect()
+    }
+
+    /// Reads every source as (member, score) pairs and combines them.
+    fn combine(
+        txn: &impl Lookup<Vec<u8>, StoreVal>,
+        keys: &[Vec<u8>],
+        weights: &[f64],
+        aggregate: Aggregate,
+        op: SetOp,
+    ) -> Result<HashMap<Vec<u8>, f64>, String> {
+        let mut sources = Vec::with_capacity(keys.len());
+        for (i, key) in keys.iter().enumerate() {
+            let weight = weights.get(i).copied().unwrap_or(1.0);
+            let members = txn
+                .with(key, |val| {
+                    let weighted = |score: f64| {
+                        let w = score * weight;
+                        if w.is_nan() { 0.0 } else { w }
+                    };
+                    match val {
+                        StoreVal::ZSet(z) => {
+                            Ok(z.iter().map(|(m, s)| (m.clone(), weighted(s))).collect())
+                        }
+                        StoreVal::Set(s) => {
+                            Ok(s.iter().map(|m| (m.clone(), weighted(1.0))).collect())
+                        }
+                        _ => Err(WRONG_TYPE.to_string()),
+                    }
+                })
+                .transpose()?
+                .unwrap_or_else(HashMap::new);
+            sources.push(members);
+        }
+
+        let mut sources = sources.into_iter();
+        let mut result: HashMap<Vec<u8>, f64> = sources.next().unwrap_or_default();
+        for other in sources {
+            match op {
+                SetOp::Union => {
+                    for (member, score) in other {
+                        result
+                            .entry(member)
+                            .and_modify(|s| *s = aggregate.apply(*s, score))
+                            .or_insert(score);
+                    }
+                }
+                SetOp::Inter => {
+                    result.retain(|m, _| other.contains_key(m));
+                    for (member, score) in result.iter_mut() {
+                        *score = aggregate.apply(*score, other[member]);
+                    }
+                }
+                SetOp::Diff => result.retain(|m, _| !other.contains_key(m)),
+            }
+        }
+        Ok(result)
+    }
+
+    /// Stores `zset` under `key`, deleting the key if it is empty.
+    /// Returns the number of members stored.
+    fn replace(txn: &mut StoreTxn, key: &[u8], zset: ZSet) -> i64 {
+        let len = zset.len() as i64;
+        if zset.is_empty() {
+            txn.delete(key);
+        } else {
+            txn.set(key.to_vec(), StoreVal::ZSet(zset), None);
+        }
+        len
+    }
+
+    /// Runs `f` on the sorted set at `key`, creating an empty one if it is missing.
+    fn upsert<R>(
+        txn: &mut StoreTxn,
+        key: &[u8],
+        f: impl FnOnce(&mut ZSet) -> Result<R, String>,
+    ) -> Result<R, String> {
+        if Self::with_zset(txn, key, |_| ())?.is_some() {
+            return Self::with_zset_mut(txn, key, f)?.expect("key checked above");
+        }
+        let mut zset = ZSet::new();
+        let result = f(&mut zset)?;
+        if !zset.is_empty() {
+            txn.set(key.to_vec(), StoreVal::ZSet(zset), None);
+        }
+        Ok(result)
+    }
+
+    /// Runs `f` on the sorted set stored at `key` without copying it.
+    /// Returns `None` if the key does not exist.
+    fn with_zset<R>(
+        txn: &impl Lookup<Vec<u8>, StoreVal>,
+        key: &[u8],
+        f: impl FnOnce(&ZSet) -> R,
+    ) -> Result<Option<R>, String> {
+        txn.with(key, |val| {
+            val.get_zset().map(f).ok_or_else(|| WRONG_TYPE.to_string())
+        })
+        .transpose()
+    }
+
+    /// Runs `f` on the sorted set stored at `key`, mutating it in place.
+    /// The key is deleted once the set becomes empty.
+    fn with_zset_mut<R>(
+        txn: &mut StoreTxn,
+        key: &[u8],
+        f: impl FnOnce(&mut ZSet) -> R,
+    ) -> Result<Option<R>, String> {
+        let result = txn.with_mut(key, |val| {
+            let zset = val.get_zset_mut().ok_or_else(|| WRONG_TYPE.to_string())?;
+            let r = f(zset);
+            Ok::<_, String>((r, zset.is_empty()))
+        });
+        match result.transpose()? {
+            Some((r, empty)) => {
+                if empty {
+                    txn.delete(key);
+                }
+                Ok(Some(r))
+            }
+            None => Ok(None),
+        }
+    }
+}